@@ -1,5 +1,5 @@
 use autoeq_backend::audio::{AudioDevice, AudioConfig};
-use autoeq_backend::{CancellationState, SharedAudioState};
+use autoeq_backend::{CancellationToken, SharedAudioState};
 use tauri::State;
 
 #[tauri::command]
@@ -53,7 +53,7 @@ pub fn run() {
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(CancellationState::new())
+        .manage(CancellationToken::new())
         .manage(SharedAudioState::default())
         .invoke_handler(tauri::generate_handler![
             exit_app,
@@ -21,12 +21,18 @@ export interface AudioPlayerConfig {
 }
 
 export interface FilterParam {
+  type?: BiquadFilterType; // "peaking" when unset
   frequency: number;
   q: number;
   gain: number;
   enabled: boolean;
 }
 
+// Filter types whose gain changes the response
+function hasGain(type: BiquadFilterType): boolean {
+  return type === "peaking" || type === "lowshelf" || type === "highshelf";
+}
+
 export interface AudioPlayerCallbacks {
   onPlay?: () => void;
   onStop?: () => void;
@@ -786,18 +792,24 @@ export class AudioPlayer {
 
     // Create new filters from parameters
     this.currentFilterParams.forEach((param) => {
-      if (param.enabled && Math.abs(param.gain) > 0.1) {
+      const type = param.type ?? "peaking";
+      const gainful = hasGain(type);
+      if (param.enabled && (!gainful || Math.abs(param.gain) > 0.1)) {
         // Only create filter if enabled and gain is significant
         const filter = this.audioContext!.createBiquadFilter();
-        filter.type = "peaking";
+        filter.type = type;
         filter.frequency.value = param.frequency;
-        filter.Q.value = param.q;
+        // Web Audio takes the resonance of low and high passes in dB
+        filter.Q.value =
+          type === "lowpass" || type === "highpass"
+            ? 20 * Math.log10(param.q)
+            : param.q;
         filter.gain.value = param.gain;
         this.eqFilters.push(filter);
         activeFilterCount++;
 
         // Track maximum positive gain
-        if (param.gain > maxPositiveGain) {
+        if (gainful && param.gain > maxPositiveGain) {
           maxPositiveGain = param.gain;
         }
       }
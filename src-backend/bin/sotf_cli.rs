@@ -0,0 +1,939 @@
+use autoeq_backend::batch::{self, BatchOptions};
+use autoeq_backend::export::{self, ExportFormat, FilterParam};
+use autoeq_backend::optim::{
+    self, JobManager, OptimizationPhase, ProgressCallback, ProgressUpdate,
+};
+use autoeq_backend::sweep::{self, SweepOptions};
+use autoeq_backend::{
+    Algorithm, CancellationToken, CapturedMeasurement, CurveAggregate, DecodedFilter,
+    DeviceProfile, FilterType, FrequencyGrid, Loss, MeasurementSpread, OptimizationParams,
+    PeqModel, PinnedFilter, ResidualMetrics, RunManifest, WeightingCurve, device_profiles,
+};
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(name = "sotf")]
+#[command(about = "Optimise speaker and headphone EQ without a GUI", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Optimise an EQ for a measurement and write the filters
+    Optimize {
+        /// Measurement CSV (frequency, SPL)
+        curve: PathBuf,
+
+        #[command(flatten)]
+        knobs: OptimizationArgs,
+
+        /// Output file (default: next to the curve, named <curve>-eq.<ext>)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Export format: camilladsp, parametric-eq or rew (default: the device's, else parametric-eq)
+        #[arg(short = 'F', long)]
+        format: Option<ExportFormat>,
+
+        /// Write a run manifest that can be replayed to reproduce the result
+        #[arg(short, long)]
+        manifest: Option<PathBuf>,
+
+        /// Do not print progress updates
+        #[arg(short, long, default_value_t = false)]
+        quiet: bool,
+
+        /// Stop the optimization after this many seconds and keep the best filters so far
+        #[arg(long, value_name = "SECONDS", value_parser = parse_time_budget)]
+        time_budget: Option<Duration>,
+
+        /// Start from this filter (repeatable), e.g. the current EQ, in format "TYPE:FREQ:Q:GAIN"
+        #[arg(long = "initial-filter", value_name = "TYPE:FREQ:Q:GAIN")]
+        initial_filters: Vec<String>,
+
+        /// Keep this filter as is and optimize around it (repeatable), in format "TYPE:FREQ:Q:GAIN"
+        #[arg(long = "fixed-filter", value_name = "TYPE:FREQ:Q:GAIN")]
+        fixed_filters: Vec<String>,
+
+        /// Another measurement of the same device (repeatable), e.g. a reseat, with an optional weight
+        #[arg(long = "measurement", value_name = "PATH[:WEIGHT]")]
+        measurements: Vec<String>,
+    },
+
+    /// Re-run an optimization from its run manifest and check the filters match
+    Replay {
+        /// Run manifest written by `optimize --manifest`
+        manifest: PathBuf,
+    },
+
+    /// Compute the preference score of a measurement without optimising
+    Score {
+        /// Measurement CSV (frequency, SPL)
+        curve: PathBuf,
+
+        #[command(flatten)]
+        knobs: OptimizationArgs,
+    },
+
+    /// Try a range of filter counts and PEQ models and recommend the smallest good one
+    Sweep {
+        /// Measurement CSV (frequency, SPL)
+        curve: PathBuf,
+
+        #[command(flatten)]
+        knobs: OptimizationArgs,
+
+        /// Smallest number of filters tried
+        #[arg(long, default_value = "3")]
+        min_filters: usize,
+
+        /// Largest number of filters tried
+        #[arg(long, default_value = "10")]
+        max_filters: usize,
+
+        /// PEQ models tried, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "pk,hp-pk")]
+        models: Vec<PeqModel>,
+
+        /// Recommend the smallest configuration whose loss is within this fraction of the best
+        #[arg(long, default_value = "0.05")]
+        tolerance: f64,
+    },
+
+    /// Optimise every measurement of a directory or manifest with the same settings
+    Batch {
+        /// Directory of measurement CSVs, or a YAML/JSON manifest listing them
+        input: PathBuf,
+
+        #[command(flatten)]
+        knobs: OptimizationArgs,
+
+        /// Directory receiving one export per input and summary.csv
+        #[arg(short, long, default_value = "batch-output")]
+        output_dir: PathBuf,
+
+        /// Number of optimizations running in parallel
+        #[arg(short = 'j', long, default_value = "2")]
+        workers: usize,
+
+        /// Export format: camilladsp, parametric-eq or rew (default: the device's, else parametric-eq)
+        #[arg(short = 'F', long)]
+        format: Option<ExportFormat>,
+
+        /// Stop each optimization after this many seconds
+        #[arg(long, value_name = "SECONDS", value_parser = parse_time_budget)]
+        time_budget: Option<Duration>,
+    },
+
+    /// Write a list of filters in one of the export formats
+    Export {
+        /// Filters in format "TYPE:FREQ:Q:GAIN" (e.g., "PK:1000:1.5:-3.0")
+        #[arg(
+            short,
+            long = "filter",
+            value_name = "TYPE:FREQ:Q:GAIN",
+            required = true
+        )]
+        filters: Vec<String>,
+
+        /// Export format: camilladsp, parametric-eq or rew
+        #[arg(short = 'F', long, default_value = "parametric-eq")]
+        format: ExportFormat,
+
+        /// Sample rate in Hz
+        #[arg(short = 'r', long, default_value = "48000")]
+        sample_rate: u32,
+
+        /// Output file (prints to stdout if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// List the hardware EQ profiles usable with --device
+    Devices,
+}
+
+/// Optimization knobs, mirroring `OptimizationParams`
+///
+/// Every knob is optional; missing values fall back to `OptimizationParams::default()`.
+#[derive(Args, Clone)]
+struct OptimizationArgs {
+    /// Target CSV (default: built from the loss and the measurement)
+    #[arg(short, long)]
+    target: Option<PathBuf>,
+
+    /// Number of filters
+    #[arg(short = 'n', long)]
+    num_filters: Option<usize>,
+
+    /// Sample rate in Hz
+    #[arg(short = 'r', long)]
+    sample_rate: Option<f64>,
+
+    /// Minimum absolute gain of a filter in dB
+    #[arg(long)]
+    min_db: Option<f64>,
+
+    /// Maximum absolute gain of a filter in dB
+    #[arg(long)]
+    max_db: Option<f64>,
+
+    /// Minimum Q
+    #[arg(long)]
+    min_q: Option<f64>,
+
+    /// Maximum Q
+    #[arg(long)]
+    max_q: Option<f64>,
+
+    /// Minimum frequency in Hz
+    #[arg(long)]
+    min_freq: Option<f64>,
+
+    /// Maximum frequency in Hz
+    #[arg(long)]
+    max_freq: Option<f64>,
+
+    /// Curve name used for speaker data (e.g., "Listening Window")
+    #[arg(long)]
+    curve_name: Option<String>,
+
+    /// Optimization algorithm (e.g., "autoeq:de", "nlopt:cobyla", "mh:pso")
+    #[arg(short, long)]
+    algo: Option<Algorithm>,
+
+    /// Population size
+    #[arg(long)]
+    population: Option<usize>,
+
+    /// Maximum number of evaluations
+    #[arg(long)]
+    maxeval: Option<usize>,
+
+    /// Run a local refinement after the global optimization
+    #[arg(long, default_value_t = false)]
+    refine: bool,
+
+    /// Local algorithm used for refinement
+    #[arg(long)]
+    local_algo: Option<String>,
+
+    /// Minimum spacing between filters in octaves
+    #[arg(long)]
+    min_spacing_oct: Option<f64>,
+
+    /// Weight of the spacing penalty
+    #[arg(long)]
+    spacing_weight: Option<f64>,
+
+    /// Disable smoothing of the input curve
+    #[arg(long, default_value_t = false)]
+    no_smooth: bool,
+
+    /// Smoothing in 1/N octave
+    #[arg(long)]
+    smooth_n: Option<usize>,
+
+    /// Loss function (speaker-flat, speaker-score, headphone-flat, headphone-score)
+    #[arg(short, long)]
+    loss: Option<Loss>,
+
+    /// PEQ model (pk, hp-pk, hp-pk-lp, free-pk-free, free)
+    #[arg(short, long)]
+    peq_model: Option<PeqModel>,
+
+    /// DE strategy
+    #[arg(long)]
+    strategy: Option<String>,
+
+    /// DE mutation factor
+    #[arg(long)]
+    de_f: Option<f64>,
+
+    /// DE recombination probability
+    #[arg(long)]
+    de_cr: Option<f64>,
+
+    /// Relative tolerance
+    #[arg(long)]
+    tolerance: Option<f64>,
+
+    /// Absolute tolerance
+    #[arg(long)]
+    atolerance: Option<f64>,
+
+    /// Largest boost of the combined EQ in dB (default: unlimited)
+    #[arg(long)]
+    max_boost: Option<f64>,
+
+    /// Flat losses: weight of the error by frequency, e.g. "8000:1,12000:0.3"
+    #[arg(long, value_name = "FREQ:WEIGHT,...")]
+    weighting: Option<WeightingCurve>,
+
+    /// Flat losses: extra cost of boosts over cuts (0 = symmetric)
+    #[arg(long)]
+    boost_penalty: Option<f64>,
+
+    /// Start the optimizer from the greedy peak-picking fit
+    #[arg(long, default_value_t = false)]
+    greedy_init: bool,
+
+    /// Hardware EQ the filters must fit, e.g. graphic-10 (see `devices`)
+    #[arg(long, value_name = "ID")]
+    device: Option<DeviceProfile>,
+
+    /// How several measurements are combined (mean, median, worst-case)
+    #[arg(long)]
+    aggregate: Option<CurveAggregate>,
+
+    /// Density of the analysis grid, e.g. 48 for room modes (default: 20)
+    #[arg(long)]
+    points_per_octave: Option<usize>,
+
+    /// First frequency of the analysis grid in Hz (default: 20)
+    #[arg(long)]
+    grid_min_freq: Option<f64>,
+
+    /// Last frequency of the analysis grid in Hz (default: 20000)
+    #[arg(long)]
+    grid_max_freq: Option<f64>,
+
+    /// Random seed (default: random, recorded in the run manifest)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of threads used to evaluate the objective (0 = all cores)
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Evaluate the objective on a single thread
+    #[arg(long, default_value_t = false)]
+    no_parallel: bool,
+}
+
+impl OptimizationArgs {
+    fn into_params(self, curve: Option<&Path>) -> OptimizationParams {
+        let defaults = OptimizationParams::default();
+        OptimizationParams {
+            num_filters: self.num_filters.unwrap_or(defaults.num_filters),
+            curve_path: curve.map(|p| p.to_string_lossy().to_string()),
+            target_path: self.target.map(|p| p.to_string_lossy().to_string()),
+            sample_rate: self.sample_rate.unwrap_or(defaults.sample_rate),
+            max_db: self.max_db.unwrap_or(defaults.max_db),
+            min_db: self.min_db.unwrap_or(defaults.min_db),
+            max_q: self.max_q.unwrap_or(defaults.max_q),
+            min_q: self.min_q.unwrap_or(defaults.min_q),
+            min_freq: self.min_freq.unwrap_or(defaults.min_freq),
+            max_freq: self.max_freq.unwrap_or(defaults.max_freq),
+            curve_name: self.curve_name.unwrap_or(defaults.curve_name),
+            algo: self.algo.unwrap_or(defaults.algo),
+            population: self.population.unwrap_or(defaults.population),
+            maxeval: self.maxeval.unwrap_or(defaults.maxeval),
+            refine: self.refine,
+            local_algo: self.local_algo.unwrap_or(defaults.local_algo),
+            min_spacing_oct: self.min_spacing_oct.unwrap_or(defaults.min_spacing_oct),
+            spacing_weight: self.spacing_weight.unwrap_or(defaults.spacing_weight),
+            smooth: !self.no_smooth,
+            smooth_n: self.smooth_n.unwrap_or(defaults.smooth_n),
+            loss: self.loss.unwrap_or(defaults.loss),
+            peq_model: self.peq_model.unwrap_or(defaults.peq_model),
+            strategy: self.strategy.or(defaults.strategy),
+            de_f: self.de_f.or(defaults.de_f),
+            de_cr: self.de_cr.or(defaults.de_cr),
+            tolerance: self.tolerance.or(defaults.tolerance),
+            atolerance: self.atolerance.or(defaults.atolerance),
+            seed: self.seed,
+            parallel_threads: self.threads.unwrap_or(defaults.parallel_threads),
+            no_parallel: self.no_parallel,
+            max_boost_db: self.max_boost,
+            device_profile: self.device,
+            greedy_init: self.greedy_init,
+            loss_weighting: self.weighting,
+            boost_penalty: self.boost_penalty.unwrap_or(defaults.boost_penalty),
+            curve_aggregate: self.aggregate.unwrap_or(defaults.curve_aggregate),
+            frequency_grid: FrequencyGrid {
+                points_per_octave: self
+                    .points_per_octave
+                    .unwrap_or(defaults.frequency_grid.points_per_octave),
+                min_freq: self
+                    .grid_min_freq
+                    .unwrap_or(defaults.frequency_grid.min_freq),
+                max_freq: self
+                    .grid_max_freq
+                    .unwrap_or(defaults.frequency_grid.max_freq),
+            },
+            ..defaults
+        }
+    }
+}
+
+/// Prints optimization progress on a single terminal line
+struct TerminalProgressCallback {
+    started: Instant,
+    last_print: Mutex<Option<Instant>>,
+}
+
+impl TerminalProgressCallback {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_print: Mutex::new(None),
+        }
+    }
+}
+
+impl ProgressCallback for TerminalProgressCallback {
+    fn on_progress(&self, update: ProgressUpdate) -> bool {
+        // Throttle output to a few lines per second
+        if let Ok(mut last) = self.last_print.lock() {
+            if last.is_some_and(|t| t.elapsed() < Duration::from_millis(200)) {
+                return true;
+            }
+            *last = Some(Instant::now());
+        }
+        match update.evaluations {
+            Some(evaluations) => eprint!(
+                "\r  evals {:>6} | best fitness {:>12.6} | {:>6.1}s   ",
+                evaluations,
+                update.fitness,
+                self.started.elapsed().as_secs_f64()
+            ),
+            None => eprint!(
+                "\r  iter {:>6} | fitness {:>12.6} | convergence {:>8.4} | {:>6.1}s   ",
+                update.iteration,
+                update.fitness,
+                update.convergence,
+                self.started.elapsed().as_secs_f64()
+            ),
+        }
+        true
+    }
+
+    fn on_phase(&self, phase: OptimizationPhase) {
+        let label = match phase {
+            OptimizationPhase::Loading => "Loading curves",
+            OptimizationPhase::TargetBuilt => "Target built",
+            OptimizationPhase::Optimizing => "Optimizing",
+            OptimizationPhase::Scoring => "Scoring result",
+        };
+        // Scoring follows the progress line, finish it first
+        if phase == OptimizationPhase::Scoring {
+            eprintln!();
+        }
+        eprintln!("  {}...", label);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Commands::Optimize {
+            curve,
+            knobs,
+            output,
+            format,
+            manifest,
+            quiet,
+            time_budget,
+            initial_filters,
+            fixed_filters,
+            measurements,
+        } => {
+            match parse_filters(&initial_filters)
+                .and_then(|initial| Ok((initial, parse_filters(&fixed_filters)?)))
+                .and_then(|(initial, fixed)| {
+                    Ok((initial, fixed, load_measurements(&curve, &measurements)?))
+                }) {
+                Ok((initial, fixed, measurements)) => {
+                    let mut params = knobs.into_params(Some(&curve));
+                    let format = resolve_format(format, &params);
+                    let output = output.unwrap_or_else(|| default_output_path(&curve, format));
+                    params.initial_filters = (!initial.is_empty()).then_some(initial);
+                    params.pinned_filters = fixed
+                        .into_iter()
+                        .map(|filter| PinnedFilter {
+                            filter,
+                            range: None,
+                        })
+                        .collect();
+                    params.captured_measurements = measurements;
+                    optimize(params, output, format, manifest, quiet, time_budget).await
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Commands::Replay { manifest } => replay(&manifest).await,
+        Commands::Score { curve, knobs } => score(knobs.into_params(Some(&curve))).await,
+        Commands::Sweep {
+            curve,
+            knobs,
+            min_filters,
+            max_filters,
+            models,
+            tolerance,
+        } => {
+            let options = SweepOptions {
+                min_filters,
+                max_filters,
+                peq_models: models,
+                tolerance,
+            };
+            run_sweep(knobs.into_params(Some(&curve)), options).await
+        }
+        Commands::Batch {
+            input,
+            knobs,
+            output_dir,
+            workers,
+            format,
+            time_budget,
+        } => {
+            let params = knobs.into_params(None);
+            let options = BatchOptions {
+                workers,
+                output_dir,
+                format: resolve_format(format, &params),
+                time_budget,
+            };
+            batch(&input, params, options).await
+        }
+        Commands::Export {
+            filters,
+            format,
+            sample_rate,
+            output,
+        } => export(&filters, format, sample_rate, output),
+        Commands::Devices => {
+            print_devices();
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn optimize(
+    params: OptimizationParams,
+    output: PathBuf,
+    format: ExportFormat,
+    manifest: Option<PathBuf>,
+    quiet: bool,
+    time_budget: Option<Duration>,
+) -> Result<(), String> {
+    println!("Optimizing {}", params.curve_path.as_deref().unwrap_or("?"));
+    println!("  Algorithm: {}", params.algo);
+    println!("  Loss: {}", params.loss);
+    println!("  Filters: {}", params.num_filters);
+    if let Some(filters) = &params.initial_filters {
+        println!("  Warm start: {} filters", filters.len());
+    }
+    if !params.pinned_filters.is_empty() {
+        println!("  Fixed filters: {}", params.pinned_filters.len());
+    }
+    if !params.captured_measurements.is_empty() {
+        println!(
+            "  Measurements: {} ({})",
+            params.captured_measurements.len(),
+            params.curve_aggregate
+        );
+    }
+    println!();
+
+    // Cancel the optimization on Ctrl+C or when the time budget runs out
+    let cancellation_token = match time_budget {
+        Some(budget) => CancellationToken::with_timeout(budget),
+        None => CancellationToken::new(),
+    };
+    let c = cancellation_token.clone();
+    ctrlc::set_handler(move || {
+        eprintln!("\n\nReceived Ctrl+C, cancelling optimization...");
+        c.cancel();
+    })
+    .map_err(|e| format!("Failed to set Ctrl+C handler: {}", e))?;
+
+    let progress_callback = Arc::new(TerminalProgressCallback::new());
+    let progress_callback_clone = Arc::clone(&progress_callback);
+    let sample_rate = params.sample_rate;
+
+    let result = if quiet {
+        optim::run_optimization_internal(
+            params,
+            Arc::new(SilentProgressCallback),
+            cancellation_token,
+        )
+        .await
+    } else {
+        optim::run_optimization_internal(params, progress_callback, cancellation_token).await
+    }
+    .map_err(|e| e.to_string())?;
+    eprintln!();
+
+    let decoded = result
+        .filters
+        .as_ref()
+        .ok_or_else(|| "Optimization returned no filters".to_string())?;
+    let filters = export::filters_for_export(decoded);
+
+    println!();
+    println!(
+        "Optimization finished in {:.1}s",
+        progress_callback_clone.started.elapsed().as_secs_f64()
+    );
+    if result.truncated {
+        println!("Time budget exceeded, these are the best filters found in time");
+    }
+    print_score("Preference score before", result.preference_score_before);
+    print_score("Preference score after", result.preference_score_after);
+    if let Some(objective) = result.objective_value {
+        println!("Final loss: {:.6}", objective);
+    }
+    if let Some(preamp) = result.preamp_db {
+        println!("Recommended preamp: {:.2} dB", preamp);
+    }
+    if let Some(metrics) = &result.residual_metrics {
+        println!();
+        print_residuals(metrics);
+    }
+    if let Some(spread) = &result.measurement_spread {
+        println!();
+        print_spread(spread);
+    }
+    println!();
+    print_filters(&filters);
+
+    let content = export::export_filters(&filters, format, sample_rate as u32)?;
+    std::fs::write(&output, content).map_err(|e| format!("Failed to write {:?}: {}", output, e))?;
+    println!("\nFilters written to: {:?}", output);
+
+    if let Some(manifest_path) = manifest {
+        let run_manifest = result
+            .run_manifest
+            .ok_or_else(|| "Optimization returned no run manifest".to_string())?;
+        run_manifest.save(&manifest_path)?;
+        println!(
+            "Run manifest (seed {}) written to: {:?}",
+            run_manifest.params.seed.unwrap_or_default(),
+            manifest_path
+        );
+        if run_manifest.truncated {
+            println!("The run was cut short by its time budget, the manifest cannot be replayed");
+        }
+    }
+    Ok(())
+}
+
+async fn replay(manifest_path: &Path) -> Result<(), String> {
+    let manifest = RunManifest::load(manifest_path)?;
+    println!(
+        "Replaying run from {:?} (seed {})",
+        manifest_path,
+        manifest.params.seed.unwrap_or_default()
+    );
+
+    let report = manifest
+        .replay(Arc::new(SilentProgressCallback), CancellationToken::new())
+        .await
+        .map_err(|e| format!("Replay failed: {}", e))?;
+
+    if report.version_mismatch {
+        println!(
+            "Warning: recorded with {:?}, running {:?}",
+            manifest.crate_versions,
+            autoeq_backend::manifest::CrateVersions::current()
+        );
+    }
+    if report.identical {
+        println!("Replay matches the recorded filters bit for bit");
+        Ok(())
+    } else {
+        Err("Replay produced different filters than the recorded run".to_string())
+    }
+}
+
+async fn score(params: OptimizationParams) -> Result<(), String> {
+    let score = optim::score_curve(&params)
+        .await
+        .map_err(|e| format!("Scoring failed: {}", e))?;
+    print_score("Preference score", score);
+    Ok(())
+}
+
+async fn run_sweep(template: OptimizationParams, options: SweepOptions) -> Result<(), String> {
+    let runs =
+        (options.max_filters + 1).saturating_sub(options.min_filters) * options.peq_models.len();
+    println!("Sweep: {} optimizations", runs);
+
+    // Stop the sweep on Ctrl+C
+    let cancellation_token = CancellationToken::new();
+    let c = cancellation_token.clone();
+    ctrlc::set_handler(move || {
+        eprintln!("\n\nReceived Ctrl+C, cancelling sweep...");
+        c.cancel();
+    })
+    .map_err(|e| format!("Failed to set Ctrl+C handler: {}", e))?;
+
+    // One configuration at a time keeps the run times comparable
+    let job_manager = JobManager::new(1);
+    let result = sweep::run_sweep(&job_manager, &template, &options, cancellation_token).await?;
+
+    println!();
+    print!("{}", result.to_table());
+    match result.recommended_point() {
+        Some(point) => println!(
+            "\nRecommended: {} filters, {} (within {:.0}% of the best loss)",
+            point.num_filters,
+            point.peq_model,
+            options.tolerance * 100.0
+        ),
+        None => return Err("No configuration of the sweep succeeded".to_string()),
+    }
+    Ok(())
+}
+
+async fn batch(
+    input: &Path,
+    template: OptimizationParams,
+    options: BatchOptions,
+) -> Result<(), String> {
+    let inputs = batch::collect_inputs(input)?;
+    println!(
+        "Batch: {} inputs, {} workers, writing to {:?}",
+        inputs.len(),
+        options.workers,
+        options.output_dir
+    );
+
+    // Cancel the remaining jobs on Ctrl+C
+    let cancellation_token = CancellationToken::new();
+    let c = cancellation_token.clone();
+    ctrlc::set_handler(move || {
+        eprintln!("\n\nReceived Ctrl+C, cancelling batch...");
+        c.cancel();
+    })
+    .map_err(|e| format!("Failed to set Ctrl+C handler: {}", e))?;
+
+    let summary = batch::run_batch(inputs, &template, &options, cancellation_token).await?;
+
+    let summary_path = options.output_dir.join("summary.csv");
+    std::fs::write(&summary_path, summary.to_csv())
+        .map_err(|e| format!("Failed to write {:?}: {}", summary_path, e))?;
+
+    println!();
+    print!("{}", summary.to_table());
+    println!("\nSummary written to: {:?}", summary_path);
+
+    if summary.failed() > 0 {
+        return Err(format!(
+            "{} of {} inputs failed",
+            summary.failed(),
+            summary.items.len()
+        ));
+    }
+    Ok(())
+}
+
+fn export(
+    filter_strings: &[String],
+    format: ExportFormat,
+    sample_rate: u32,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let filters: Vec<FilterParam> = parse_filters(filter_strings)?
+        .iter()
+        .map(FilterParam::from)
+        .collect();
+    let content = export::export_filters(&filters, format, sample_rate)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)
+                .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+            println!("Filters written to: {:?}", path);
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Progress callback used with --quiet
+struct SilentProgressCallback;
+
+impl ProgressCallback for SilentProgressCallback {
+    fn on_progress(&self, _update: ProgressUpdate) -> bool {
+        true
+    }
+}
+
+/// Export format given on the command line, else the device's, else parametric-eq
+fn resolve_format(format: Option<ExportFormat>, params: &OptimizationParams) -> ExportFormat {
+    format
+        .or(params.device_profile.as_ref().map(|p| p.export_format))
+        .unwrap_or(ExportFormat::ParametricEQ)
+}
+
+fn default_output_path(curve: &Path, format: ExportFormat) -> PathBuf {
+    let stem = curve
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "autoeq".to_string());
+    curve.with_file_name(format!("{}-eq.{}", stem, format.file_extension()))
+}
+
+fn print_score(label: &str, score: Option<f64>) {
+    match score {
+        Some(value) => println!("{}: {:.2}", label, value),
+        None => println!("{}: n/a (loss has no preference model)", label),
+    }
+}
+
+fn print_residuals(metrics: &ResidualMetrics) {
+    println!("Residual deviation from target:");
+    let bands = [
+        ("overall", Some(&metrics.overall)),
+        ("bass", metrics.bass.as_ref()),
+        ("mid", metrics.mid.as_ref()),
+        ("treble", metrics.treble.as_ref()),
+    ];
+    for (name, band) in bands {
+        if let Some(band) = band {
+            println!(
+                "  {:<8} {:>6.0}-{:<6.0} Hz  rms {:>5.2} dB  max {:>5.2} dB",
+                name, band.min_freq, band.max_freq, band.rms_db, band.max_abs_db
+            );
+        }
+    }
+    if !metrics.worst_fit.is_empty() {
+        let worst: Vec<String> = metrics
+            .worst_fit
+            .iter()
+            .map(|w| format!("{:.0} Hz ({:+.1} dB)", w.freq, w.deviation_db))
+            .collect();
+        println!("  worst fit: {}", worst.join(", "));
+    }
+}
+
+fn print_spread(spread: &MeasurementSpread) {
+    println!("Spread across measurements:");
+    for (i, rms) in spread.residual_rms_db.iter().enumerate() {
+        println!("  measurement {:<3} rms {:>5.2} dB", i + 1, rms);
+    }
+    println!(
+        "  std dev     mean {:>5.2} dB  max {:>5.2} dB",
+        spread.mean_std_db, spread.max_std_db
+    );
+}
+
+fn print_devices() {
+    for profile in device_profiles() {
+        let q = match &profile.q_values {
+            Some(values) => format!("Q in {:?}", values),
+            None => format!("Q {}-{}", profile.min_q, profile.max_q),
+        };
+        let step = profile
+            .gain_step
+            .map(|step| format!(", {} dB steps", step))
+            .unwrap_or_default();
+        println!(
+            "{:<16} {} ({} bands, {}{}, {} to {} dB)",
+            profile.id,
+            profile.name,
+            profile.max_filters,
+            q,
+            step,
+            profile.min_gain,
+            profile.max_gain
+        );
+    }
+}
+
+fn print_filters(filters: &[FilterParam]) {
+    println!("EQ Filters:");
+    for (idx, filter) in filters.iter().enumerate() {
+        println!(
+            "  [{}] {} {:.0} Hz, Q={:.2}, Gain={:.2} dB",
+            idx + 1,
+            filter.filter_type,
+            filter.frequency,
+            filter.q,
+            filter.gain
+        );
+    }
+}
+
+/// Load `curve` and the extra measurements as one set, `curve` with weight 1
+///
+/// Returns no measurement when there is no extra one: `curve` is then
+/// optimized on its own.
+fn load_measurements(curve: &Path, extra: &[String]) -> Result<Vec<CapturedMeasurement>, String> {
+    if extra.is_empty() {
+        return Ok(Vec::new());
+    }
+    let load = |path: &Path, weight: f64| -> Result<CapturedMeasurement, String> {
+        let (freq, spl) = autoeq::load_frequency_response(path)
+            .map_err(|e| format!("Failed to load {:?}: {}", path, e))?;
+        Ok(CapturedMeasurement {
+            weight,
+            ..CapturedMeasurement::new(freq.to_vec(), spl.to_vec())
+        })
+    };
+    let mut measurements = vec![load(curve, 1.0)?];
+    for measurement in extra {
+        // The weight is optional, a path may contain ':' too
+        let (path, weight) = measurement
+            .rsplit_once(':')
+            .and_then(|(path, weight)| Some((path, weight.parse::<f64>().ok()?)))
+            .unwrap_or((measurement.as_str(), 1.0));
+        measurements.push(load(Path::new(path), weight)?);
+    }
+    Ok(measurements)
+}
+
+/// Parse a time budget in seconds, which must be positive and finite
+fn parse_time_budget(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s
+        .parse()
+        .map_err(|_| format!("Invalid time budget: {}", s))?;
+    if seconds <= 0.0 {
+        return Err(format!("Time budget must be > 0 seconds (got: {})", s));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("Invalid time budget {}: {}", s, e))
+}
+
+fn parse_filters(filter_strings: &[String]) -> Result<Vec<DecodedFilter>, String> {
+    filter_strings
+        .iter()
+        .map(|filter_str| {
+            let parts: Vec<&str> = filter_str.split(':').collect();
+            if parts.len() != 4 {
+                return Err(format!(
+                    "Invalid filter format '{}'. Expected 'TYPE:FREQ:Q:GAIN'",
+                    filter_str
+                ));
+            }
+            let frequency = parts[1]
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid frequency: {}", parts[1]))?;
+            let q = parts[2]
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid Q: {}", parts[2]))?;
+            let gain = parts[3]
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid gain: {}", parts[3]))?;
+            Ok(DecodedFilter {
+                filter_type: parts[0].parse::<FilterType>()?,
+                frequency,
+                q,
+                gain,
+            })
+        })
+        .collect()
+}
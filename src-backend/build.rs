@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+/// Expose the locked autoeq version to the run manifest
+fn main() {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let lock_path = manifest_dir.join("..").join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let version = std::fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|lock| locked_version(&lock, "autoeq"))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=AUTOEQ_VERSION={}", version);
+}
+
+fn locked_version(lock: &str, name: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", name);
+    let lines: Vec<&str> = lock.lines().map(str::trim).collect();
+    let idx = lines.iter().position(|line| *line == name_line)?;
+    lines
+        .get(idx + 1)?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(str::to_string)
+}
@@ -0,0 +1,558 @@
+use crate::export::{ExportFormat, export_filters, filters_for_export};
+use crate::optim::{
+    CancellationToken, OptimizationParams, ProgressCallback, ProgressUpdate,
+    run_optimization_internal,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// One measurement to optimize in a batch
+#[derive(Debug, Clone)]
+pub struct BatchInput {
+    /// Name used for the export file and the summary table
+    pub name: String,
+    pub curve_path: PathBuf,
+    /// Per-input target, overrides the template target when set
+    pub target_path: Option<PathBuf>,
+}
+
+/// Batch settings shared by all inputs
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Maximum number of optimizations running at the same time
+    pub workers: usize,
+    pub output_dir: PathBuf,
+    pub format: ExportFormat,
+    /// Time allowed for each input, counted from when its optimization starts
+    pub time_budget: Option<Duration>,
+}
+
+/// Outcome of a single batch input
+#[derive(Debug, Clone)]
+pub struct BatchItemResult {
+    pub name: String,
+    pub curve_path: PathBuf,
+    pub output_path: Option<PathBuf>,
+    pub preference_score_before: Option<f64>,
+    pub preference_score_after: Option<f64>,
+    pub num_filters: usize,
+    pub run_time: Duration,
+    /// The time budget ran out and the export holds the best filters found by then
+    pub truncated: bool,
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn status(&self) -> &'static str {
+        match (&self.error, self.truncated) {
+            (Some(_), _) => "failed",
+            (None, true) => "budget",
+            (None, false) => "ok",
+        }
+    }
+}
+
+/// Results of a whole batch, in input order
+#[derive(Debug, Clone, Default)]
+pub struct BatchSummary {
+    pub items: Vec<BatchItemResult>,
+}
+
+impl BatchSummary {
+    pub fn succeeded(&self) -> usize {
+        self.items.iter().filter(|item| item.is_success()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.items.len() - self.succeeded()
+    }
+
+    /// Human readable summary table
+    pub fn to_table(&self) -> String {
+        let name_width = self
+            .items
+            .iter()
+            .map(|item| item.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Name".len());
+
+        let mut table = String::new();
+        writeln!(
+            table,
+            "{:<name_width$}  {:<6}  {:>8}  {:>8}  {:>7}  {:>8}  Output",
+            "Name", "Status", "Before", "After", "Filters", "Time (s)"
+        )
+        .unwrap();
+        for item in &self.items {
+            let detail = match &item.error {
+                None => item
+                    .output_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+                Some(error) => error.clone(),
+            };
+            writeln!(
+                table,
+                "{:<name_width$}  {:<6}  {:>8}  {:>8}  {:>7}  {:>8.1}  {}",
+                item.name,
+                item.status(),
+                format_score(item.preference_score_before),
+                format_score(item.preference_score_after),
+                item.num_filters,
+                item.run_time.as_secs_f64(),
+                detail
+            )
+            .unwrap();
+        }
+        writeln!(
+            table,
+            "{} succeeded, {} failed",
+            self.succeeded(),
+            self.failed()
+        )
+        .unwrap();
+        table
+    }
+
+    /// Summary as CSV, one row per input
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "name,curve,status,score_before,score_after,num_filters,run_time_s,output,error\n",
+        );
+        for item in &self.items {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{:.3},{},{}",
+                csv_field(&item.name),
+                csv_field(&item.curve_path.display().to_string()),
+                item.status(),
+                item.preference_score_before
+                    .map(|s| format!("{:.4}", s))
+                    .unwrap_or_default(),
+                item.preference_score_after
+                    .map(|s| format!("{:.4}", s))
+                    .unwrap_or_default(),
+                item.num_filters,
+                item.run_time.as_secs_f64(),
+                csv_field(
+                    &item
+                        .output_path
+                        .as_ref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default()
+                ),
+                csv_field(item.error.as_deref().unwrap_or(""))
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+fn format_score(score: Option<f64>) -> String {
+    score
+        .map(|s| format!("{:.2}", s))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Collect all CSV measurements in a directory, sorted by file name
+pub fn inputs_from_dir(dir: &Path) -> Result<Vec<BatchInput>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?;
+
+    let mut inputs: Vec<BatchInput> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        })
+        .map(|path| BatchInput {
+            name: input_name(&path),
+            curve_path: path,
+            target_path: None,
+        })
+        .collect();
+    inputs.sort_by(|a, b| a.curve_path.cmp(&b.curve_path));
+
+    if inputs.is_empty() {
+        return Err(format!("No CSV measurements found in {:?}", dir));
+    }
+    Ok(inputs)
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchManifest {
+    inputs: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    curve: PathBuf,
+    #[serde(default)]
+    target: Option<PathBuf>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Load a YAML or JSON manifest listing the inputs of a batch
+///
+/// Relative paths are resolved against the manifest directory.
+pub fn inputs_from_manifest(path: &Path) -> Result<Vec<BatchInput>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read manifest {:?}: {}", path, e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    parse_manifest(&content, base_dir)
+}
+
+fn parse_manifest(content: &str, base_dir: &Path) -> Result<Vec<BatchInput>, String> {
+    // YAML is a superset of JSON, so one parser handles both
+    let manifest: BatchManifest =
+        serde_yaml::from_str(content).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.inputs.is_empty() {
+        return Err("Manifest has no inputs".to_string());
+    }
+
+    Ok(manifest
+        .inputs
+        .into_iter()
+        .map(|entry| {
+            let curve_path = base_dir.join(&entry.curve);
+            BatchInput {
+                name: entry.name.unwrap_or_else(|| input_name(&curve_path)),
+                curve_path,
+                target_path: entry.target.map(|t| base_dir.join(t)),
+            }
+        })
+        .collect())
+}
+
+/// Collect inputs from a directory of CSV files or from a manifest file
+pub fn collect_inputs(path: &Path) -> Result<Vec<BatchInput>, String> {
+    if path.is_dir() {
+        inputs_from_dir(path)
+    } else {
+        inputs_from_manifest(path)
+    }
+}
+
+fn input_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "input".to_string())
+}
+
+/// Fail when two inputs would write the same export file
+///
+/// Names differing only by case collide too, on case-insensitive file systems.
+fn check_unique_names(inputs: &[BatchInput]) -> Result<(), String> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+    for input in inputs {
+        if let Some(other) = seen.insert(input.name.to_lowercase(), &input.curve_path) {
+            return Err(format!(
+                "Inputs {:?} and {:?} would both be exported as '{}', set distinct names in the manifest",
+                other, input.curve_path, input.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Progress callback for batch jobs: only forwards cancellation
+struct BatchProgressCallback {
+    cancellation_token: CancellationToken,
+}
+
+impl ProgressCallback for BatchProgressCallback {
+    fn on_progress(&self, _update: ProgressUpdate) -> bool {
+        !self.cancellation_token.is_cancelled()
+    }
+}
+
+/// Optimize every input with the shared parameter template
+///
+/// At most `options.workers` optimizations run at once. A failing input is
+/// recorded in the summary and does not stop the rest of the batch. Each input
+/// runs under a child of `cancellation_token`, so cancelling it stops the whole
+/// batch while an exhausted time budget only stops that input, which keeps the
+/// best filters found by then. Inputs sharing
+/// a name are rejected before anything runs, as their exports would overwrite
+/// each other.
+pub async fn run_batch(
+    inputs: Vec<BatchInput>,
+    template: &OptimizationParams,
+    options: &BatchOptions,
+    cancellation_token: CancellationToken,
+) -> Result<BatchSummary, String> {
+    check_unique_names(&inputs)?;
+    std::fs::create_dir_all(&options.output_dir).map_err(|e| {
+        format!(
+            "Failed to create output directory {:?}: {}",
+            options.output_dir, e
+        )
+    })?;
+
+    println!(
+        "[RUST DEBUG] Starting batch of {} inputs with {} workers",
+        inputs.len(),
+        options.workers
+    );
+
+    let semaphore = Arc::new(Semaphore::new(options.workers.max(1)));
+    let runtime = tokio::runtime::Handle::current();
+
+    let tasks = inputs.into_iter().map(|input| {
+        let semaphore = Arc::clone(&semaphore);
+        let cancellation_token = cancellation_token.clone();
+        let time_budget = options.time_budget;
+        let runtime = runtime.clone();
+        let mut params = template.clone();
+        params.curve_path = Some(input.curve_path.to_string_lossy().to_string());
+        if let Some(target) = &input.target_path {
+            params.target_path = Some(target.to_string_lossy().to_string());
+        }
+        let output_path = options.output_dir.join(format!(
+            "{}-eq.{}",
+            input.name,
+            options.format.file_extension()
+        ));
+        let format = options.format;
+
+        async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("batch semaphore closed");
+            let name = input.name.clone();
+            let curve_path = input.curve_path.clone();
+            let job_token = match time_budget {
+                Some(budget) => cancellation_token.child_with_timeout(budget),
+                None => cancellation_token.child(),
+            };
+
+            // The optimizers are CPU bound, keep them off the async workers
+            tokio::task::spawn_blocking(move || {
+                runtime.block_on(run_batch_item(
+                    input,
+                    params,
+                    output_path,
+                    format,
+                    job_token,
+                ))
+            })
+            .await
+            .unwrap_or_else(|e| BatchItemResult {
+                name,
+                curve_path,
+                output_path: None,
+                preference_score_before: None,
+                preference_score_after: None,
+                num_filters: 0,
+                run_time: Duration::ZERO,
+                truncated: false,
+                error: Some(format!("Worker failed: {}", e)),
+            })
+        }
+    });
+
+    let items = futures_util::future::join_all(tasks).await;
+    let summary = BatchSummary { items };
+    println!(
+        "[RUST DEBUG] Batch finished: {} succeeded, {} failed",
+        summary.succeeded(),
+        summary.failed()
+    );
+    Ok(summary)
+}
+
+async fn run_batch_item(
+    input: BatchInput,
+    params: OptimizationParams,
+    output_path: PathBuf,
+    format: ExportFormat,
+    cancellation_token: CancellationToken,
+) -> BatchItemResult {
+    let started = Instant::now();
+    let sample_rate = params.sample_rate as u32;
+
+    let outcome = async {
+        if cancellation_token.is_cancelled() {
+            return Err("Batch cancelled".to_string());
+        }
+        let progress_callback = Arc::new(BatchProgressCallback {
+            cancellation_token: cancellation_token.clone(),
+        });
+        let result = run_optimization_internal(params, progress_callback, cancellation_token)
+            .await
+            .map_err(|e| e.to_string())?;
+        let decoded = result
+            .filters
+            .as_ref()
+            .ok_or_else(|| "Optimization returned no filters".to_string())?;
+        let filters = filters_for_export(decoded);
+        let content = export_filters(&filters, format, sample_rate)?;
+        std::fs::write(&output_path, content)
+            .map_err(|e| format!("Failed to write {:?}: {}", output_path, e))?;
+        Ok((result, filters.len()))
+    }
+    .await;
+
+    match outcome {
+        Ok((result, num_filters)) => BatchItemResult {
+            name: input.name,
+            curve_path: input.curve_path,
+            output_path: Some(output_path),
+            preference_score_before: result.preference_score_before,
+            preference_score_after: result.preference_score_after,
+            num_filters,
+            run_time: started.elapsed(),
+            truncated: result.truncated,
+            error: None,
+        },
+        Err(error) => {
+            println!(
+                "[RUST DEBUG] Batch input '{}' failed: {}",
+                input.name, error
+            );
+            BatchItemResult {
+                name: input.name,
+                curve_path: input.curve_path,
+                output_path: None,
+                preference_score_before: None,
+                preference_score_after: None,
+                num_filters: 0,
+                run_time: started.elapsed(),
+                truncated: false,
+                error: Some(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest_yaml() {
+        let yaml =
+            "inputs:\n  - curve: a.csv\n  - curve: sub/b.csv\n    target: t.csv\n    name: bee\n";
+        let inputs = parse_manifest(yaml, Path::new("/data")).unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].name, "a");
+        assert_eq!(inputs[0].curve_path, PathBuf::from("/data/a.csv"));
+        assert!(inputs[0].target_path.is_none());
+        assert_eq!(inputs[1].name, "bee");
+        assert_eq!(inputs[1].curve_path, PathBuf::from("/data/sub/b.csv"));
+        assert_eq!(inputs[1].target_path, Some(PathBuf::from("/data/t.csv")));
+    }
+
+    #[test]
+    fn test_parse_manifest_json() {
+        let json = r#"{"inputs": [{"curve": "/abs/x.csv"}]}"#;
+        let inputs = parse_manifest(json, Path::new("/data")).unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].curve_path, PathBuf::from("/abs/x.csv"));
+    }
+
+    #[test]
+    fn test_parse_manifest_empty() {
+        assert!(parse_manifest("inputs: []", Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let yaml = "inputs:\n  - curve: a/x.csv\n  - curve: b/x.csv\n";
+        let inputs = parse_manifest(yaml, Path::new("/data")).unwrap();
+        let error = check_unique_names(&inputs).unwrap_err();
+        assert!(error.contains("a/x.csv") && error.contains("b/x.csv"));
+
+        let yaml = "inputs:\n  - curve: a/x.csv\n  - curve: b/x.csv\n    name: x-b\n";
+        let inputs = parse_manifest(yaml, Path::new("/data")).unwrap();
+        assert!(check_unique_names(&inputs).is_ok());
+    }
+
+    #[test]
+    fn test_inputs_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.csv"), "").unwrap();
+        std::fs::write(dir.path().join("a.CSV"), "").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let inputs = inputs_from_dir(dir.path()).unwrap();
+        let names: Vec<&str> = inputs.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_summary_counts_and_csv() {
+        let summary = BatchSummary {
+            items: vec![
+                BatchItemResult {
+                    name: "ok".to_string(),
+                    curve_path: PathBuf::from("ok.csv"),
+                    output_path: Some(PathBuf::from("out/ok-eq.txt")),
+                    preference_score_before: Some(4.0),
+                    preference_score_after: Some(6.5),
+                    num_filters: 5,
+                    run_time: Duration::from_millis(1500),
+                    truncated: false,
+                    error: None,
+                },
+                BatchItemResult {
+                    name: "slow".to_string(),
+                    curve_path: PathBuf::from("slow.csv"),
+                    output_path: Some(PathBuf::from("out/slow-eq.txt")),
+                    preference_score_before: Some(4.0),
+                    preference_score_after: Some(5.0),
+                    num_filters: 5,
+                    run_time: Duration::from_secs(60),
+                    truncated: true,
+                    error: None,
+                },
+                BatchItemResult {
+                    name: "bad".to_string(),
+                    curve_path: PathBuf::from("bad.csv"),
+                    output_path: None,
+                    preference_score_before: None,
+                    preference_score_after: None,
+                    num_filters: 0,
+                    run_time: Duration::ZERO,
+                    truncated: false,
+                    error: Some("missing file, really".to_string()),
+                },
+            ],
+        };
+        assert_eq!(summary.succeeded(), 2);
+        assert_eq!(summary.failed(), 1);
+
+        let csv = summary.to_csv();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains("ok,ok.csv,ok,4.0000,6.5000,5,1.500,out/ok-eq.txt,"));
+        assert!(csv.contains("slow,slow.csv,budget,"));
+        assert!(csv.contains("\"missing file, really\""));
+
+        let table = summary.to_table();
+        assert!(table.contains("2 succeeded, 1 failed"));
+    }
+}
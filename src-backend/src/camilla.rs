@@ -1,15 +1,29 @@
+pub use crate::camilla_config::{
+    CamillaDSPConfig, CaptureDevice, DeviceConfig, PipelineStep, PlaybackDevice,
+};
+use crate::camilla_config::{
+    BiquadParameters, Filter, GainParameters, Mixer, MixerChannels, MixerMapping, MixerSource,
+    NotchWidth,
+};
+use crate::filters::{DecodedFilter, FilterType, recommended_preamp_db};
 use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::TcpListener;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use tempfile::NamedTempFile;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
 
 // ============================================================================
 // Error Types
@@ -125,6 +139,12 @@ impl FilterParams {
                 self.gain
             )));
         }
+        if self.filter_type.parse::<FilterType>().is_err() && passthrough_biquad(self).is_none() {
+            return Err(CamillaError::InvalidConfiguration(format!(
+                "Filter type '{}' is not a CamillaDSP biquad set by frequency, Q and gain",
+                self.filter_type
+            )));
+        }
         Ok(())
     }
 }
@@ -178,6 +198,9 @@ pub struct AudioStreamState {
     pub capture_channel_map: Option<Vec<u16>>,
     /// Last error message
     pub error_message: Option<String>,
+    /// Restart from the top at the end of the file instead of stopping
+    #[serde(default)]
+    pub looping: bool,
 }
 
 impl Default for AudioStreamState {
@@ -196,85 +219,13 @@ impl Default for AudioStreamState {
             playback_channel_map: None,
             capture_channel_map: None,
             error_message: None,
+            looping: false,
         }
     }
 }
 
 pub type SharedAudioStreamState = Arc<Mutex<AudioStreamState>>;
 
-// ============================================================================
-// CamillaDSP Configuration Structures
-// ============================================================================
-
-/// Top-level CamillaDSP configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CamillaDSPConfig {
-    pub devices: DeviceConfig,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub filters: Option<serde_yaml::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mixers: Option<serde_yaml::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub pipeline: Option<Vec<PipelineStep>>,
-}
-
-/// Audio device configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeviceConfig {
-    pub samplerate: u32,
-    pub chunksize: u32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub capture: Option<CaptureDevice>,
-    pub playback: PlaybackDevice,
-}
-
-/// Capture device configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CaptureDevice {
-    #[serde(rename = "type")]
-    pub device_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub device: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub filename: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub channels: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub format: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub channel_map: Option<Vec<u16>>,
-}
-
-/// Playback device configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlaybackDevice {
-    #[serde(rename = "type")]
-    pub device_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub device: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub filename: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub channels: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub format: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub channel_map: Option<Vec<u16>>,
-}
-
-/// Pipeline step in the processing chain
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PipelineStep {
-    #[serde(rename = "type")]
-    pub step_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub channel: Option<u16>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub names: Option<Vec<String>>,
-}
-
 // ============================================================================
 // Channel mapping mode
 // ============================================================================
@@ -301,6 +252,8 @@ pub struct CamillaDSPProcess {
     websocket_port: u16,
     /// Process health check interval
     health_check_interval: Duration,
+    /// WebSocket client shared by every caller
+    client: Option<Arc<CamillaWebSocketClient>>,
 }
 
 impl CamillaDSPProcess {
@@ -314,12 +267,14 @@ impl CamillaDSPProcess {
             config_path: None,
             websocket_port,
             health_check_interval: Duration::from_secs(5),
+            client: None,
         }
     }
 
     /// Set the WebSocket port
     pub fn with_port(mut self, port: u16) -> Self {
         self.websocket_port = port;
+        self.client = None;
         self
     }
 
@@ -358,6 +313,15 @@ impl CamillaDSPProcess {
         format!("ws://127.0.0.1:{}", self.websocket_port)
     }
 
+    /// WebSocket client of this instance, with one connection for all callers
+    pub fn client(&mut self) -> Arc<CamillaWebSocketClient> {
+        let url = self.websocket_url();
+        Arc::clone(
+            self.client
+                .get_or_insert_with(|| Arc::new(CamillaWebSocketClient::new(url))),
+        )
+    }
+
     /// Get the config path
     pub fn config_path(&self) -> Option<&PathBuf> {
         self.config_path.as_ref()
@@ -431,6 +395,8 @@ impl CamillaDSPProcess {
 
     /// Stop the CamillaDSP process gracefully
     pub fn stop(&mut self) -> CamillaResult<()> {
+        // Closes the connection once callers release the client
+        self.client = None;
         if let Some(mut child) = self.process.take() {
             println!("[CamillaDSP] Stopping subprocess...");
 
@@ -559,20 +525,180 @@ impl Drop for CamillaDSPProcess {
 pub enum CamillaCommand {
     GetConfig,
     SetConfig { config: String },
+    ValidateConfig { config: String },
     GetState,
+    GetStopReason,
     Stop,
     GetCaptureSignalPeak,
     GetPlaybackSignalPeak,
+    GetSignalLevels,
+    GetSignalPeaksSinceStart,
+    ResetSignalPeaksSinceStart,
+    GetClippedSamples,
+    ResetClippedSamples,
     GetBufferLevel,
+    GetProcessingLoad,
+    GetRateAdjust,
+    GetCaptureRate,
+    GetVolume,
+    SetVolume { volume: f32 },
+    AdjustVolume { delta: f32 },
+    GetMute,
+    SetMute { mute: bool },
+    ToggleMute,
+}
+
+impl CamillaCommand {
+    /// Command name, also the key of its response
+    pub fn name(&self) -> &'static str {
+        match self {
+            CamillaCommand::GetConfig => "GetConfig",
+            CamillaCommand::SetConfig { .. } => "SetConfig",
+            CamillaCommand::ValidateConfig { .. } => "ValidateConfig",
+            CamillaCommand::GetState => "GetState",
+            CamillaCommand::GetStopReason => "GetStopReason",
+            CamillaCommand::Stop => "Stop",
+            CamillaCommand::GetCaptureSignalPeak => "GetCaptureSignalPeak",
+            CamillaCommand::GetPlaybackSignalPeak => "GetPlaybackSignalPeak",
+            CamillaCommand::GetSignalLevels => "GetSignalLevels",
+            CamillaCommand::GetSignalPeaksSinceStart => "GetSignalPeaksSinceStart",
+            CamillaCommand::ResetSignalPeaksSinceStart => "ResetSignalPeaksSinceStart",
+            CamillaCommand::GetClippedSamples => "GetClippedSamples",
+            CamillaCommand::ResetClippedSamples => "ResetClippedSamples",
+            CamillaCommand::GetBufferLevel => "GetBufferLevel",
+            CamillaCommand::GetProcessingLoad => "GetProcessingLoad",
+            CamillaCommand::GetRateAdjust => "GetRateAdjust",
+            CamillaCommand::GetCaptureRate => "GetCaptureRate",
+            CamillaCommand::GetVolume => "GetVolume",
+            CamillaCommand::SetVolume { .. } => "SetVolume",
+            CamillaCommand::AdjustVolume { .. } => "AdjustVolume",
+            CamillaCommand::GetMute => "GetMute",
+            CamillaCommand::SetMute { .. } => "SetMute",
+            CamillaCommand::ToggleMute => "ToggleMute",
+        }
+    }
+
+    /// JSON message sent over the WebSocket
+    ///
+    /// Commands without argument are a bare string, the others an object
+    /// holding the argument under the command name.
+    fn to_json(&self) -> String {
+        let argument = match self {
+            CamillaCommand::SetConfig { config } | CamillaCommand::ValidateConfig { config } => {
+                serde_json::json!(config)
+            }
+            CamillaCommand::SetVolume { volume } => serde_json::json!(volume),
+            CamillaCommand::AdjustVolume { delta } => serde_json::json!(delta),
+            CamillaCommand::SetMute { mute } => serde_json::json!(mute),
+            _ => return serde_json::Value::String(self.name().to_string()).to_string(),
+        };
+        serde_json::json!({ self.name(): argument }).to_string()
+    }
+}
+
+/// Signal levels of every channel, in dBFS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalLevels {
+    pub playback_rms: Vec<f32>,
+    pub playback_peak: Vec<f32>,
+    pub capture_rms: Vec<f32>,
+    pub capture_peak: Vec<f32>,
+}
+
+/// Highest peak of every channel since processing started, in dBFS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalPeaks {
+    pub playback: Vec<f32>,
+    pub capture: Vec<f32>,
+}
+
+/// Why processing last stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StopReason {
+    None,
+    Done,
+    CaptureError(String),
+    PlaybackError(String),
+    CaptureFormatChange(u32),  // New capture sample rate
+    PlaybackFormatChange(u32), // New playback sample rate
+    UnknownError(String),
+}
+
+// CamillaDSP replies with the command name as key, like
+// {"GetState": {"result": "Ok", "value": "Running"}}
+
+/// Reply to one command
+#[derive(Debug, Deserialize)]
+struct CommandReply {
+    result: String,
+    #[serde(default)]
+    value: serde_json::Value,
 }
 
-// We parse responses dynamically since CamillaDSP uses externally tagged
-// commands like {"GetState": {"result": "Ok", "value": "Running"}}
+/// Value of the reply to command `name`, or the error CamillaDSP reported
+fn reply_value<T: DeserializeOwned>(text: &str, name: &str) -> CamillaResult<T> {
+    let v: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
+    if let Some(error) = v.get("Invalid").and_then(|x| x.get("error")) {
+        return Err(CamillaError::ProcessCommunicationFailed(format!(
+            "{} rejected: {}",
+            name, error
+        )));
+    }
+    let reply: CommandReply = v
+        .get(name)
+        .cloned()
+        .and_then(|x| serde_json::from_value(x).ok())
+        .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
+    if reply.result != "Ok" {
+        let detail = reply.value.as_str().unwrap_or(&reply.result).to_string();
+        return Err(CamillaError::ProcessCommunicationFailed(format!(
+            "{} failed: {}",
+            name, detail
+        )));
+    }
+    serde_json::from_value(reply.value)
+        .map_err(|e| CamillaError::WebSocketError(format!("Unexpected {} value: {}", name, e)))
+}
+
+/// Level of one channel or of every channel
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChannelLevels {
+    Single(f32),
+    Channels(Vec<f32>),
+}
+
+impl ChannelLevels {
+    /// Loudest channel, silence when there is none
+    fn loudest(self) -> f32 {
+        match self {
+            ChannelLevels::Single(level) => level,
+            ChannelLevels::Channels(levels) => levels.into_iter().fold(-1000.0, f32::max),
+        }
+    }
+}
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connection attempts before a queued command fails
+const CONNECT_ATTEMPTS: u32 = 3;
+
+/// Command waiting in the queue or for its response
+struct QueuedCommand {
+    name: &'static str,
+    message: String,
+    reply: oneshot::Sender<CamillaResult<String>>,
+}
 
 /// WebSocket client for CamillaDSP control
+///
+/// Commands are queued to a task owning one long-lived connection, opened on
+/// the first command and reopened on the next command after it drops.
 pub struct CamillaWebSocketClient {
     url: String,
     timeout: Duration,
+    queue: Mutex<Option<mpsc::UnboundedSender<QueuedCommand>>>,
 }
 
 impl CamillaWebSocketClient {
@@ -581,6 +707,7 @@ impl CamillaWebSocketClient {
         Self {
             url,
             timeout: Duration::from_secs(5),
+            queue: Mutex::new(None),
         }
     }
 
@@ -590,184 +717,181 @@ impl CamillaWebSocketClient {
         self
     }
 
-    /// Send a command and wait for response
-    pub async fn send_command(&self, command: CamillaCommand) -> CamillaResult<String> {
-        // Connect to WebSocket
-        let (ws_stream, _) = connect_async(&self.url)
-            .await
-            .map_err(|e| CamillaError::WebSocketError(format!("Connection failed: {}", e)))?;
-
-        let (mut write, mut read) = ws_stream.split();
-
-        // Build and send command
-        match command {
-            CamillaCommand::SetConfig { ref config } => {
-                let command_json = serde_json::json!({ "SetConfig": { "config": config } }).to_string();
-                println!("[WebSocket] Sending command: {}", command_json);
-                write
-                    .send(Message::Text(command_json))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::GetConfig => {
-                println!("[WebSocket] Sending command: GetConfig");
-                let txt = serde_json::to_string(&"GetConfig").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::GetState => {
-                println!("[WebSocket] Sending command: GetState");
-                let txt = serde_json::to_string(&"GetState").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::Stop => {
-                println!("[WebSocket] Sending command: Stop");
-                let txt = serde_json::to_string(&"Stop").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::GetCaptureSignalPeak => {
-                println!("[WebSocket] Sending command: GetCaptureSignalPeak");
-                let txt = serde_json::to_string(&"GetCaptureSignalPeak").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::GetPlaybackSignalPeak => {
-                println!("[WebSocket] Sending command: GetPlaybackSignalPeak");
-                let txt = serde_json::to_string(&"GetPlaybackSignalPeak").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
-            }
-            CamillaCommand::GetBufferLevel => {
-                println!("[WebSocket] Sending command: GetBufferLevel");
-                let txt = serde_json::to_string(&"GetBufferLevel").unwrap();
-                write
-                    .send(Message::Text(txt))
-                    .await
-                    .map_err(|e| CamillaError::WebSocketError(format!("Send failed: {}", e)))?;
+    /// Queue of the connection task, started on first use
+    fn queue(&self) -> CamillaResult<mpsc::UnboundedSender<QueuedCommand>> {
+        let mut queue = self.queue.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock command queue: {}", e))
+        })?;
+        match queue.as_ref() {
+            Some(sender) if !sender.is_closed() => Ok(sender.clone()),
+            _ => {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                tokio::spawn(run_connection(self.url.clone(), self.timeout, receiver));
+                *queue = Some(sender.clone());
+                Ok(sender)
             }
         }
+    }
+
+    /// Send a command and wait for response
+    ///
+    /// Commands are not logged, metering polls several of them per tick.
+    pub async fn send_command(&self, command: CamillaCommand) -> CamillaResult<String> {
+        let message = command.to_json();
+        let (reply, response) = oneshot::channel();
+        self.queue()?
+            .send(QueuedCommand {
+                name: command.name(),
+                message,
+                reply,
+            })
+            .map_err(|_| CamillaError::WebSocketError("Connection task stopped".to_string()))?;
 
         // Wait for response with timeout
-        let response_future = read.next();
-        let response_msg = tokio::time::timeout(self.timeout, response_future)
+        tokio::time::timeout(self.timeout, response)
             .await
             .map_err(|_| CamillaError::Timeout("WebSocket response timeout".to_string()))?
-            .ok_or_else(|| CamillaError::WebSocketError("Connection closed".to_string()))?
-            .map_err(|e| CamillaError::WebSocketError(format!("Receive failed: {}", e)))?;
+            .map_err(|_| CamillaError::WebSocketError("Connection closed".to_string()))?
+    }
 
-        match response_msg {
-            Message::Text(text) => {
-                println!("[WebSocket] Received response: {}", text);
-                Ok(text)
-            }
-            _ => Err(CamillaError::WebSocketError(
-                "Unexpected message type".to_string(),
-            )),
-        }
+    /// Send a command and decode the value of its reply
+    pub async fn request<T: DeserializeOwned>(&self, command: CamillaCommand) -> CamillaResult<T> {
+        let name = command.name();
+        let text = self.send_command(command).await?;
+        reply_value(&text, name)
     }
 
     /// Get current state
     pub async fn get_state(&self) -> CamillaResult<String> {
-        let text = self.send_command(CamillaCommand::GetState).await?;
-        // Expected: {"GetState": {"result":"Ok","value":"Running"}}
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let state = v.get("GetState")
-            .and_then(|x| x.get("value"))
-            .and_then(|x| x.as_str())
-            .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
-        Ok(state.to_string())
+        self.request(CamillaCommand::GetState).await
+    }
+
+    /// Reason processing last stopped
+    pub async fn get_stop_reason(&self) -> CamillaResult<StopReason> {
+        self.request(CamillaCommand::GetStopReason).await
     }
 
     /// Get current configuration
     pub async fn get_config(&self) -> CamillaResult<String> {
-        let text = self.send_command(CamillaCommand::GetConfig).await?;
-        // Expect {"GetConfig": {"result":"Ok","value":"<yaml>"}}
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let cfg = v.get("GetConfig")
-            .and_then(|x| x.get("value"))
-            .and_then(|x| x.as_str())
-            .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
-        Ok(cfg.to_string())
+        self.request(CamillaCommand::GetConfig).await
     }
 
     /// Set new configuration
     pub async fn set_config(&self, config_yaml: String) -> CamillaResult<()> {
-        let text = self
-            .send_command(CamillaCommand::SetConfig { config: config_yaml })
-            .await?;
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let ok = v.get("SetConfig")
-            .and_then(|x| x.get("result"))
-            .and_then(|x| x.as_str())
-            .unwrap_or("") == "Ok";
-        if ok { Ok(()) } else { Err(CamillaError::ProcessCommunicationFailed("SetConfig failed".to_string())) }
+        self.request(CamillaCommand::SetConfig {
+            config: config_yaml,
+        })
+        .await
+    }
+
+    /// Check a configuration without applying it
+    ///
+    /// Returns the configuration completed with defaults, or the reason it
+    /// was rejected as `InvalidConfiguration`.
+    pub async fn validate_config(&self, config_yaml: String) -> CamillaResult<String> {
+        self.request(CamillaCommand::ValidateConfig {
+            config: config_yaml,
+        })
+        .await
+        .map_err(|e| match e {
+            CamillaError::ProcessCommunicationFailed(message) => {
+                CamillaError::InvalidConfiguration(message)
+            }
+            other => other,
+        })
     }
 
     /// Stop playback
     pub async fn stop(&self) -> CamillaResult<()> {
-        let text = self.send_command(CamillaCommand::Stop).await?;
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let ok = v.get("Stop")
-            .and_then(|x| x.get("result"))
-            .and_then(|x| x.as_str())
-            .unwrap_or("") == "Ok";
-        if ok { Ok(()) } else { Err(CamillaError::ProcessCommunicationFailed("Stop failed".to_string())) }
+        self.request(CamillaCommand::Stop).await
     }
 
     /// Get capture signal peak (volume level)
     pub async fn get_capture_signal_peak(&self) -> CamillaResult<f32> {
-        let text = self
-            .send_command(CamillaCommand::GetCaptureSignalPeak)
-            .await?;
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let value = v.get("GetCaptureSignalPeak")
-            .and_then(|x| x.get("value"))
-            .and_then(|x| x.as_f64())
-            .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
-        Ok(value as f32)
+        let levels: ChannelLevels = self.request(CamillaCommand::GetCaptureSignalPeak).await?;
+        Ok(levels.loudest())
     }
 
     /// Get playback signal peak (volume level)
     pub async fn get_playback_signal_peak(&self) -> CamillaResult<f32> {
-        let text = self
-            .send_command(CamillaCommand::GetPlaybackSignalPeak)
-            .await?;
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let value = v.get("GetPlaybackSignalPeak")
-            .and_then(|x| x.get("value"))
-            .and_then(|x| x.as_f64())
-            .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
-        Ok(value as f32)
+        let levels: ChannelLevels = self.request(CamillaCommand::GetPlaybackSignalPeak).await?;
+        Ok(levels.loudest())
+    }
+
+    /// RMS and peak level of every capture and playback channel
+    pub async fn get_signal_levels(&self) -> CamillaResult<SignalLevels> {
+        self.request(CamillaCommand::GetSignalLevels).await
+    }
+
+    /// Highest peak of every channel since processing started
+    pub async fn get_signal_peaks_since_start(&self) -> CamillaResult<SignalPeaks> {
+        self.request(CamillaCommand::GetSignalPeaksSinceStart).await
+    }
+
+    /// Restart the peaks since start from the current levels
+    pub async fn reset_signal_peaks_since_start(&self) -> CamillaResult<()> {
+        self.request(CamillaCommand::ResetSignalPeaksSinceStart)
+            .await
+    }
+
+    /// Number of samples clipped since start or the last reset
+    pub async fn get_clipped_samples(&self) -> CamillaResult<u64> {
+        self.request(CamillaCommand::GetClippedSamples).await
+    }
+
+    /// Reset the clipped-sample counter
+    pub async fn reset_clipped_samples(&self) -> CamillaResult<()> {
+        self.request(CamillaCommand::ResetClippedSamples).await
     }
 
     /// Get buffer level
     pub async fn get_buffer_level(&self) -> CamillaResult<i32> {
-        let text = self.send_command(CamillaCommand::GetBufferLevel).await?;
-        let v: serde_json::Value = serde_json::from_str(&text)
-            .map_err(|e| CamillaError::WebSocketError(format!("JSON parse error: {}", e)))?;
-        let value = v.get("GetBufferLevel")
-            .and_then(|x| x.get("value"))
-            .and_then(|x| x.as_i64())
-            .ok_or_else(|| CamillaError::WebSocketError("Unexpected response format".to_string()))?;
-        Ok(value as i32)
+        self.request(CamillaCommand::GetBufferLevel).await
+    }
+
+    /// Processing load, in percent of the time available per chunk
+    pub async fn get_processing_load(&self) -> CamillaResult<f32> {
+        self.request(CamillaCommand::GetProcessingLoad).await
+    }
+
+    /// Rate adjust factor applied to the capture device, 1.0 when unadjusted
+    pub async fn get_rate_adjust(&self) -> CamillaResult<f32> {
+        self.request(CamillaCommand::GetRateAdjust).await
+    }
+
+    /// Measured sample rate of the capture device, in Hz
+    pub async fn get_capture_rate(&self) -> CamillaResult<u32> {
+        self.request(CamillaCommand::GetCaptureRate).await
+    }
+
+    /// Main volume, in dB
+    pub async fn get_volume(&self) -> CamillaResult<f32> {
+        self.request(CamillaCommand::GetVolume).await
+    }
+
+    /// Set the main volume, in dB
+    pub async fn set_volume(&self, volume: f32) -> CamillaResult<()> {
+        self.request(CamillaCommand::SetVolume { volume }).await
+    }
+
+    /// Change the main volume by `delta` dB, returning the new volume
+    pub async fn adjust_volume(&self, delta: f32) -> CamillaResult<f32> {
+        self.request(CamillaCommand::AdjustVolume { delta }).await
+    }
+
+    /// Whether the main volume is muted
+    pub async fn get_mute(&self) -> CamillaResult<bool> {
+        self.request(CamillaCommand::GetMute).await
+    }
+
+    /// Mute or unmute the main volume
+    pub async fn set_mute(&self, mute: bool) -> CamillaResult<()> {
+        self.request(CamillaCommand::SetMute { mute }).await
+    }
+
+    /// Toggle mute, returning whether the main volume is now muted
+    pub async fn toggle_mute(&self) -> CamillaResult<bool> {
+        self.request(CamillaCommand::ToggleMute).await
     }
 
     /// Test connection to WebSocket server
@@ -816,6 +940,354 @@ impl CamillaWebSocketClient {
     }
 }
 
+/// Serve the command queue of a client over one connection
+///
+/// Runs until the client is dropped. CamillaDSP answers the commands of a
+/// connection in order, keyed by command name. When the connection drops,
+/// the commands waiting for a response fail since they may or may not have
+/// been applied, and the next queued command reconnects.
+async fn run_connection(
+    url: String,
+    timeout: Duration,
+    mut queue: mpsc::UnboundedReceiver<QueuedCommand>,
+) {
+    let mut next = None;
+    loop {
+        // Stay disconnected until there is something to send
+        let first = match next.take() {
+            Some(command) => command,
+            None => match queue.recv().await {
+                Some(command) => command,
+                None => return,
+            },
+        };
+
+        let mut socket = match connect(&url, timeout).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                let _ = first.reply.send(Err(e));
+                continue;
+            }
+        };
+
+        let mut pending: VecDeque<QueuedCommand> = VecDeque::new();
+        let mut command = Some(first);
+        loop {
+            if let Some(command) = command.take() {
+                if let Err(e) = socket.send(Message::Text(command.message.clone())).await {
+                    // Not sent: it goes first on the next connection
+                    println!("[WebSocket] Send failed: {}", e);
+                    next = Some(command);
+                    break;
+                }
+                pending.push_back(command);
+            }
+
+            tokio::select! {
+                queued = queue.recv() => match queued {
+                    Some(queued) => command = Some(queued),
+                    None => {
+                        let _ = socket.close(None).await;
+                        return;
+                    }
+                },
+                received = socket.next() => match received {
+                    Some(Ok(Message::Text(text))) => resolve_response(&mut pending, text),
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+
+        println!("[WebSocket] Connection to {} lost", url);
+        for command in pending.drain(..) {
+            let _ = command.reply.send(Err(CamillaError::WebSocketError(
+                "Connection lost before the response".to_string(),
+            )));
+        }
+    }
+}
+
+/// Open the WebSocket, retrying with backoff while CamillaDSP comes up
+async fn connect(url: &str, timeout: Duration) -> CamillaResult<WebSocket> {
+    let mut delay = Duration::from_millis(100);
+    let mut attempt = 1;
+    loop {
+        let error = match tokio::time::timeout(timeout, connect_async(url)).await {
+            Ok(Ok((socket, _))) => {
+                println!("[WebSocket] Connected to {}", url);
+                return Ok(socket);
+            }
+            Ok(Err(e)) => CamillaError::WebSocketError(format!("Connection failed: {}", e)),
+            Err(_) => CamillaError::Timeout("WebSocket connection timeout".to_string()),
+        };
+        if attempt == CONNECT_ATTEMPTS {
+            return Err(error);
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Hand a response to the oldest pending command it answers
+///
+/// Commands queued before that one got no response of their own and fail.
+/// CamillaDSP answers a message it cannot parse with "Invalid", which goes to
+/// the oldest command.
+fn resolve_response(pending: &mut VecDeque<QueuedCommand>, text: String) {
+    let name = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|v| v.as_object()?.keys().next().cloned());
+    let index = match name.as_deref() {
+        Some("Invalid") if !pending.is_empty() => Some(0),
+        Some(name) => pending.iter().position(|c| c.name == name),
+        None => None,
+    };
+    let Some(index) = index else {
+        println!("[WebSocket] Ignoring unexpected response: {}", text);
+        return;
+    };
+    for skipped in pending.drain(..index) {
+        let _ = skipped.reply.send(Err(CamillaError::WebSocketError(format!(
+            "No response to {}",
+            skipped.name
+        ))));
+    }
+    if let Some(command) = pending.pop_front() {
+        let _ = command.reply.send(Ok(text));
+    }
+}
+
+// ============================================================================
+// Level Metering
+// ============================================================================
+
+/// Default time between two metering polls
+pub const DEFAULT_METER_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Readings kept for subscribers that fall behind
+const METER_CHANNEL_CAPACITY: usize = 16;
+
+/// Levels of one metering poll, in dBFS per channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeterReading {
+    pub capture_peak: Vec<f32>,
+    pub capture_rms: Vec<f32>,
+    pub playback_peak: Vec<f32>,
+    pub playback_rms: Vec<f32>,
+    pub clipped_samples: u64, // Counted by CamillaDSP since processing started
+    pub clipping: bool,       // Samples clipped since the previous reading
+    pub buffer_level: i32,    // Frames in the playback buffer
+}
+
+impl MeterReading {
+    /// Loudest playback channel, for a single VU meter
+    pub fn playback_peak_max(&self) -> f32 {
+        self.playback_peak.iter().copied().fold(-1000.0, f32::max)
+    }
+}
+
+/// Poll the levels every `interval` and publish them on `meter`
+///
+/// Failed polls are skipped: CamillaDSP may still be starting or already
+/// stopping, and the next poll reconnects.
+async fn run_metering(
+    client: Arc<CamillaWebSocketClient>,
+    interval: Duration,
+    meter: broadcast::Sender<MeterReading>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last_clipped = None;
+    loop {
+        ticker.tick().await;
+        // The three commands share the connection, queued back to back
+        let (levels, clipped_samples, buffer_level) = tokio::join!(
+            client.get_signal_levels(),
+            client.get_clipped_samples(),
+            client.get_buffer_level()
+        );
+        let (Ok(levels), Ok(clipped_samples), Ok(buffer_level)) =
+            (levels, clipped_samples, buffer_level)
+        else {
+            continue;
+        };
+        let clipping = last_clipped.is_some_and(|last| clipped_samples > last);
+        last_clipped = Some(clipped_samples);
+        // Without subscribers the reading is dropped
+        let _ = meter.send(MeterReading {
+            capture_peak: levels.capture_peak,
+            capture_rms: levels.capture_rms,
+            playback_peak: levels.playback_peak,
+            playback_rms: levels.playback_rms,
+            clipped_samples,
+            clipping,
+            buffer_level,
+        });
+    }
+}
+
+// ============================================================================
+// Playback Position
+// ============================================================================
+
+/// Time between two playback position updates
+const POSITION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time CamillaDSP gets past the end of the file to play what it buffered
+const END_OF_FILE_GRACE: Duration = Duration::from_secs(1);
+
+/// Format and length of a WAV file, from its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub frames: u64,
+}
+
+impl WavInfo {
+    /// Read the header of the WAV file at `path`
+    ///
+    /// Only the "fmt " and "data" chunks are read, not the samples. A data
+    /// chunk running past the end of the file, as left by an interrupted
+    /// recording, is cut to what the file holds.
+    pub fn read(path: &Path) -> CamillaResult<Self> {
+        let invalid = |reason: &str| {
+            CamillaError::IOError(format!("Invalid WAV file {:?}: {}", path, reason))
+        };
+        let mut file = fs::File::open(path)
+            .map_err(|e| CamillaError::IOError(format!("Failed to open {:?}: {}", path, e)))?;
+        let mut riff = [0u8; 12];
+        file.read_exact(&mut riff)
+            .map_err(|_| invalid("header too short"))?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(invalid("not a RIFF/WAVE file"));
+        }
+
+        let mut format = None; // (sample_rate, channels, block_align)
+        loop {
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header)
+                .map_err(|_| invalid("no data chunk"))?;
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+            let unread = match &header[0..4] {
+                b"fmt " => {
+                    if size < 16 {
+                        return Err(invalid("format chunk too short"));
+                    }
+                    let mut fmt = [0u8; 16];
+                    file.read_exact(&mut fmt)
+                        .map_err(|_| invalid("format chunk too short"))?;
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                    let block_align = u16::from_le_bytes([fmt[12], fmt[13]]);
+                    if channels == 0 || sample_rate == 0 || block_align == 0 {
+                        return Err(invalid("empty format"));
+                    }
+                    format = Some((sample_rate, channels, block_align));
+                    size - 16
+                }
+                b"data" => {
+                    let (sample_rate, channels, block_align) =
+                        format.ok_or_else(|| invalid("data chunk before format chunk"))?;
+                    let available = file
+                        .metadata()?
+                        .len()
+                        .saturating_sub(file.stream_position()?);
+                    return Ok(Self {
+                        sample_rate,
+                        channels,
+                        frames: size.min(available) / block_align as u64,
+                    });
+                }
+                _ => size,
+            };
+            // Chunks are padded to an even size
+            file.seek(SeekFrom::Current((unread + size % 2) as i64))?;
+        }
+    }
+
+    /// Playing time of the file
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Follow a file playback until it ends, updating the position in `state`
+///
+/// The position is the wall-clock time since playback started, which is
+/// what CamillaDSP has played as long as it keeps up. Past the duration of
+/// the file, the playback ends once CamillaDSP leaves the Running state, or
+/// after END_OF_FILE_GRACE. It then starts over when looping, otherwise
+/// CamillaDSP is stopped and the state goes back to Idle.
+async fn track_playback(
+    client: Arc<CamillaWebSocketClient>,
+    process: Arc<Mutex<CamillaDSPProcess>>,
+    meter_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    state: SharedAudioStreamState,
+    duration: Duration,
+) {
+    let mut ticker = tokio::time::interval(POSITION_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut started = Instant::now();
+    loop {
+        ticker.tick().await;
+        let elapsed = started.elapsed();
+        let looping = {
+            let Ok(mut current) = state.lock() else {
+                return;
+            };
+            if current.state != AudioState::Playing {
+                return;
+            }
+            current.position_seconds = elapsed.min(duration).as_secs_f64();
+            current.looping
+        };
+        if elapsed < duration {
+            continue;
+        }
+        let running = matches!(client.get_state().await.as_deref(), Ok("Running"));
+        if running && elapsed < duration + END_OF_FILE_GRACE {
+            continue;
+        }
+
+        if looping {
+            // Loading the config again reopens the file
+            let restarted = match client.get_config().await {
+                Ok(config) => client.set_config(config).await,
+                Err(e) => Err(e),
+            };
+            match restarted {
+                Ok(()) => {
+                    println!("[AudioManager] End of file, playing again");
+                    started = Instant::now();
+                    continue;
+                }
+                Err(e) => println!("[AudioManager] Failed to loop playback: {}", e),
+            }
+        }
+
+        println!("[AudioManager] End of file, stopping playback");
+        if let Some(task) = meter_task.lock().ok().and_then(|mut task| task.take()) {
+            task.abort();
+        }
+        let _ = client.stop().await;
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Ok(mut process) = process.lock() {
+                let _ = process.stop();
+            }
+        })
+        .await;
+        if let Ok(mut current) = state.lock() {
+            current.state = AudioState::Idle;
+            current.position_seconds = 0.0;
+            current.current_file = None;
+        }
+        return;
+    }
+}
+
 // ============================================================================
 // Audio Manager - High-Level API
 // ============================================================================
@@ -826,6 +1298,10 @@ pub struct AudioManager {
     process: Arc<Mutex<CamillaDSPProcess>>,
     state: SharedAudioStreamState,
     temp_config_file: Arc<Mutex<Option<NamedTempFile>>>,
+    meter: broadcast::Sender<MeterReading>,
+    meter_interval: Mutex<Duration>,
+    meter_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    playback_task: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl AudioManager {
@@ -836,6 +1312,10 @@ impl AudioManager {
             process: Arc::new(Mutex::new(process)),
             state: Arc::new(Mutex::new(AudioStreamState::default())),
             temp_config_file: Arc::new(Mutex::new(None)),
+            meter: broadcast::channel(METER_CHANNEL_CAPACITY).0,
+            meter_interval: Mutex::new(DEFAULT_METER_INTERVAL),
+            meter_task: Arc::new(Mutex::new(None)),
+            playback_task: Mutex::new(None),
         }
     }
 
@@ -877,6 +1357,8 @@ impl AudioManager {
                 CamillaError::ProcessCommunicationFailed(format!("Failed to lock state: {}", e))
             })?;
             state.state = AudioState::Idle;
+            state.position_seconds = 0.0;
+            state.duration_seconds = None;
             state.current_file = Some(audio_file.clone());
             state.output_device = output_device.clone();
             state.sample_rate = sample_rate;
@@ -894,6 +1376,37 @@ impl AudioManager {
             return Err(CamillaError::IOError(error));
         }
 
+        // The file sets the sample rate, CamillaDSP does not resample it
+        let wav = match WavInfo::read(&audio_file) {
+            Ok(wav) => wav,
+            Err(e) => {
+                self.set_error(&e.to_string())?;
+                return Err(e);
+            }
+        };
+        if wav.channels != 2 {
+            let error = format!(
+                "Playback needs a stereo file (got {} channels)",
+                wav.channels
+            );
+            self.set_error(&error)?;
+            return Err(CamillaError::InvalidConfiguration(error));
+        }
+        if wav.sample_rate != sample_rate {
+            println!(
+                "[AudioManager] Playing at the file sample rate {}Hz instead of {}Hz",
+                wav.sample_rate, sample_rate
+            );
+        }
+        let sample_rate = wav.sample_rate;
+        {
+            let mut state = self.state.lock().map_err(|e| {
+                CamillaError::ProcessCommunicationFailed(format!("Failed to lock state: {}", e))
+            })?;
+            state.sample_rate = sample_rate;
+            state.duration_seconds = Some(wav.duration().as_secs_f64());
+        }
+
         // Generate config
         let config = generate_playback_config(
             &audio_file,
@@ -929,14 +1442,7 @@ impl AudioManager {
         }
 
         // Wait for WebSocket to be ready and verify connection
-        let ws_url = {
-            let process = self.process.lock().map_err(|e| {
-                CamillaError::ProcessCommunicationFailed(format!("Failed to lock process: {}", e))
-            })?;
-            process.websocket_url()
-        };
-
-        let client = CamillaWebSocketClient::new(ws_url);
+        let client = self.client()?;
         // Use shorter retry for faster startup
         client
             .connect_with_retry(3, Duration::from_millis(300))
@@ -951,6 +1457,9 @@ impl AudioManager {
             state.position_seconds = 0.0;
         }
 
+        self.start_metering()?;
+        self.start_tracking(client, wav.duration())?;
+
         println!("[AudioManager] Playback started successfully");
         Ok(())
     }
@@ -958,9 +1467,11 @@ impl AudioManager {
     /// Stop playback
     pub async fn stop_playback(&self) -> CamillaResult<()> {
         println!("[AudioManager] Stopping playback");
+        self.stop_tracking()?;
+        self.stop_metering()?;
 
         // Try to stop via WebSocket first
-        let ws_url = {
+        let client = {
             let mut process = self.process.lock().map_err(|e| {
                 CamillaError::ProcessCommunicationFailed(format!("Failed to lock process: {}", e))
             })?;
@@ -968,10 +1479,9 @@ impl AudioManager {
                 println!("[AudioManager] Process not running, nothing to stop");
                 return Ok(());
             }
-            process.websocket_url()
+            process.client()
         };
 
-        let client = CamillaWebSocketClient::new(ws_url);
         let _ = client.stop().await; // Ignore errors, we'll kill the process anyway
 
         // Stop the process
@@ -1051,15 +1561,7 @@ impl AudioManager {
         let config_yaml = serde_yaml::to_string(&config)?;
 
         // Send config update via WebSocket
-        let ws_url = {
-            let process = self.process.lock().map_err(|e| {
-                CamillaError::ProcessCommunicationFailed(format!("Failed to lock process: {}", e))
-            })?;
-            process.websocket_url()
-        };
-
-        let client = CamillaWebSocketClient::new(ws_url);
-        client.set_config(config_yaml).await?;
+        self.client()?.set_config(config_yaml).await?;
 
         // Update state with new filters
         {
@@ -1132,6 +1634,8 @@ impl AudioManager {
             process.start(config_path)?;
         }
 
+        self.start_metering()?;
+
         println!("[AudioManager] Recording started");
         Ok(())
     }
@@ -1160,15 +1664,115 @@ impl AudioManager {
 
     /// Get signal peak from WebSocket (for VU meters)
     pub async fn get_signal_peak(&self) -> CamillaResult<f32> {
-        let ws_url = {
-            let process = self.process.lock().map_err(|e| {
-                CamillaError::ProcessCommunicationFailed(format!("Failed to lock process: {}", e))
-            })?;
-            process.websocket_url()
-        };
+        self.client()?.get_playback_signal_peak().await
+    }
 
-        let client = CamillaWebSocketClient::new(ws_url);
-        client.get_playback_signal_peak().await
+    /// Receive the level readings while audio is running
+    ///
+    /// Readings are published whether or not anybody listens, so VU meters
+    /// can subscribe at any time instead of polling.
+    pub fn subscribe_meter(&self) -> broadcast::Receiver<MeterReading> {
+        self.meter.subscribe()
+    }
+
+    /// Set the time between two metering polls, applied right away if metering runs
+    pub fn set_meter_interval(&self, interval: Duration) -> CamillaResult<()> {
+        if interval.is_zero() {
+            return Err(CamillaError::InvalidConfiguration(
+                "Metering interval must be greater than 0".to_string(),
+            ));
+        }
+        *self.meter_interval.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock meter: {}", e))
+        })? = interval;
+        let running = self
+            .meter_task
+            .lock()
+            .map_err(|e| {
+                CamillaError::ProcessCommunicationFailed(format!("Failed to lock meter: {}", e))
+            })?
+            .is_some();
+        if running {
+            self.start_metering()?;
+        }
+        Ok(())
+    }
+
+    /// Start the metering task, replacing a running one
+    fn start_metering(&self) -> CamillaResult<()> {
+        let interval = *self.meter_interval.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock meter: {}", e))
+        })?;
+        let client = self.client()?;
+        let mut task = self.meter_task.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock meter: {}", e))
+        })?;
+        if let Some(previous) = task.take() {
+            previous.abort();
+        }
+        *task = Some(tokio::spawn(run_metering(client, interval, self.meter.clone())));
+        Ok(())
+    }
+
+    /// Stop the metering task
+    fn stop_metering(&self) -> CamillaResult<()> {
+        let mut task = self.meter_task.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock meter: {}", e))
+        })?;
+        if let Some(task) = task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    /// Loop the file at its end instead of stopping, from the current playback on
+    pub fn set_looping(&self, looping: bool) -> CamillaResult<()> {
+        let mut state = self.state.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock state: {}", e))
+        })?;
+        state.looping = looping;
+        Ok(())
+    }
+
+    /// Start following the playback position, replacing a running tracker
+    fn start_tracking(
+        &self,
+        client: Arc<CamillaWebSocketClient>,
+        duration: Duration,
+    ) -> CamillaResult<()> {
+        let mut task = self.playback_task.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock playback: {}", e))
+        })?;
+        if let Some(previous) = task.take() {
+            previous.abort();
+        }
+        *task = Some(tokio::spawn(track_playback(
+            client,
+            Arc::clone(&self.process),
+            Arc::clone(&self.meter_task),
+            Arc::clone(&self.state),
+            duration,
+        )));
+        Ok(())
+    }
+
+    /// Stop following the playback position
+    fn stop_tracking(&self) -> CamillaResult<()> {
+        let mut task = self.playback_task.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock playback: {}", e))
+        })?;
+        if let Some(task) = task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    /// WebSocket client of the CamillaDSP process, for the commands not wrapped here
+    pub fn client(&self) -> CamillaResult<Arc<CamillaWebSocketClient>> {
+        let mut process = self.process.lock().map_err(|e| {
+            CamillaError::ProcessCommunicationFailed(format!("Failed to lock process: {}", e))
+        })?;
+        Ok(process.client())
     }
 
     /// Set error state
@@ -1269,9 +1873,12 @@ pub fn generate_playback_config(
         playback,
     };
 
+    // Lower the level before the EQ so its boosts cannot clip
+    let preamp_db = playback_preamp_db(filters, sample_rate)?;
+
     // Generate filters section
     let filters_section = if !filters.is_empty() {
-        Some(generate_filters_yaml(filters)?)
+        Some(generate_filters(filters, preamp_db)?)
     } else {
         None
     };
@@ -1285,15 +1892,16 @@ pub fn generate_playback_config(
     };
 
     // Generate mixers section (stereo routing)
-    let mixers_section = Some(generate_stereo_mixer_yaml(map_mode, mixer_out_channels, left_dest, right_dest));
+    let mixers_section = Some(generate_stereo_mixer(map_mode, mixer_out_channels, left_dest, right_dest));
 
     // Generate pipeline - always include mixer; add filters if any
-    let pipeline = Some(generate_pipeline(mixer_out_channels, filters));
+    let pipeline = Some(generate_pipeline(mixer_out_channels, filters, preamp_db < 0.0));
 
     Ok(CamillaDSPConfig {
         devices,
         filters: filters_section,
         mixers: mixers_section,
+        processors: None,
         pipeline,
     })
 }
@@ -1362,6 +1970,7 @@ pub fn generate_recording_config(
         devices,
         filters: None,
         mixers: None,
+        processors: None,
         pipeline: None,
     })
 }
@@ -1409,83 +2018,122 @@ fn map_input_device(device: Option<&str>) -> CamillaResult<(String, Option<Strin
     map_output_device(device)
 }
 
-/// Generate the filters section as YAML
-fn generate_filters_yaml(filters: &[FilterParams]) -> CamillaResult<serde_yaml::Value> {
-    let mut filters_map = serde_yaml::Mapping::new();
+/// CamillaDSP biquads without a [`FilterType`], passed through as given
+fn passthrough_biquad(filter: &FilterParams) -> Option<BiquadParameters> {
+    let (freq, q, gain) = (filter.frequency, filter.q, filter.gain);
+    Some(match filter.filter_type.as_str() {
+        "HighpassFO" => BiquadParameters::HighpassFO { freq },
+        "LowpassFO" => BiquadParameters::LowpassFO { freq },
+        "HighshelfFO" => BiquadParameters::HighshelfFO { freq, gain },
+        "LowshelfFO" => BiquadParameters::LowshelfFO { freq, gain },
+        "Allpass" => BiquadParameters::Allpass(NotchWidth::Q { freq, q }),
+        "AllpassFO" => BiquadParameters::AllpassFO { freq },
+        _ => return None,
+    })
+}
 
-    for (idx, filter) in filters.iter().enumerate() {
-        let filter_name = format!("peq{}", idx + 1);
+/// Q of the second-order shelf standing in for a first-order one
+const FIRST_ORDER_SHELF_Q: f64 = 0.5;
 
-        let mut params = serde_yaml::Mapping::new();
-        params.insert(
-            serde_yaml::Value::String("type".to_string()),
-            serde_yaml::Value::String(filter.filter_type.clone()),
-        );
-        params.insert(
-            serde_yaml::Value::String("freq".to_string()),
-            serde_yaml::Value::Number(serde_yaml::Number::from(filter.frequency as i64)),
-        );
-        params.insert(
-            serde_yaml::Value::String("gain".to_string()),
-            serde_yaml::Value::Number(serde_yaml::Number::from(filter.gain as i64)),
-        );
-        params.insert(
-            serde_yaml::Value::String("q".to_string()),
-            serde_yaml::Value::Number(serde_yaml::Number::from(filter.q as i64)),
-        );
+/// Playback filters decoded into typed filters, for the preamp
+///
+/// Passed-through biquads are approximated: first-order shelves by
+/// second-order ones with the same gain, and the others, which never boost,
+/// are left out.
+fn decode_playback_filters(filters: &[FilterParams]) -> CamillaResult<Vec<DecodedFilter>> {
+    let mut decoded = Vec::with_capacity(filters.len());
+    for filter in filters {
+        let shelf = match filter.filter_type.as_str() {
+            "HighshelfFO" => FilterType::Highshelf,
+            "LowshelfFO" => FilterType::Lowshelf,
+            _ if passthrough_biquad(filter).is_some() => continue,
+            _ => {
+                decoded.push(
+                    DecodedFilter::try_from(filter).map_err(CamillaError::InvalidConfiguration)?,
+                );
+                continue;
+            }
+        };
+        decoded.push(DecodedFilter {
+            filter_type: shelf,
+            frequency: filter.frequency,
+            q: FIRST_ORDER_SHELF_Q,
+            gain: filter.gain,
+        });
+    }
+    Ok(decoded)
+}
 
-        let mut filter_config = serde_yaml::Mapping::new();
-        filter_config.insert(
-            serde_yaml::Value::String("type".to_string()),
-            serde_yaml::Value::String("Biquad".to_string()),
-        );
-        filter_config.insert(
-            serde_yaml::Value::String("parameters".to_string()),
-            serde_yaml::Value::Mapping(params),
-        );
+/// Recommended preamp of playback filters
+fn playback_preamp_db(filters: &[FilterParams], sample_rate: u32) -> CamillaResult<f64> {
+    let decoded = decode_playback_filters(filters)?;
+    Ok(recommended_preamp_db(&decoded, sample_rate as f64))
+}
+
+/// Name of the Gain filter holding the preamp
+const PREAMP_FILTER: &str = "preamp";
 
-        filters_map.insert(
-            serde_yaml::Value::String(filter_name),
-            serde_yaml::Value::Mapping(filter_config),
+/// Generate the filters section: the preamp if any, then one biquad per filter
+fn generate_filters(
+    filters: &[FilterParams],
+    preamp_db: f64,
+) -> CamillaResult<BTreeMap<String, Filter>> {
+    let mut section = BTreeMap::new();
+
+    if preamp_db < 0.0 {
+        section.insert(
+            PREAMP_FILTER.to_string(),
+            Filter::Gain(GainParameters::new(preamp_db)),
         );
     }
 
-    Ok(serde_yaml::Value::Mapping(filters_map))
+    for (idx, filter) in filters.iter().enumerate() {
+        let biquad = match passthrough_biquad(filter) {
+            Some(biquad) => biquad,
+            None => BiquadParameters::from(
+                &DecodedFilter::try_from(filter).map_err(CamillaError::InvalidConfiguration)?,
+            ),
+        };
+        section.insert(format!("peq{}", idx + 1), Filter::Biquad(biquad));
+    }
+
+    Ok(section)
 }
 
 /// Generate a stereo mixer configuration
-fn generate_stereo_mixer_yaml(map_mode: ChannelMapMode, out_channels: u16, left_dest: u16, right_dest: u16) -> serde_yaml::Value {
-    let (l_src, r_src) = match map_mode { ChannelMapMode::Normal => (0,1), ChannelMapMode::Swap => (1,0) };
-    // Build YAML dynamically
-    let yaml = format!(
-        r#"
-        stereo_mixer:
-          channels:
-            in: 2
-            out: {out}
-          mapping:
-            - dest: {ld}
-              sources:
-                - channel: {ls}
-                  gain: 0
-                  inverted: false
-            - dest: {rd}
-              sources:
-                - channel: {rs}
-                  gain: 0
-                  inverted: false
-        "#,
-        out = out_channels,
-        ld = left_dest,
-        ls = l_src,
-        rd = right_dest,
-        rs = r_src
-    );
-    serde_yaml::from_str::<serde_yaml::Value>(&yaml).unwrap()
+fn generate_stereo_mixer(
+    map_mode: ChannelMapMode,
+    out_channels: u16,
+    left_dest: u16,
+    right_dest: u16,
+) -> BTreeMap<String, Mixer> {
+    let (l_src, r_src) = match map_mode {
+        ChannelMapMode::Normal => (0, 1),
+        ChannelMapMode::Swap => (1, 0),
+    };
+    let mixer = Mixer {
+        channels: MixerChannels {
+            input: 2,
+            output: out_channels,
+        },
+        mapping: vec![
+            MixerMapping {
+                dest: left_dest,
+                mute: None,
+                sources: vec![MixerSource::new(l_src)],
+            },
+            MixerMapping {
+                dest: right_dest,
+                mute: None,
+                sources: vec![MixerSource::new(r_src)],
+            },
+        ],
+    };
+    BTreeMap::from([("stereo_mixer".to_string(), mixer)])
 }
 
 /// Generate the pipeline
-fn generate_pipeline(channels: u16, filters: &[FilterParams]) -> Vec<PipelineStep> {
+fn generate_pipeline(channels: u16, filters: &[FilterParams], with_preamp: bool) -> Vec<PipelineStep> {
     let mut pipeline = Vec::new();
 
     // Always add mixer first
@@ -1498,8 +2146,11 @@ fn generate_pipeline(channels: u16, filters: &[FilterParams]) -> Vec<PipelineSte
 
     // Add filters for each channel
     if !filters.is_empty() {
-        let filter_names: Vec<String> = (0..filters.len())
-            .map(|idx| format!("peq{}", idx + 1))
+        // The preamp comes first so the boosts have headroom
+        let filter_names: Vec<String> = with_preamp
+            .then(|| PREAMP_FILTER.to_string())
+            .into_iter()
+            .chain((0..filters.len()).map(|idx| format!("peq{}", idx + 1)))
             .collect();
 
         for ch in 0..channels {
@@ -1615,6 +2266,7 @@ pub fn find_camilladsp_binary() -> CamillaResult<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_filter_params_validation() {
@@ -1696,7 +2348,7 @@ mod tests {
             FilterParams::new(10000.0, 2.0, 1.5),
         ];
 
-        let config = generate_playback_config(&audio_file, None, 48000, 2, &filters, ChannelMapMode::Normal).unwrap();
+        let config = generate_playback_config(&audio_file, None, 48000, 2, &filters, ChannelMapMode::Normal, None).unwrap();
 
         assert_eq!(config.devices.samplerate, 48000);
         assert_eq!(config.devices.playback.channels, Some(2));
@@ -1711,17 +2363,70 @@ mod tests {
         let audio_file = PathBuf::from("/tmp/test.wav");
         let filters = vec![];
 
-        let config = generate_playback_config(&audio_file, None, 44100, 2, &filters, ChannelMapMode::Normal).unwrap();
+        let config = generate_playback_config(&audio_file, None, 44100, 2, &filters, ChannelMapMode::Normal, None).unwrap();
 
         assert_eq!(config.devices.samplerate, 44100);
         assert!(config.filters.is_none());
         assert!(config.pipeline.is_none());
     }
 
+    #[test]
+    fn test_preamp_filter_and_pipeline() {
+        let filters = vec![
+            FilterParams::new(100.0, 1.0, 3.0),
+            FilterParams::new(1000.0, 1.5, -2.0),
+        ];
+        let preamp_db = playback_preamp_db(&filters, 48000).unwrap();
+        assert!(preamp_db < -2.5 && preamp_db > -3.5);
+
+        let section = generate_filters(&filters, preamp_db).unwrap();
+        assert_eq!(
+            section[PREAMP_FILTER],
+            Filter::Gain(GainParameters::new(preamp_db))
+        );
+        let yaml = serde_yaml::to_string(&section).unwrap();
+        assert!(yaml.contains("type: Gain"));
+        // Fractional values are kept, not truncated
+        assert!(yaml.contains("q: 1.5"));
+        assert!(yaml.contains("gain: -2.0"));
+
+        let pipeline = generate_pipeline(2, &filters, true);
+        let names = pipeline[1].names.as_ref().unwrap();
+        assert_eq!(names, &vec!["preamp".to_string(), "peq1".to_string(), "peq2".to_string()]);
+
+        // Cuts only: no preamp needed
+        let cuts = vec![FilterParams::new(1000.0, 1.5, -2.0)];
+        assert_eq!(playback_preamp_db(&cuts, 48000).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_passthrough_filter_types() {
+        let filter = |filter_type: &str, gain: f64| FilterParams {
+            filter_type: filter_type.to_string(),
+            ..FilterParams::new(80.0, 0.7, gain)
+        };
+        let filters = vec![filter("HighpassFO", 0.0), filter("Allpass", 0.0), filter("LowshelfFO", 4.0)];
+        assert!(filters.iter().all(|f| f.validate().is_ok()));
+
+        let section = generate_filters(&filters, 0.0).unwrap();
+        assert_eq!(section["peq1"], Filter::Biquad(BiquadParameters::HighpassFO { freq: 80.0 }));
+        assert_eq!(
+            section["peq3"],
+            Filter::Biquad(BiquadParameters::LowshelfFO { freq: 80.0, gain: 4.0 })
+        );
+        // The first-order shelf counts in the preamp
+        let preamp_db = playback_preamp_db(&filters, 48000).unwrap();
+        assert!(preamp_db < -3.5 && preamp_db > -4.5, "preamp {}", preamp_db);
+
+        let unknown = filter("LinkwitzTransform", 0.0);
+        assert!(unknown.validate().unwrap_err().to_string().contains("LinkwitzTransform"));
+        assert!(generate_filters(&[unknown], 0.0).is_err());
+    }
+
     #[test]
     fn test_generate_recording_config() {
         let output_file = PathBuf::from("/tmp/recording.wav");
-        let config = generate_recording_config(&output_file, None, 48000, 2).unwrap();
+        let config = generate_recording_config(&output_file, None, 48000, 2, None).unwrap();
 
         assert_eq!(config.devices.samplerate, 48000);
         assert_eq!(config.devices.playback.channels, Some(2));
@@ -1734,7 +2439,7 @@ mod tests {
         let audio_file = PathBuf::from("/tmp/test.wav");
         let filters = vec![FilterParams::new(1000.0, 1.0, 3.0)];
 
-        let config = generate_playback_config(&audio_file, None, 48000, 2, &filters, ChannelMapMode::Normal).unwrap();
+        let config = generate_playback_config(&audio_file, None, 48000, 2, &filters, ChannelMapMode::Normal, None).unwrap();
         let yaml = serde_yaml::to_string(&config).unwrap();
 
         // Verify YAML contains expected fields
@@ -1788,46 +2493,248 @@ mod tests {
 
     #[test]
     fn test_command_serialization() {
-        // Build JSON the same way send_command does
-        let cmd = CamillaCommand::GetState;
-        let json = match cmd { CamillaCommand::GetState => serde_json::json!({"GetState": {}}).to_string(), _ => String::new() };
-        assert!(json.contains("GetState"));
+        assert_eq!(CamillaCommand::GetState.to_json(), r#""GetState""#);
+        assert_eq!(CamillaCommand::Stop.to_json(), r#""Stop""#);
 
-        let cmd = CamillaCommand::Stop;
-        let json = match cmd { CamillaCommand::Stop => serde_json::json!({"Stop": {}}).to_string(), _ => String::new() };
-        assert!(json.contains("Stop"));
+        let json = CamillaCommand::SetConfig {
+            config: "test config".to_string(),
+        }
+        .to_json();
+        assert_eq!(json, r#"{"SetConfig":"test config"}"#);
+
+        assert_eq!(
+            CamillaCommand::SetVolume { volume: -12.5 }.to_json(),
+            r#"{"SetVolume":-12.5}"#
+        );
+        assert_eq!(
+            CamillaCommand::SetMute { mute: true }.to_json(),
+            r#"{"SetMute":true}"#
+        );
+    }
 
-        let cmd = CamillaCommand::SetConfig { config: "test config".to_string() };
-        let json = match cmd { CamillaCommand::SetConfig { ref config } => serde_json::json!({"SetConfig": {"config": config}}).to_string(), _ => String::new() };
-        assert!(json.contains("SetConfig"));
-        assert!(json.contains("test config"));
+    fn queued(name: &'static str) -> (QueuedCommand, oneshot::Receiver<CamillaResult<String>>) {
+        let (reply, response) = oneshot::channel();
+        let command = QueuedCommand {
+            name,
+            message: String::new(),
+            reply,
+        };
+        (command, response)
     }
 
     #[test]
-    fn test_response_deserialization() {
-        // Test State response
-        let json = r#"{"state":"Playing"}"#;
-        let response: CamillaResponse = serde_json::from_str(json).unwrap();
-        match response {
-            CamillaResponse::State { state } => assert_eq!(state, "Playing"),
-            _ => panic!("Wrong response type"),
-        }
+    fn test_response_matching() {
+        let (state, mut state_rx) = queued("GetState");
+        let (peak, mut peak_rx) = queued("GetPlaybackSignalPeak");
+        let (config, mut config_rx) = queued("GetConfig");
+        let mut pending = VecDeque::from([state, peak, config]);
+
+        // The second command is answered: the first one will not be
+        let text = r#"{"GetPlaybackSignalPeak":{"result":"Ok","value":-3.5}}"#;
+        resolve_response(&mut pending, text.to_string());
+        assert!(state_rx.try_recv().unwrap().is_err());
+        assert_eq!(peak_rx.try_recv().unwrap().unwrap(), text);
+
+        // Responses nobody waits for are ignored
+        resolve_response(&mut pending, r#"{"GetVolume":{"result":"Ok"}}"#.to_string());
+        assert_eq!(pending.len(), 1);
+
+        resolve_response(&mut pending, r#"{"Invalid":{"error":"bad"}}"#.to_string());
+        assert!(config_rx.try_recv().unwrap().unwrap().contains("Invalid"));
+        assert!(pending.is_empty());
+    }
+
+    /// CamillaDSP stand-in for the state and metering commands
+    ///
+    /// It drops the connection on Stop, and counts one more clipped sample at
+    /// each GetClippedSamples.
+    async fn fake_camilla() -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        tokio::spawn(async move {
+            let mut clipped = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(text))) = socket.next().await {
+                    let name: String = serde_json::from_str(&text).unwrap_or_default();
+                    let value = match name.as_str() {
+                        "GetState" => serde_json::json!("Running"),
+                        "GetSignalLevels" => serde_json::json!({
+                            "playback_rms": [-20.0, -21.0], "playback_peak": [-6.0, -3.0],
+                            "capture_rms": [-18.0, -19.0], "capture_peak": [-4.0, -5.0]
+                        }),
+                        "GetClippedSamples" => {
+                            clipped += 1;
+                            serde_json::json!(clipped)
+                        }
+                        "GetBufferLevel" => serde_json::json!(512),
+                        _ => break,
+                    };
+                    let reply = serde_json::json!({ name: {"result": "Ok", "value": value} });
+                    socket.send(Message::Text(reply.to_string())).await.unwrap();
+                }
+            }
+        });
+        (url, connections)
+    }
 
-        // Test Error response
-        let json = r#"{"error":"Something went wrong"}"#;
-        let response: CamillaResponse = serde_json::from_str(json).unwrap();
-        match response {
-            CamillaResponse::Error { error } => assert_eq!(error, "Something went wrong"),
-            _ => panic!("Wrong response type"),
+    #[tokio::test]
+    async fn test_persistent_connection() {
+        let (url, connections) = fake_camilla().await;
+        let client = CamillaWebSocketClient::new(url);
+        for _ in 0..3 {
+            assert_eq!(client.get_state().await.unwrap(), "Running");
         }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // The command in flight fails with the connection, the next one reconnects
+        assert!(client.stop().await.is_err());
+        assert_eq!(client.get_state().await.unwrap(), "Running");
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_metering_stream() {
+        let (url, _) = fake_camilla().await;
+        let client = Arc::new(CamillaWebSocketClient::new(url));
+        let (meter, mut readings) = broadcast::channel(METER_CHANNEL_CAPACITY);
+        let task = tokio::spawn(run_metering(client, Duration::from_millis(10), meter));
+
+        let first = readings.recv().await.unwrap();
+        assert_eq!(first.playback_peak, vec![-6.0, -3.0]);
+        assert_eq!(first.capture_rms, vec![-18.0, -19.0]);
+        assert_eq!(first.playback_peak_max(), -3.0);
+        assert_eq!(first.buffer_level, 512);
+        assert!(!first.clipping);
+
+        // The counter went up between the two polls
+        let second = readings.recv().await.unwrap();
+        assert!(second.clipped_samples > first.clipped_samples);
+        assert!(second.clipping);
+        task.abort();
+    }
+
+    /// 16-bit stereo WAV header with an odd-sized chunk before the data
+    fn wav_header(sample_rate: u32, data_size: u32) -> Vec<u8> {
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes()); // PCM
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend((sample_rate * 4).to_le_bytes());
+        wav.extend(4u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"LIST\x03\0\0\0abc\0");
+        wav.extend(b"data");
+        wav.extend(data_size.to_le_bytes());
+        wav
+    }
 
-        // Test SignalPeak response
-        let json = r#"{"value":-12.5}"#;
-        let response: CamillaResponse = serde_json::from_str(json).unwrap();
-        match response {
-            CamillaResponse::SignalPeak { value } => assert_eq!(value, -12.5),
-            _ => panic!("Wrong response type"),
+    #[test]
+    fn test_wav_info() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&wav_header(44100, 4 * 4410)).unwrap();
+        file.write_all(&vec![0u8; 4 * 4410]).unwrap();
+        let wav = WavInfo::read(file.path()).unwrap();
+        assert_eq!(wav.sample_rate, 44100);
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.frames, 4410);
+        assert!((wav.duration().as_secs_f64() - 0.1).abs() < 1e-9);
+
+        // A data size past the end of the file is cut to the samples present
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&wav_header(48000, u32::MAX)).unwrap();
+        file.write_all(&vec![0u8; 4 * 100]).unwrap();
+        assert_eq!(WavInfo::read(file.path()).unwrap().frames, 100);
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"ID3 not a wav file").unwrap();
+        assert!(WavInfo::read(file.path()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_playback_goes_idle_at_end_of_file() {
+        let (url, _) = fake_camilla().await;
+        let client = Arc::new(CamillaWebSocketClient::new(url));
+        let process = Arc::new(Mutex::new(CamillaDSPProcess::new(PathBuf::from(
+            "/usr/local/bin/camilladsp",
+        ))));
+        let state = Arc::new(Mutex::new(AudioStreamState {
+            state: AudioState::Playing,
+            current_file: Some(PathBuf::from("/tmp/test.wav")),
+            ..AudioStreamState::default()
+        }));
+        let duration = Duration::from_millis(200);
+        let task = tokio::spawn(track_playback(
+            client,
+            process,
+            Arc::new(Mutex::new(None)),
+            Arc::clone(&state),
+            duration,
+        ));
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let position = state.lock().unwrap().position_seconds;
+        assert!(position > 0.0 && position <= 0.2);
+
+        // The fake stays Running, so the playback ends after the grace time
+        tokio::time::timeout(duration + END_OF_FILE_GRACE * 2, task)
+            .await
+            .unwrap()
+            .unwrap();
+        let state = state.lock().unwrap();
+        assert_eq!(state.state, AudioState::Idle);
+        assert_eq!(state.position_seconds, 0.0);
+        assert!(state.current_file.is_none());
+    }
+
+    #[test]
+    fn test_response_deserialization() {
+        // State response
+        let json = r#"{"GetState":{"result":"Ok","value":"Running"}}"#;
+        let state: String = reply_value(json, "GetState").unwrap();
+        assert_eq!(state, "Running");
+
+        // Error response
+        let json = r#"{"SetConfig":{"result":"Error","value":"Something went wrong"}}"#;
+        match reply_value::<()>(json, "SetConfig") {
+            Err(CamillaError::ProcessCommunicationFailed(message)) => {
+                assert!(message.contains("Something went wrong"))
+            }
+            other => panic!("Wrong response type: {:?}", other),
         }
+
+        // Signal peak response, one level per channel
+        let json = r#"{"GetPlaybackSignalPeak":{"result":"Ok","value":[-12.5,-3.0]}}"#;
+        let levels: ChannelLevels = reply_value(json, "GetPlaybackSignalPeak").unwrap();
+        assert_eq!(levels.loudest(), -3.0);
+        let json = r#"{"GetPlaybackSignalPeak":{"result":"Ok","value":-12.5}}"#;
+        let levels: ChannelLevels = reply_value(json, "GetPlaybackSignalPeak").unwrap();
+        assert_eq!(levels.loudest(), -12.5);
+
+        // Typed values
+        let json = r#"{"GetSignalLevels":{"result":"Ok","value":{
+            "playback_rms":[-20.0,-21.0],"playback_peak":[-6.0,-7.0],
+            "capture_rms":[-18.0,-19.0],"capture_peak":[-4.0,-5.0]}}}"#;
+        let levels: SignalLevels = reply_value(json, "GetSignalLevels").unwrap();
+        assert_eq!(levels.capture_peak, vec![-4.0, -5.0]);
+
+        let json = r#"{"GetStopReason":{"result":"Ok","value":{"CaptureFormatChange":44100}}}"#;
+        let reason: StopReason = reply_value(json, "GetStopReason").unwrap();
+        assert_eq!(reason, StopReason::CaptureFormatChange(44100));
+        let json = r#"{"GetStopReason":{"result":"Ok","value":"Done"}}"#;
+        let reason: StopReason = reply_value(json, "GetStopReason").unwrap();
+        assert_eq!(reason, StopReason::Done);
+
+        let json = r#"{"GetMute":{"result":"Ok","value":true}}"#;
+        assert!(reply_value::<bool>(json, "GetMute").unwrap());
+
+        // Unparsable commands
+        let json = r#"{"Invalid":{"error":"unknown command"}}"#;
+        assert!(reply_value::<()>(json, "GetState").is_err());
     }
 
     #[test]
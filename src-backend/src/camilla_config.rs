@@ -0,0 +1,757 @@
+//! Typed CamillaDSP configuration
+//!
+//! Mirrors the YAML configuration read by CamillaDSP: devices, filters,
+//! mixers, processors and the pipeline. Values are plain `f64` and serialize
+//! at full precision, so a Q of 0.707 or a gain of 2.5 dB reaches the DSP
+//! unchanged.
+
+use crate::filters::{DecodedFilter, FilterType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// ============================================================================
+// Top Level
+// ============================================================================
+
+/// Top-level CamillaDSP configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CamillaDSPConfig {
+    pub devices: DeviceConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<BTreeMap<String, Filter>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mixers: Option<BTreeMap<String, Mixer>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processors: Option<BTreeMap<String, Processor>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<Vec<PipelineStep>>,
+}
+
+// ============================================================================
+// Devices
+// ============================================================================
+
+/// Audio device configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    pub samplerate: u32,
+    pub chunksize: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<CaptureDevice>,
+    pub playback: PlaybackDevice,
+}
+
+/// Capture device configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureDevice {
+    #[serde(rename = "type")]
+    pub device_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_map: Option<Vec<u16>>,
+}
+
+/// Playback device configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackDevice {
+    #[serde(rename = "type")]
+    pub device_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_map: Option<Vec<u16>>,
+}
+
+// ============================================================================
+// Filters
+// ============================================================================
+
+/// Filter definition, referenced by name from the pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "parameters")]
+pub enum Filter {
+    Biquad(BiquadParameters),
+    Gain(GainParameters),
+    Delay(DelayParameters),
+    Conv(ConvParameters),
+    Loudness(LoudnessParameters),
+    Volume(VolumeParameters),
+}
+
+/// Biquad filter, one variant per CamillaDSP subtype
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BiquadParameters {
+    Free {
+        a1: f64,
+        a2: f64,
+        b0: f64,
+        b1: f64,
+        b2: f64,
+    },
+    Highpass {
+        freq: f64,
+        q: f64,
+    },
+    Lowpass {
+        freq: f64,
+        q: f64,
+    },
+    Peaking(PeakingWidth),
+    Highshelf(ShelfSteepness),
+    Lowshelf(ShelfSteepness),
+    HighpassFO {
+        freq: f64,
+    },
+    LowpassFO {
+        freq: f64,
+    },
+    HighshelfFO {
+        freq: f64,
+        gain: f64,
+    },
+    LowshelfFO {
+        freq: f64,
+        gain: f64,
+    },
+    Notch(NotchWidth),
+    GeneralNotch {
+        freq_p: f64,
+        freq_z: f64,
+        q_p: f64,
+        #[serde(default)]
+        normalize_at_dc: bool,
+    },
+    Bandpass(NotchWidth),
+    Allpass(NotchWidth),
+    AllpassFO {
+        freq: f64,
+    },
+    LinkwitzTransform {
+        freq_act: f64,
+        q_act: f64,
+        freq_target: f64,
+        q_target: f64,
+    },
+}
+
+/// Width of a peaking filter, as a Q or in octaves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PeakingWidth {
+    Q {
+        freq: f64,
+        gain: f64,
+        q: f64,
+    },
+    Bandwidth {
+        freq: f64,
+        gain: f64,
+        bandwidth: f64,
+    },
+}
+
+/// Steepness of a shelf filter, as a Q or a slope in dB/octave
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ShelfSteepness {
+    Q { freq: f64, gain: f64, q: f64 },
+    Slope { freq: f64, gain: f64, slope: f64 },
+}
+
+/// Width of a notch, bandpass or allpass filter, as a Q or in octaves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NotchWidth {
+    Q { freq: f64, q: f64 },
+    Bandwidth { freq: f64, bandwidth: f64 },
+}
+
+impl From<&DecodedFilter> for BiquadParameters {
+    fn from(filter: &DecodedFilter) -> Self {
+        let (freq, q, gain) = (filter.frequency, filter.q, filter.gain);
+        match filter.filter_type {
+            FilterType::Peak => BiquadParameters::Peaking(PeakingWidth::Q { freq, gain, q }),
+            FilterType::Lowpass => BiquadParameters::Lowpass { freq, q },
+            FilterType::Highpass | FilterType::HighpassVariableQ => {
+                BiquadParameters::Highpass { freq, q }
+            }
+            FilterType::Lowshelf => BiquadParameters::Lowshelf(ShelfSteepness::Q { freq, gain, q }),
+            FilterType::Highshelf => {
+                BiquadParameters::Highshelf(ShelfSteepness::Q { freq, gain, q })
+            }
+            FilterType::Bandpass => BiquadParameters::Bandpass(NotchWidth::Q { freq, q }),
+            FilterType::Notch => BiquadParameters::Notch(NotchWidth::Q { freq, q }),
+        }
+    }
+}
+
+/// Scale of a gain value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GainScale {
+    #[serde(rename = "dB")]
+    Decibel,
+    #[serde(rename = "linear")]
+    Linear,
+}
+
+/// Constant gain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GainParameters {
+    pub gain: f64, // dB unless `scale` says linear
+    #[serde(default)]
+    pub inverted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<GainScale>,
+}
+
+impl GainParameters {
+    /// Gain of `gain_db`, not inverted
+    pub fn new(gain_db: f64) -> Self {
+        Self {
+            gain: gain_db,
+            inverted: false,
+            mute: None,
+            scale: None,
+        }
+    }
+}
+
+/// Unit of a delay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DelayUnit {
+    Ms,
+    Us,
+    Samples,
+    Mm,
+}
+
+/// Constant delay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DelayParameters {
+    pub delay: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<DelayUnit>, // ms when missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subsample: Option<bool>,
+}
+
+/// Sample format of a raw impulse response file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RawFormat {
+    Text,
+    S16le,
+    S24le,
+    S24le3,
+    S32le,
+    Float32le,
+    Float64le,
+}
+
+/// FIR convolution, with the impulse response from a file or inline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ConvParameters {
+    Raw {
+        filename: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        format: Option<RawFormat>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        skip_bytes_lines: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        read_bytes_lines: Option<usize>,
+    },
+    Wav {
+        filename: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel: Option<usize>,
+    },
+    Values {
+        values: Vec<f64>,
+    },
+}
+
+/// Volume control driving Volume and Loudness filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fader {
+    Main,
+    Aux1,
+    Aux2,
+    Aux3,
+    Aux4,
+}
+
+/// Loudness compensation following a fader
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessParameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fader: Option<Fader>, // Main when missing
+    pub reference_level: f64, // dB, volume without compensation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high_boost: Option<f64>, // dB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_boost: Option<f64>, // dB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attenuate_mid: Option<bool>,
+}
+
+/// Volume control following an aux fader
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VolumeParameters {
+    pub fader: Fader,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ramp_time: Option<f64>, // ms
+}
+
+// ============================================================================
+// Mixers
+// ============================================================================
+
+/// Mixer routing input channels to output channels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mixer {
+    pub channels: MixerChannels,
+    pub mapping: Vec<MixerMapping>,
+}
+
+/// Channel counts of a mixer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MixerChannels {
+    #[serde(rename = "in")]
+    pub input: u16,
+    #[serde(rename = "out")]
+    pub output: u16,
+}
+
+/// Sources summed into one output channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixerMapping {
+    pub dest: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    pub sources: Vec<MixerSource>,
+}
+
+/// One input channel of a mixer mapping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MixerSource {
+    pub channel: u16,
+    #[serde(default)]
+    pub gain: f64, // dB unless `scale` says linear
+    #[serde(default)]
+    pub inverted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<GainScale>,
+}
+
+impl MixerSource {
+    /// Channel `channel` at unity gain
+    pub fn new(channel: u16) -> Self {
+        Self {
+            channel,
+            gain: 0.0,
+            inverted: false,
+            mute: None,
+            scale: None,
+        }
+    }
+}
+
+// ============================================================================
+// Processors
+// ============================================================================
+
+/// Processor acting on several channels at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "parameters")]
+pub enum Processor {
+    Compressor(CompressorParameters),
+    NoiseGate(NoiseGateParameters),
+}
+
+/// Dynamic range compressor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressorParameters {
+    pub channels: u16,
+    pub attack: f64,    // s
+    pub release: f64,   // s
+    pub threshold: f64, // dB
+    pub factor: f64,    // Compression ratio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub makeup_gain: Option<f64>, // dB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clip_limit: Option<f64>, // dB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_clip: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_channels: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process_channels: Option<Vec<u16>>,
+}
+
+/// Noise gate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseGateParameters {
+    pub channels: u16,
+    pub attack: f64,      // s
+    pub release: f64,     // s
+    pub threshold: f64,   // dB
+    pub attenuation: f64, // dB applied while the gate is closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_channels: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process_channels: Option<Vec<u16>>,
+}
+
+// ============================================================================
+// Pipeline
+// ============================================================================
+
+/// Pipeline step in the processing chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStep {
+    #[serde(rename = "type")]
+    pub step_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub names: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYBACK_CONFIG: &str = r#"
+devices:
+  samplerate: 48000
+  chunksize: 1024
+  capture:
+    type: WavFile
+    filename: /tmp/music.wav
+  playback:
+    type: Alsa
+    device: hw:0
+    channels: 2
+    format: S32LE
+filters:
+  preamp:
+    type: Gain
+    parameters:
+      gain: -3.25
+      inverted: false
+  peq1:
+    type: Biquad
+    parameters:
+      type: Peaking
+      freq: 1234.5
+      gain: 2.5
+      q: 0.707
+  bass:
+    type: Biquad
+    parameters:
+      type: Lowshelf
+      freq: 105.0
+      gain: 6.5
+      slope: 12.0
+  notch:
+    type: Biquad
+    parameters:
+      type: Notch
+      freq: 60.0
+      bandwidth: 0.1
+mixers:
+  stereo:
+    channels:
+      in: 2
+      out: 2
+    mapping:
+      - dest: 0
+        sources:
+          - channel: 1
+            gain: -1.5
+            inverted: true
+      - dest: 1
+        sources:
+          - channel: 0
+            gain: 0.0
+            inverted: false
+pipeline:
+  - type: Mixer
+    name: stereo
+  - type: Filter
+    channel: 0
+    names:
+      - preamp
+      - peq1
+"#;
+
+    const FILTERS_CONFIG: &str = r#"
+devices:
+  samplerate: 44100
+  chunksize: 512
+  playback:
+    type: File
+    filename: /dev/null
+    channels: 2
+    format: FLOAT32LE
+filters:
+  free:
+    type: Biquad
+    parameters:
+      type: Free
+      a1: -1.8
+      a2: 0.81
+      b0: 1.0
+      b1: -1.9
+      b2: 0.9025
+  hp:
+    type: Biquad
+    parameters:
+      type: Highpass
+      freq: 20.5
+      q: 0.5
+  lp:
+    type: Biquad
+    parameters:
+      type: Lowpass
+      freq: 18000.0
+      q: 0.7071
+  hpfo:
+    type: Biquad
+    parameters:
+      type: HighpassFO
+      freq: 30.0
+  lpfo:
+    type: Biquad
+    parameters:
+      type: LowpassFO
+      freq: 15000.0
+  hsfo:
+    type: Biquad
+    parameters:
+      type: HighshelfFO
+      freq: 8000.0
+      gain: -1.25
+  lsfo:
+    type: Biquad
+    parameters:
+      type: LowshelfFO
+      freq: 80.0
+      gain: 3.75
+  hs:
+    type: Biquad
+    parameters:
+      type: Highshelf
+      freq: 6000.0
+      gain: -2.5
+      q: 0.6
+  peak_bw:
+    type: Biquad
+    parameters:
+      type: Peaking
+      freq: 500.0
+      gain: -4.5
+      bandwidth: 0.5
+  general_notch:
+    type: Biquad
+    parameters:
+      type: GeneralNotch
+      freq_p: 40.0
+      freq_z: 45.0
+      q_p: 1.5
+      normalize_at_dc: true
+  bp:
+    type: Biquad
+    parameters:
+      type: Bandpass
+      freq: 1000.0
+      q: 2.5
+  ap:
+    type: Biquad
+    parameters:
+      type: Allpass
+      freq: 300.0
+      bandwidth: 1.5
+  apfo:
+    type: Biquad
+    parameters:
+      type: AllpassFO
+      freq: 250.0
+  lt:
+    type: Biquad
+    parameters:
+      type: LinkwitzTransform
+      freq_act: 45.0
+      q_act: 0.9
+      freq_target: 25.0
+      q_target: 0.707
+  delay:
+    type: Delay
+    parameters:
+      delay: 1.25
+      unit: ms
+      subsample: true
+  room:
+    type: Conv
+    parameters:
+      type: Raw
+      filename: /tmp/room.raw
+      format: FLOAT32LE
+      skip_bytes_lines: 4
+  wav_ir:
+    type: Conv
+    parameters:
+      type: Wav
+      filename: /tmp/room.wav
+      channel: 1
+  short_ir:
+    type: Conv
+    parameters:
+      type: Values
+      values:
+        - 1.0
+        - 0.5
+        - 0.25
+  loudness:
+    type: Loudness
+    parameters:
+      fader: Main
+      reference_level: -25.0
+      high_boost: 7.0
+      low_boost: 7.5
+      attenuate_mid: false
+  volume:
+    type: Volume
+    parameters:
+      fader: Aux1
+      ramp_time: 200.0
+  linear:
+    type: Gain
+    parameters:
+      gain: 0.5
+      inverted: false
+      mute: false
+      scale: linear
+processors:
+  compressor:
+    type: Compressor
+    parameters:
+      channels: 2
+      attack: 0.025
+      release: 1.0
+      threshold: -25.0
+      factor: 5.0
+      makeup_gain: 15.0
+      soft_clip: true
+      process_channels:
+        - 0
+        - 1
+  gate:
+    type: NoiseGate
+    parameters:
+      channels: 2
+      attack: 0.01
+      release: 0.5
+      threshold: -60.0
+      attenuation: 40.0
+pipeline:
+  - type: Processor
+    name: compressor
+"#;
+
+    fn round_trip(yaml: &str) -> CamillaDSPConfig {
+        let config: CamillaDSPConfig = serde_yaml::from_str(yaml).unwrap();
+        let written = serde_yaml::to_string(&config).unwrap();
+        let reread: CamillaDSPConfig = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(config, reread);
+        // Nothing lost compared to the sample itself
+        let original: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let rewritten: serde_yaml::Value = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(original, rewritten);
+        config
+    }
+
+    #[test]
+    fn test_playback_round_trip() {
+        let config = round_trip(PLAYBACK_CONFIG);
+        let filters = config.filters.unwrap();
+        assert_eq!(
+            filters["peq1"],
+            Filter::Biquad(BiquadParameters::Peaking(PeakingWidth::Q {
+                freq: 1234.5,
+                gain: 2.5,
+                q: 0.707,
+            }))
+        );
+        assert_eq!(
+            filters["bass"],
+            Filter::Biquad(BiquadParameters::Lowshelf(ShelfSteepness::Slope {
+                freq: 105.0,
+                gain: 6.5,
+                slope: 12.0,
+            }))
+        );
+        assert_eq!(
+            config.mixers.unwrap()["stereo"].mapping[0].sources[0].gain,
+            -1.5
+        );
+    }
+
+    #[test]
+    fn test_all_filter_kinds_round_trip() {
+        let config = round_trip(FILTERS_CONFIG);
+        let filters = config.filters.unwrap();
+        assert_eq!(filters.len(), 21);
+        assert!(matches!(
+            filters["room"],
+            Filter::Conv(ConvParameters::Raw {
+                format: Some(RawFormat::Float32le),
+                ..
+            })
+        ));
+        assert!(matches!(
+            filters["linear"],
+            Filter::Gain(GainParameters {
+                scale: Some(GainScale::Linear),
+                ..
+            })
+        ));
+        assert_eq!(config.processors.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_full_precision() {
+        let filter = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: 1234.5678,
+            q: 0.707,
+            gain: 2.5,
+        };
+        let yaml = serde_yaml::to_string(&Filter::Biquad((&filter).into())).unwrap();
+        assert!(yaml.contains("freq: 1234.5678"));
+        assert!(yaml.contains("q: 0.707"));
+        assert!(yaml.contains("gain: 2.5"));
+    }
+}
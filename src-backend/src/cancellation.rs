@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Why a token reports itself as cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// `cancel()` was called on the token or one of its ancestors
+    Requested,
+    /// The deadline of the token or one of its ancestors has passed
+    DeadlineExceeded,
+}
+
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CancelReason::Requested => write!(f, "Optimization cancelled"),
+            CancelReason::DeadlineExceeded => write!(f, "Optimization time budget exceeded"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+    parent: Option<CancellationToken>,
+}
+
+/// Shared cancellation token
+///
+/// Clones share the same state, so cancelling any clone reaches every holder.
+/// A child token is cancelled together with its parent, but cancelling the
+/// child leaves the parent running.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    fn build(parent: Option<CancellationToken>, deadline: Option<Instant>) -> Self {
+        Self {
+            inner: Arc::new(TokenInner {
+                cancelled: AtomicBool::new(false),
+                deadline: Mutex::new(deadline),
+                parent,
+            }),
+        }
+    }
+
+    pub fn new() -> Self {
+        Self::build(None, None)
+    }
+
+    /// Token that cancels itself at `deadline`
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self::build(None, Some(deadline))
+    }
+
+    /// Token that cancels itself after `budget`
+    pub fn with_timeout(budget: Duration) -> Self {
+        Self::with_deadline(Instant::now() + budget)
+    }
+
+    /// Token cancelled with this one, that can also be cancelled on its own
+    pub fn child(&self) -> Self {
+        Self::build(Some(self.clone()), None)
+    }
+
+    /// Child token with its own time budget
+    pub fn child_with_timeout(&self, budget: Duration) -> Self {
+        Self::build(Some(self.clone()), Some(Instant::now() + budget))
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Clear the cancellation flag and deadline of this token (not its parents)
+    pub fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::Relaxed);
+        self.set_deadline(None);
+    }
+
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        if let Ok(mut current) = self.inner.deadline.lock() {
+            *current = deadline;
+        }
+    }
+
+    /// Earliest deadline of this token and its ancestors
+    pub fn deadline(&self) -> Option<Instant> {
+        let own = self.inner.deadline.lock().ok().and_then(|d| *d);
+        let inherited = self.inner.parent.as_ref().and_then(|p| p.deadline());
+        match (own, inherited) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Time left before the deadline, if any
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline()
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Why the token is cancelled, or `None` while it is still live
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        if self.inner.cancelled.load(Ordering::Relaxed) {
+            return Some(CancelReason::Requested);
+        }
+        let expired = self
+            .inner
+            .deadline
+            .lock()
+            .ok()
+            .and_then(|d| *d)
+            .is_some_and(|d| Instant::now() >= d);
+        if expired {
+            return Some(CancelReason::DeadlineExceeded);
+        }
+        self.inner.parent.as_ref().and_then(|p| p.cancel_reason())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_reason().is_some()
+    }
+}
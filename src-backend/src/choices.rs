@@ -0,0 +1,268 @@
+//! Typed choices for the loss function, optimization algorithm and PEQ model
+//!
+//! Each enum (de)serializes to the same strings the UI and autoeq use
+//! (`"speaker-flat"`, `"nlopt:cobyla"`, `"hp-pk"`, ...). Unknown strings are
+//! rejected instead of silently falling back to a default.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// One entry of a UI dropdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChoiceInfo {
+    pub value: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+}
+
+macro_rules! choice_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $kind:literal {
+            $( $(#[$vmeta:meta])* $variant:ident => $value:literal $(| $alias:literal)*, $label:literal, $description:literal; )+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub enum $name {
+            $($(#[$vmeta])* $variant),+
+        }
+
+        impl $name {
+            /// All variants, in the order UIs should list them
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// Canonical string value
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+
+            /// Short human readable name
+            pub fn label(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $label),+
+                }
+            }
+
+            /// One sentence explaining the choice
+            pub fn description(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $description),+
+                }
+            }
+
+            /// Valid values with labels and descriptions, for dropdowns
+            pub fn choices() -> Vec<ChoiceInfo> {
+                Self::ALL
+                    .iter()
+                    .map(|c| ChoiceInfo {
+                        value: c.as_str(),
+                        label: c.label(),
+                        description: c.description(),
+                    })
+                    .collect()
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($value $(| $alias)* => Ok($name::$variant),)+
+                    _ => Err(format!(
+                        "Unknown {} '{}' (expected one of: {})",
+                        $kind,
+                        s,
+                        Self::ALL
+                            .iter()
+                            .map(|c| c.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = String;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                value.as_str().to_string()
+            }
+        }
+    };
+}
+
+choice_enum! {
+    /// Loss function minimised by the optimizer
+    Loss, "loss" {
+        SpeakerFlat => "speaker-flat" | "flat", "Speaker Flat",
+            "Flatten the selected speaker curve towards the target";
+        SpeakerScore => "speaker-score" | "score", "Speaker Score",
+            "Maximise the speaker preference score computed from the CEA2034 data";
+        HeadphoneFlat => "headphone-flat", "Headphone Flat",
+            "Flatten the headphone response towards the target";
+        HeadphoneScore => "headphone-score", "Headphone Score",
+            "Maximise the headphone preference score (Olive et al.)";
+    }
+}
+
+impl Loss {
+    pub fn is_headphone(&self) -> bool {
+        matches!(self, Loss::HeadphoneFlat | Loss::HeadphoneScore)
+    }
+
+    pub fn is_flat(&self) -> bool {
+        matches!(self, Loss::SpeakerFlat | Loss::HeadphoneFlat)
+    }
+}
+
+impl From<Loss> for autoeq::LossType {
+    fn from(loss: Loss) -> Self {
+        match loss {
+            Loss::SpeakerFlat => autoeq::LossType::SpeakerFlat,
+            Loss::SpeakerScore => autoeq::LossType::SpeakerScore,
+            Loss::HeadphoneFlat => autoeq::LossType::HeadphoneFlat,
+            Loss::HeadphoneScore => autoeq::LossType::HeadphoneScore,
+        }
+    }
+}
+
+choice_enum! {
+    /// Global optimization algorithm
+    Algorithm, "algorithm" {
+        AutoeqDe => "autoeq:de", "Auto DE (Recommended)",
+            "Adaptive differential evolution with progress reporting";
+        NloptIsres => "nlopt:isres", "NLOPT ISRES",
+            "Improved stochastic ranking evolution strategy, handles constraints";
+        NloptAgs => "nlopt:ags", "NLOPT AGS",
+            "Derivative-free global search for low dimensional problems";
+        NloptOrigDirect => "nlopt:origdirect", "NLOPT Original DIRECT",
+            "Original DIviding RECTangles global search";
+        NloptCrs2Lm => "nlopt:crs2lm", "NLOPT CRS2 LM",
+            "Controlled random search with local mutation";
+        NloptDirect => "nlopt:direct", "NLOPT DIRECT",
+            "DIviding RECTangles global search";
+        NloptDirectL => "nlopt:directl", "NLOPT DIRECT-L",
+            "Locally biased DIRECT, faster on problems with few local minima";
+        NloptGmlsl => "nlopt:gmlsl", "NLOPT GMLSL",
+            "Multi-level single-linkage with random starting points";
+        NloptGmlslLds => "nlopt:gmlsllds", "NLOPT GMLSL LDS",
+            "Multi-level single-linkage with low-discrepancy starting points";
+        NloptStogo => "nlopt:stogo", "NLOPT StoGO",
+            "Branch-and-bound global search with local gradient steps";
+        NloptStogoRand => "nlopt:stogorand", "NLOPT StoGO Rand",
+            "Randomized StoGO";
+        NloptCobyla => "nlopt:cobyla", "NLOPT COBYLA",
+            "Local search by linear approximations, handles constraints";
+        NloptBobyqa => "nlopt:bobyqa", "NLOPT BOBYQA",
+            "Local search by quadratic approximations within bounds";
+        NloptNelderMead => "nlopt:neldermead", "NLOPT Nelder-Mead",
+            "Local simplex search";
+        NloptSbplx => "nlopt:sbplx", "NLOPT Subplex",
+            "Nelder-Mead on subspaces, more robust on larger problems";
+        NloptSlsqp => "nlopt:slsqp", "NLOPT SLSQP",
+            "Sequential quadratic programming with numerical gradients";
+        MhDe => "mh:de", "MH Differential Evolution",
+            "Metaheuristic differential evolution with progress reporting";
+        MhPso => "mh:pso", "MH Particle Swarm",
+            "Particle swarm optimization with progress reporting";
+        MhRga => "mh:rga", "MH Genetic Algorithm",
+            "Real-coded genetic algorithm with progress reporting";
+        MhTlbo => "mh:tlbo", "MH TLBO",
+            "Teaching-learning based optimization with progress reporting";
+        MhFirefly => "mh:firefly", "MH Firefly",
+            "Firefly algorithm with progress reporting";
+        GreedyPeaks => "greedy:peaks", "Greedy Peak Picking",
+            "Fits a peak filter to the largest deviation, one at a time; instant preview";
+    }
+}
+
+impl Algorithm {
+    pub fn is_metaheuristic(&self) -> bool {
+        self.as_str().starts_with("mh:")
+    }
+
+    pub fn is_nlopt(&self) -> bool {
+        self.as_str().starts_with("nlopt:")
+    }
+}
+
+choice_enum! {
+    /// Layout of the filter types in the PEQ
+    #[derive(Default)]
+    PeqModel, "PEQ model" {
+        #[default]
+        Pk => "pk", "Peak",
+            "All filters are peak/bell filters";
+        HpPk => "hp-pk", "Highpass + Peak",
+            "First filter is highpass, rest are peak filters";
+        HpPkLp => "hp-pk-lp", "Highpass + Peak + Lowpass",
+            "First filter is highpass, last is lowpass, rest are peak filters";
+        FreePkFree => "free-pk-free", "Free + Peak + Free",
+            "First and last filters can be any type, middle filters are peak";
+        Free => "free", "Free",
+            "All filters can be any type";
+    }
+}
+
+choice_enum! {
+    /// How several measurements of the same device are combined into one curve
+    #[derive(Default)]
+    CurveAggregate, "aggregate" {
+        #[default]
+        Mean => "mean", "Weighted Mean",
+            "Minimizes the weighted mean squared error over the measurements";
+        Median => "median", "Median",
+            "Follows the weighted median, robust to a few outlier measurements";
+        WorstCase => "worst-case", "Worst Case",
+            "Minimizes the largest error over the measurements at each frequency";
+    }
+}
+
+impl From<PeqModel> for autoeq::cli::PeqModel {
+    fn from(model: PeqModel) -> Self {
+        match model {
+            PeqModel::Pk => autoeq::cli::PeqModel::Pk,
+            PeqModel::HpPk => autoeq::cli::PeqModel::HpPk,
+            PeqModel::HpPkLp => autoeq::cli::PeqModel::HpPkLp,
+            PeqModel::FreePkFree => autoeq::cli::PeqModel::FreePkFree,
+            PeqModel::Free => autoeq::cli::PeqModel::Free,
+        }
+    }
+}
+
+/// All dropdown choices of the optimization form
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizationChoices {
+    pub losses: Vec<ChoiceInfo>,
+    pub algorithms: Vec<ChoiceInfo>,
+    pub peq_models: Vec<ChoiceInfo>,
+    pub aggregates: Vec<ChoiceInfo>,
+}
+
+pub fn optimization_choices() -> OptimizationChoices {
+    OptimizationChoices {
+        losses: Loss::choices(),
+        algorithms: Algorithm::choices(),
+        peq_models: PeqModel::choices(),
+        aggregates: CurveAggregate::choices(),
+    }
+}
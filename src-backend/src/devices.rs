@@ -0,0 +1,358 @@
+//! Hardware EQ device profiles
+//!
+//! Many hardware EQs only accept gains in fixed steps, a list of Q values, a
+//! fixed frequency grid and a limited number of bands. A [`DeviceProfile`]
+//! describes these limits: it narrows the optimizer bounds, rejects
+//! parameters the device cannot play and snaps the result to values the
+//! device accepts.
+
+use crate::export::ExportFormat;
+use crate::filters::{DecodedFilter, FilterType, model_filter_types};
+use crate::optim::OptimizationParams;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// ISO octave band centres in Hz
+const ISO_OCTAVE_BANDS: [f64; 10] = [
+    31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// ISO third-octave band centres in Hz
+const ISO_THIRD_OCTAVE_BANDS: [f64; 31] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0,
+    500.0, 630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0,
+    8000.0, 10000.0, 12500.0, 16000.0, 20000.0,
+];
+
+/// Half width, relative to the Q, of the search window around a single allowed Q
+const SINGLE_Q_WINDOW: f64 = 0.05;
+
+/// Limits of a hardware EQ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub id: String,
+    pub name: String,
+    pub max_filters: usize,            // Number of bands
+    pub filter_types: Vec<FilterType>, // Types the device supports
+    pub min_gain: f64,                 // dB
+    pub max_gain: f64,
+    pub gain_step: Option<f64>, // dB, None = continuous
+    pub min_q: f64,
+    pub max_q: f64,
+    pub q_values: Option<Vec<f64>>, // Allowed Q values, None = continuous
+    pub frequencies: Option<Vec<f64>>, // Band frequencies in Hz, None = continuous
+    pub export_format: ExportFormat, // Format the device imports
+}
+
+/// Profiles shipped with the application
+pub fn device_profiles() -> Vec<DeviceProfile> {
+    vec![
+        DeviceProfile {
+            id: "graphic-10".to_string(),
+            name: "10-band graphic EQ".to_string(),
+            max_filters: ISO_OCTAVE_BANDS.len(),
+            filter_types: vec![FilterType::Peak],
+            min_gain: -12.0,
+            max_gain: 12.0,
+            gain_step: Some(1.0),
+            min_q: 1.41,
+            max_q: 1.41,
+            q_values: Some(vec![1.41]),
+            frequencies: Some(ISO_OCTAVE_BANDS.to_vec()),
+            export_format: ExportFormat::ParametricEQ,
+        },
+        DeviceProfile {
+            id: "graphic-31".to_string(),
+            name: "31-band graphic EQ".to_string(),
+            max_filters: ISO_THIRD_OCTAVE_BANDS.len(),
+            filter_types: vec![FilterType::Peak],
+            min_gain: -12.0,
+            max_gain: 12.0,
+            gain_step: Some(0.5),
+            min_q: 4.32,
+            max_q: 4.32,
+            q_values: Some(vec![4.32]),
+            frequencies: Some(ISO_THIRD_OCTAVE_BANDS.to_vec()),
+            export_format: ExportFormat::ParametricEQ,
+        },
+        DeviceProfile {
+            id: "peq-5-fixed-q".to_string(),
+            name: "5-band parametric EQ, third-octave frequencies and fixed Q values".to_string(),
+            max_filters: 5,
+            filter_types: vec![
+                FilterType::Peak,
+                FilterType::Lowshelf,
+                FilterType::Highshelf,
+            ],
+            min_gain: -15.0,
+            max_gain: 15.0,
+            gain_step: Some(0.5),
+            min_q: 0.5,
+            max_q: 8.0,
+            q_values: Some(vec![0.5, 0.7, 1.0, 1.4, 2.0, 2.8, 4.0, 5.6, 8.0]),
+            frequencies: Some(ISO_THIRD_OCTAVE_BANDS.to_vec()),
+            export_format: ExportFormat::ParametricEQ,
+        },
+        DeviceProfile {
+            id: "peq-10-half-db".to_string(),
+            name: "10-band parametric EQ, 0.5 dB gain steps".to_string(),
+            max_filters: 10,
+            filter_types: vec![
+                FilterType::Peak,
+                FilterType::Lowshelf,
+                FilterType::Highshelf,
+                FilterType::Highpass,
+                FilterType::HighpassVariableQ,
+                FilterType::Lowpass,
+            ],
+            min_gain: -12.0,
+            max_gain: 12.0,
+            gain_step: Some(0.5),
+            min_q: 0.2,
+            max_q: 10.0,
+            q_values: None,
+            frequencies: None,
+            export_format: ExportFormat::CamillaDSP,
+        },
+    ]
+}
+
+impl FromStr for DeviceProfile {
+    type Err = String;
+
+    /// Look up a built-in profile by id
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let profiles = device_profiles();
+        profiles
+            .iter()
+            .find(|profile| profile.id == s)
+            .cloned()
+            .ok_or_else(|| {
+                let ids: Vec<&str> = profiles.iter().map(|p| p.id.as_str()).collect();
+                format!(
+                    "Unknown device profile '{}' (expected one of: {})",
+                    s,
+                    ids.join(", ")
+                )
+            })
+    }
+}
+
+/// Element of `values` closest to `value` on a log scale
+fn nearest_log(values: &[f64], value: f64) -> Option<f64> {
+    values.iter().copied().min_by(|a, b| {
+        let da = (a.ln() - value.ln()).abs();
+        let db = (b.ln() - value.ln()).abs();
+        da.total_cmp(&db)
+    })
+}
+
+impl DeviceProfile {
+    /// Range of Q values the device accepts
+    fn q_range(&self) -> (f64, f64) {
+        match &self.q_values {
+            Some(values) if !values.is_empty() => (
+                values.iter().copied().fold(f64::INFINITY, f64::min),
+                values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ),
+            _ => (self.min_q, self.max_q),
+        }
+    }
+
+    /// Check that `params` can be played on this device
+    pub fn check_params(&self, params: &OptimizationParams) -> Result<(), String> {
+        // Bounded pinned filters take optimizer slots, fixed ones come on top
+        let fixed = params
+            .pinned_filters
+            .iter()
+            .filter(|pinned| pinned.range.is_none())
+            .count();
+        if params.num_filters + fixed > self.max_filters {
+            return Err(format!(
+                "Device '{}' has {} bands but {} filters are requested",
+                self.name,
+                self.max_filters,
+                params.num_filters + fixed
+            ));
+        }
+
+        let types = model_filter_types(params.peq_model, params.num_filters).ok_or_else(|| {
+            format!(
+                "Device profiles need a PEQ model with fixed filter types (got: {})",
+                params.peq_model
+            )
+        })?;
+        let pinned = params.pinned_filters.iter().map(|p| p.filter.filter_type);
+        if let Some(unsupported) = types
+            .into_iter()
+            .chain(pinned)
+            .find(|t| !self.filter_types.contains(t))
+        {
+            return Err(format!(
+                "Device '{}' does not support {} filters",
+                self.name,
+                unsupported.camilla_name()
+            ));
+        }
+
+        let (min_q, max_q) = self.q_range();
+        if params.max_q < min_q || params.min_q > max_q {
+            return Err(format!(
+                "Q range {}-{} is outside the Q range {}-{} of device '{}'",
+                params.min_q, params.max_q, min_q, max_q, self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Narrow the optimizer bounds to what the device accepts
+    pub fn limit_params(&self, params: &mut OptimizationParams) {
+        let (min_q, max_q) = self.q_range();
+        params.min_q = params.min_q.max(min_q);
+        params.max_q = params.max_q.min(max_q);
+        if params.min_q >= params.max_q {
+            // A single allowed Q: search close to it, snapping does the rest
+            params.min_q = min_q * (1.0 - SINGLE_Q_WINDOW);
+            params.max_q = max_q * (1.0 + SINGLE_Q_WINDOW);
+        }
+        params.max_db = params.max_db.min(self.max_gain.max(-self.min_gain));
+    }
+
+    /// Snap gain and Q of `filter` to allowed values
+    fn snap_shape(&self, filter: &mut DecodedFilter) {
+        filter.q = match &self.q_values {
+            Some(values) => nearest_log(values, filter.q).unwrap_or(filter.q),
+            None => filter.q.clamp(self.min_q, self.max_q),
+        };
+        filter.gain = if filter.filter_type.has_gain() {
+            let gain = filter.gain.clamp(self.min_gain, self.max_gain);
+            match self.gain_step {
+                Some(step) if step > 0.0 => (gain / step).round() * step,
+                _ => gain,
+            }
+        } else {
+            0.0
+        };
+    }
+
+    /// Snap filters to values the device accepts
+    ///
+    /// With a frequency grid every filter gets its own band: the filters with
+    /// the largest gain pick first and take the free band closest to them.
+    /// Bands in `taken` are already used by other filters.
+    pub fn snap_filters(&self, filters: &mut [DecodedFilter], taken: &[f64]) {
+        for filter in filters.iter_mut() {
+            self.snap_shape(filter);
+        }
+
+        let Some(bands) = &self.frequencies else {
+            return;
+        };
+        let mut order: Vec<usize> = (0..filters.len()).collect();
+        order.sort_by(|&a, &b| filters[b].gain.abs().total_cmp(&filters[a].gain.abs()));
+        let mut free: Vec<f64> = bands
+            .iter()
+            .copied()
+            .filter(|band| !taken.contains(band))
+            .collect();
+        for i in order {
+            if let Some(band) = nearest_log(&free, filters[i].frequency) {
+                filters[i].frequency = band;
+                free.retain(|f| *f != band);
+            } else if let Some(band) = nearest_log(bands, filters[i].frequency) {
+                // More filters than bands; check_params rules this out
+                filters[i].frequency = band;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::PeqModel;
+
+    fn peak(frequency: f64, q: f64, gain: f64) -> DecodedFilter {
+        DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency,
+            q,
+            gain,
+        }
+    }
+
+    #[test]
+    fn test_snap_gain_and_q() {
+        let profile: DeviceProfile = "peq-5-fixed-q".parse().unwrap();
+        let mut filters = vec![peak(1100.0, 1.1, 2.3), peak(95.0, 9.0, -20.0)];
+        profile.snap_filters(&mut filters, &[]);
+        assert_eq!(filters[0].frequency, 1000.0);
+        assert_eq!(filters[0].q, 1.0);
+        assert_eq!(filters[0].gain, 2.5);
+        assert_eq!(filters[1].frequency, 100.0);
+        assert_eq!(filters[1].q, 8.0);
+        assert_eq!(filters[1].gain, -15.0);
+    }
+
+    #[test]
+    fn test_snap_gives_each_filter_its_own_band() {
+        let profile: DeviceProfile = "graphic-10".parse().unwrap();
+        let mut filters = vec![peak(110.0, 1.0, 1.0), peak(120.0, 1.0, -4.0)];
+        profile.snap_filters(&mut filters, &[]);
+        // The larger cut takes the 125 Hz band, the other one moves next door
+        assert_eq!(filters[1].frequency, 125.0);
+        assert_eq!(filters[0].frequency, 63.0);
+        assert!(filters.iter().all(|f| f.q == 1.41));
+
+        let mut filters = vec![peak(120.0, 1.0, -4.0)];
+        profile.snap_filters(&mut filters, &[125.0]);
+        assert_eq!(filters[0].frequency, 63.0);
+    }
+
+    #[test]
+    fn test_check_params() {
+        let profile: DeviceProfile = "graphic-10".parse().unwrap();
+        let params = OptimizationParams {
+            num_filters: 8,
+            ..OptimizationParams::default()
+        };
+        assert!(profile.check_params(&params).is_ok());
+
+        let too_many = OptimizationParams {
+            num_filters: 12,
+            ..params.clone()
+        };
+        assert!(profile.check_params(&too_many).is_err());
+
+        let highpass = OptimizationParams {
+            peq_model: PeqModel::HpPk,
+            ..params.clone()
+        };
+        assert!(profile.check_params(&highpass).is_err());
+
+        let free = OptimizationParams {
+            peq_model: PeqModel::Free,
+            ..params
+        };
+        assert!(profile.check_params(&free).is_err());
+    }
+
+    #[test]
+    fn test_limit_params_single_q() {
+        let profile: DeviceProfile = "graphic-10".parse().unwrap();
+        let mut params = OptimizationParams::default();
+        profile.limit_params(&mut params);
+        assert!(params.min_q < 1.41 && params.max_q > 1.41);
+        assert!(params.max_q - params.min_q < 0.2);
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        assert!("no-such-device".parse::<DeviceProfile>().is_err());
+        assert!(
+            device_profiles()
+                .iter()
+                .all(|p| p.id.parse::<DeviceProfile>().is_ok())
+        );
+    }
+}
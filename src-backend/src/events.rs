@@ -0,0 +1,135 @@
+//! Stream of optimization events
+//!
+//! [`optimization_events`] runs an optimization and yields its phases,
+//! progress updates and final result as a `futures::Stream`, for consumers
+//! that would rather `select!`, throttle or forward events than implement
+//! [`ProgressCallback`].
+
+use crate::cancellation::CancellationToken;
+use crate::jobs::runtime_handle;
+use crate::optim::{
+    OptimizationParams, OptimizationPhase, OptimizationResult, ProgressCallback, ProgressUpdate,
+    run_optimization_internal,
+};
+use futures_util::Stream;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// One event of an optimization run
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum OptimizationEvent {
+    Phase(OptimizationPhase),
+    Progress(ProgressUpdate),
+    /// Always the last event, also for failed and cancelled runs
+    Finished(Box<OptimizationResult>),
+}
+
+/// Forwards callbacks into the event channel
+struct ChannelProgressCallback {
+    tx: mpsc::UnboundedSender<OptimizationEvent>,
+}
+
+impl ProgressCallback for ChannelProgressCallback {
+    fn on_progress(&self, update: ProgressUpdate) -> bool {
+        // Stop optimizing once nobody listens anymore
+        self.tx.send(OptimizationEvent::Progress(update)).is_ok()
+    }
+
+    fn on_phase(&self, phase: OptimizationPhase) {
+        let _ = self.tx.send(OptimizationEvent::Phase(phase));
+    }
+}
+
+/// Run an optimization and stream its events
+///
+/// The run starts right away on a worker thread. Dropping the stream stops it
+/// at the next progress update; `cancellation_token` stops it at any time.
+pub fn optimization_events(
+    params: OptimizationParams,
+    cancellation_token: CancellationToken,
+) -> impl Stream<Item = OptimizationEvent> + Send + Unpin + 'static {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let progress_callback = Arc::new(ChannelProgressCallback { tx: tx.clone() });
+
+    let runtime = runtime_handle();
+    runtime.clone().spawn_blocking(move || {
+        let result = match runtime.block_on(run_optimization_internal(
+            params,
+            progress_callback,
+            cancellation_token,
+        )) {
+            Ok(result) => result,
+            Err(e) => OptimizationResult::from_error(&e),
+        };
+        let _ = tx.send(OptimizationEvent::Finished(Box::new(result)));
+    });
+
+    Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mocks::mocks::create_minimal_optimization_params;
+    use futures_util::StreamExt;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_invalid_params_finish_the_stream() {
+        let params = OptimizationParams {
+            num_filters: 0,
+            ..create_minimal_optimization_params()
+        };
+        let events: Vec<OptimizationEvent> = optimization_events(params, CancellationToken::new())
+            .collect()
+            .await;
+
+        // Validation fails before any phase starts
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            OptimizationEvent::Finished(result) => {
+                assert!(!result.success);
+                assert!(!result.cancelled);
+            }
+            other => panic!("expected Finished, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancelled_run_finishes_with_cancelled_result() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut events = optimization_events(create_minimal_optimization_params(), token);
+
+        let last = events.next().await.unwrap();
+        assert!(matches!(last, OptimizationEvent::Finished(ref r) if r.cancelled));
+        assert!(events.next().await.is_none());
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = OptimizationEvent::Phase(OptimizationPhase::TargetBuilt);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "phase");
+        assert_eq!(json["data"], "target_built");
+    }
+
+    #[test]
+    fn test_callback_stops_without_listener() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let callback = ChannelProgressCallback { tx };
+        let update = ProgressUpdate {
+            iteration: 1,
+            fitness: 1.0,
+            params: vec![],
+            convergence: 0.0,
+            evaluations: None,
+        };
+        assert!(callback.on_progress(update.clone()));
+        drop(rx);
+        assert!(!callback.on_progress(update));
+    }
+}
@@ -1,12 +1,52 @@
+use crate::filters::{DecodedFilter, recommended_preamp_db};
+use serde::{Deserialize, Serialize};
 use std::fmt::Write;
+use std::str::FromStr;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ExportFormat {
+    #[serde(rename = "camilladsp")]
     CamillaDSP,
+    #[serde(rename = "parametric-eq")]
     ParametricEQ,
+    #[serde(rename = "rew")]
     REW,
 }
 
+impl ExportFormat {
+    /// All supported export formats
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::CamillaDSP,
+        ExportFormat::ParametricEQ,
+        ExportFormat::REW,
+    ];
+
+    /// Conventional file extension for this format
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::CamillaDSP => "yml",
+            ExportFormat::ParametricEQ => "txt",
+            ExportFormat::REW => "txt",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "camilladsp" | "camilla" => Ok(ExportFormat::CamillaDSP),
+            "parametric-eq" | "parametriceq" | "peq" => Ok(ExportFormat::ParametricEQ),
+            "rew" => Ok(ExportFormat::REW),
+            _ => Err(format!(
+                "Unknown export format '{}' (expected camilladsp, parametric-eq or rew)",
+                s
+            )),
+        }
+    }
+}
+
 /// Single filter parameter for export
 #[derive(Clone, Debug)]
 pub struct FilterParam {
@@ -16,22 +56,53 @@ pub struct FilterParam {
     pub q: f64,
 }
 
+/// Convert decoded filters to export filters
+///
+/// Filters are sorted by frequency for readability.
+pub fn filters_for_export(filters: &[DecodedFilter]) -> Vec<FilterParam> {
+    let mut filters: Vec<FilterParam> = filters.iter().map(FilterParam::from).collect();
+    filters.sort_by(|a, b| a.frequency.partial_cmp(&b.frequency).unwrap_or(std::cmp::Ordering::Equal));
+    filters
+}
+
 /// Export filter parameters to various formats
+///
+/// Every format includes the preamp that keeps the EQ from clipping.
 pub fn export_filters(filters: &[FilterParam], format: ExportFormat, sample_rate: u32) -> Result<String, String> {
+    let preamp_db = preamp_for_export(filters, sample_rate)?;
     match format {
-        ExportFormat::CamillaDSP => export_camilladsp(filters, sample_rate),
-        ExportFormat::ParametricEQ => export_parametric_eq(filters),
-        ExportFormat::REW => export_rew(filters),
+        ExportFormat::CamillaDSP => export_camilladsp(filters, preamp_db, sample_rate),
+        ExportFormat::ParametricEQ => export_parametric_eq(filters, preamp_db),
+        ExportFormat::REW => export_rew(filters, preamp_db),
     }
 }
 
-fn export_camilladsp(filters: &[FilterParam], _sample_rate: u32) -> Result<String, String> {
+/// Recommended preamp of export filters
+fn preamp_for_export(filters: &[FilterParam], sample_rate: u32) -> Result<f64, String> {
+    let decoded = filters
+        .iter()
+        .map(DecodedFilter::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(recommended_preamp_db(&decoded, sample_rate as f64))
+}
+
+/// Preamp rounded down to `decimals`, so the written value never leaves less headroom
+fn floor_preamp(preamp_db: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    // The margin keeps values already on a step where they are
+    (preamp_db * scale + 1e-9).floor() / scale
+}
+
+fn export_camilladsp(filters: &[FilterParam], preamp_db: f64, _sample_rate: u32) -> Result<String, String> {
     let mut output = String::new();
     
     writeln!(&mut output, "# CamillaDSP Configuration").map_err(|e| e.to_string())?;
     writeln!(&mut output, "# Generated by AutoEQ").map_err(|e| e.to_string())?;
     writeln!(&mut output).map_err(|e| e.to_string())?;
     writeln!(&mut output, "filters:").map_err(|e| e.to_string())?;
+    writeln!(&mut output, "  preamp:").map_err(|e| e.to_string())?;
+    writeln!(&mut output, "    type: Gain").map_err(|e| e.to_string())?;
+    writeln!(&mut output, "    gain: {:.2}", floor_preamp(preamp_db, 2)).map_err(|e| e.to_string())?;
     
     for (i, filter) in filters.iter().enumerate() {
         writeln!(&mut output, "  filter{}:", i + 1).map_err(|e| e.to_string())?;
@@ -44,12 +115,13 @@ fn export_camilladsp(filters: &[FilterParam], _sample_rate: u32) -> Result<Strin
     Ok(output)
 }
 
-fn export_parametric_eq(filters: &[FilterParam]) -> Result<String, String> {
+fn export_parametric_eq(filters: &[FilterParam], preamp_db: f64) -> Result<String, String> {
     let mut output = String::new();
     
     writeln!(&mut output, "# Parametric EQ Settings").map_err(|e| e.to_string())?;
     writeln!(&mut output, "# Generated by AutoEQ").map_err(|e| e.to_string())?;
     writeln!(&mut output).map_err(|e| e.to_string())?;
+    writeln!(&mut output, "Preamp: {:.2} dB", floor_preamp(preamp_db, 2)).map_err(|e| e.to_string())?;
     writeln!(&mut output, "Type\tFreq\tGain\tQ").map_err(|e| e.to_string())?;
     
     for filter in filters {
@@ -63,12 +135,13 @@ fn export_parametric_eq(filters: &[FilterParam]) -> Result<String, String> {
     Ok(output)
 }
 
-fn export_rew(filters: &[FilterParam]) -> Result<String, String> {
+fn export_rew(filters: &[FilterParam], preamp_db: f64) -> Result<String, String> {
     let mut output = String::new();
     
     writeln!(&mut output, "# Room EQ Wizard Filter Export").map_err(|e| e.to_string())?;
     writeln!(&mut output, "# Generated by AutoEQ").map_err(|e| e.to_string())?;
     writeln!(&mut output).map_err(|e| e.to_string())?;
+    writeln!(&mut output, "Preamp: {:.1} dB", floor_preamp(preamp_db, 1)).map_err(|e| e.to_string())?;
     
     for (i, filter) in filters.iter().enumerate() {
         writeln!(
@@ -88,10 +161,12 @@ fn export_rew(filters: &[FilterParam]) -> Result<String, String> {
 fn map_filter_type(filter_type: &str) -> &str {
     match filter_type.to_uppercase().as_str() {
         "PK" => "Peaking",
-        "HP" => "HighPass",
+        "HP" | "HPQ" => "HighPass",
         "LP" => "LowPass",
         "HS" => "HighShelf",
         "LS" => "LowShelf",
+        "BP" => "BandPass",
+        "NO" => "Notch",
         _ => filter_type,
     }
 }
@@ -120,10 +195,12 @@ mod tests {
     #[test]
     fn test_export_camilladsp() {
         let filters = get_test_filters();
-        let result = export_camilladsp(&filters, 48000);
+        let result = export_camilladsp(&filters, -2.5, 48000);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("CamillaDSP"));
+        assert!(output.contains("type: Gain"));
+        assert!(output.contains("gain: -2.50"));
         assert!(output.contains("filter1:"));
         assert!(output.contains("freq: 100"));
         assert!(output.contains("gain: 2.5"));
@@ -132,21 +209,93 @@ mod tests {
     #[test]
     fn test_export_parametric_eq() {
         let filters = get_test_filters();
-        let result = export_parametric_eq(&filters);
+        let result = export_parametric_eq(&filters, -2.5);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("Parametric EQ"));
+        assert!(output.contains("Preamp: -2.50 dB"));
         assert!(output.contains("100.0"));
         assert!(output.contains("-3.00"));
     }
 
+    #[test]
+    fn test_preamp_rounds_towards_attenuation() {
+        let filters = get_test_filters();
+        let rew = export_rew(&filters, -2.44).unwrap();
+        assert!(rew.contains("Preamp: -2.5 dB"), "{}", rew);
+        let rew = export_rew(&filters, -2.5).unwrap();
+        assert!(rew.contains("Preamp: -2.5 dB"), "{}", rew);
+        let peq = export_parametric_eq(&filters, -2.444).unwrap();
+        assert!(peq.contains("Preamp: -2.45 dB"), "{}", peq);
+        let camilla = export_camilladsp(&filters, 0.0, 48000).unwrap();
+        assert!(camilla.contains("gain: 0.00"), "{}", camilla);
+    }
+
+    #[test]
+    fn test_export_filters_preamp() {
+        // The 2.5 dB boost at 100 Hz sets the preamp
+        let output = export_filters(&get_test_filters(), ExportFormat::ParametricEQ, 48000).unwrap();
+        let preamp_line = output.lines().find(|l| l.starts_with("Preamp:")).unwrap();
+        let preamp: f64 = preamp_line
+            .trim_start_matches("Preamp:")
+            .trim_end_matches("dB")
+            .trim()
+            .parse()
+            .unwrap();
+        assert!(preamp < -2.0 && preamp > -3.0, "preamp {}", preamp);
+
+        let unknown = vec![FilterParam {
+            filter_type: "XX".to_string(),
+            frequency: 100.0,
+            gain: 1.0,
+            q: 1.0,
+        }];
+        assert!(export_filters(&unknown, ExportFormat::REW, 48000).is_err());
+    }
+
+    #[test]
+    fn test_export_format_from_str() {
+        assert!(matches!("camilladsp".parse::<ExportFormat>(), Ok(ExportFormat::CamillaDSP)));
+        assert!(matches!("Parametric-EQ".parse::<ExportFormat>(), Ok(ExportFormat::ParametricEQ)));
+        assert!(matches!("rew".parse::<ExportFormat>(), Ok(ExportFormat::REW)));
+        assert!("wav".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_filters_for_export() {
+        use crate::filters::FilterType;
+
+        let decoded = vec![
+            DecodedFilter {
+                filter_type: FilterType::Highpass,
+                frequency: 1000.0,
+                q: 2.0,
+                gain: 0.0,
+            },
+            DecodedFilter {
+                filter_type: FilterType::Peak,
+                frequency: 100.0,
+                q: 1.0,
+                gain: 2.5,
+            },
+        ];
+        let filters = filters_for_export(&decoded);
+        assert_eq!(filters.len(), 2);
+        // Sorted by frequency: the 1 kHz highpass comes after the 100 Hz peak
+        assert_eq!(filters[0].filter_type, "PK");
+        assert!((filters[0].frequency - 100.0).abs() < 1e-9);
+        assert_eq!(filters[1].filter_type, "HP");
+        assert!((filters[1].frequency - 1000.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_export_rew() {
         let filters = get_test_filters();
-        let result = export_rew(&filters);
+        let result = export_rew(&filters, -2.5);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("Room EQ Wizard"));
+        assert!(output.contains("Preamp: -2.5 dB"));
         assert!(output.contains("Filter 1:"));
         assert!(output.contains("Fc 100"));
     }
@@ -0,0 +1,513 @@
+//! Typed PEQ filters decoded from the optimizer parameter vector
+//!
+//! The optimizers work on a flat `Vec<f64>`; the filter type of each slot
+//! depends on the PEQ model. Decoding goes through `autoeq::x2peq`, the same
+//! code the loss function uses, so exports and playback apply exactly the
+//! filters that were optimized.
+
+use crate::camilla::FilterParams;
+use crate::choices::PeqModel;
+use crate::export::FilterParam;
+use autoeq::iir::{Biquad, BiquadFilterType};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Shape of a single biquad filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterType {
+    Peak,
+    Lowpass,
+    Highpass,
+    HighpassVariableQ, // First filter of the hp-pk models, its Q is optimized
+    Lowshelf,
+    Highshelf,
+    Bandpass,
+    Notch,
+}
+
+impl FilterType {
+    /// Short code used by the text exports ("PK", "HP", ...)
+    pub fn code(&self) -> &'static str {
+        match self {
+            FilterType::Peak => "PK",
+            FilterType::Lowpass => "LP",
+            FilterType::Highpass => "HP",
+            FilterType::HighpassVariableQ => "HPQ",
+            FilterType::Lowshelf => "LS",
+            FilterType::Highshelf => "HS",
+            FilterType::Bandpass => "BP",
+            FilterType::Notch => "NO",
+        }
+    }
+
+    /// Biquad type name in a CamillaDSP configuration
+    ///
+    /// CamillaDSP's highpass always takes a Q, so both highpass types map to it.
+    pub fn camilla_name(&self) -> &'static str {
+        match self {
+            FilterType::Peak => "Peaking",
+            FilterType::Lowpass => "Lowpass",
+            FilterType::Highpass | FilterType::HighpassVariableQ => "Highpass",
+            FilterType::Lowshelf => "Lowshelf",
+            FilterType::Highshelf => "Highshelf",
+            FilterType::Bandpass => "Bandpass",
+            FilterType::Notch => "Notch",
+        }
+    }
+
+    /// Whether the gain parameter changes the response
+    pub fn has_gain(&self) -> bool {
+        matches!(
+            self,
+            FilterType::Peak | FilterType::Lowshelf | FilterType::Highshelf
+        )
+    }
+
+    fn from_biquad(filter_type: BiquadFilterType) -> Self {
+        match filter_type {
+            BiquadFilterType::Lowpass => FilterType::Lowpass,
+            BiquadFilterType::Highpass => FilterType::Highpass,
+            BiquadFilterType::HighpassVariableQ => FilterType::HighpassVariableQ,
+            BiquadFilterType::Lowshelf => FilterType::Lowshelf,
+            BiquadFilterType::Highshelf => FilterType::Highshelf,
+            BiquadFilterType::Bandpass => FilterType::Bandpass,
+            BiquadFilterType::Notch => FilterType::Notch,
+            _ => FilterType::Peak,
+        }
+    }
+
+    fn to_biquad(self) -> BiquadFilterType {
+        match self {
+            FilterType::Peak => BiquadFilterType::Peak,
+            FilterType::Lowpass => BiquadFilterType::Lowpass,
+            FilterType::Highpass => BiquadFilterType::Highpass,
+            FilterType::HighpassVariableQ => BiquadFilterType::HighpassVariableQ,
+            FilterType::Lowshelf => BiquadFilterType::Lowshelf,
+            FilterType::Highshelf => BiquadFilterType::Highshelf,
+            FilterType::Bandpass => BiquadFilterType::Bandpass,
+            FilterType::Notch => BiquadFilterType::Notch,
+        }
+    }
+}
+
+impl FromStr for FilterType {
+    type Err = String;
+
+    /// Accepts export codes ("PK"), CamillaDSP names ("Peaking") and serde names ("peak")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pk" | "peak" | "peaking" => Ok(FilterType::Peak),
+            "lp" | "lowpass" => Ok(FilterType::Lowpass),
+            "hp" | "highpass" => Ok(FilterType::Highpass),
+            "hpq" | "highpass_variable_q" => Ok(FilterType::HighpassVariableQ),
+            "ls" | "lsc" | "lowshelf" => Ok(FilterType::Lowshelf),
+            "hs" | "hsc" | "highshelf" => Ok(FilterType::Highshelf),
+            "bp" | "bandpass" => Ok(FilterType::Bandpass),
+            "no" | "notch" => Ok(FilterType::Notch),
+            _ => Err(format!("Unknown filter type '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for FilterType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// One filter of an optimized PEQ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodedFilter {
+    pub filter_type: FilterType,
+    pub frequency: f64, // Hz
+    pub q: f64,
+    pub gain: f64, // dB, ignored by filters without gain
+}
+
+impl DecodedFilter {
+    /// Biquad implementing this filter at `sample_rate`
+    pub fn biquad(&self, sample_rate: f64) -> Biquad {
+        Biquad::new(
+            self.filter_type.to_biquad(),
+            self.frequency,
+            sample_rate,
+            self.q,
+            self.gain,
+        )
+    }
+}
+
+impl From<&DecodedFilter> for FilterParam {
+    fn from(filter: &DecodedFilter) -> Self {
+        FilterParam {
+            filter_type: filter.filter_type.code().to_string(),
+            frequency: filter.frequency,
+            gain: filter.gain,
+            q: filter.q,
+        }
+    }
+}
+
+impl TryFrom<&FilterParam> for DecodedFilter {
+    type Error = String;
+
+    fn try_from(filter: &FilterParam) -> Result<Self, Self::Error> {
+        Ok(DecodedFilter {
+            filter_type: filter.filter_type.parse()?,
+            frequency: filter.frequency,
+            q: filter.q,
+            gain: filter.gain,
+        })
+    }
+}
+
+impl TryFrom<&FilterParams> for DecodedFilter {
+    type Error = String;
+
+    fn try_from(filter: &FilterParams) -> Result<Self, Self::Error> {
+        Ok(DecodedFilter {
+            filter_type: filter.filter_type.parse()?,
+            frequency: filter.frequency,
+            q: filter.q,
+            gain: filter.gain,
+        })
+    }
+}
+
+/// Decode an optimizer parameter vector into typed filters
+///
+/// Filters keep the order of the parameter vector, so filter `i` comes from
+/// the `i`-th slot of the PEQ model.
+pub fn decode_filters(x: &[f64], sample_rate: f64, peq_model: PeqModel) -> Vec<DecodedFilter> {
+    autoeq::x2peq::x2peq(x, sample_rate, peq_model.into())
+        .iter()
+        .map(|(_, biquad)| DecodedFilter {
+            filter_type: FilterType::from_biquad(biquad.filter_type),
+            frequency: biquad.freq,
+            q: biquad.q,
+            gain: biquad.db_gain,
+        })
+        .collect()
+}
+
+/// Filter type of slot `index` in a model with fixed types, `None` for free slots
+fn slot_type(peq_model: PeqModel, index: usize, num_filters: usize) -> Option<FilterType> {
+    match peq_model {
+        PeqModel::Pk => Some(FilterType::Peak),
+        PeqModel::HpPk if index == 0 => Some(FilterType::HighpassVariableQ),
+        PeqModel::HpPk => Some(FilterType::Peak),
+        PeqModel::HpPkLp if index == 0 => Some(FilterType::HighpassVariableQ),
+        PeqModel::HpPkLp if index + 1 == num_filters => Some(FilterType::Lowpass),
+        PeqModel::HpPkLp => Some(FilterType::Peak),
+        PeqModel::FreePkFree | PeqModel::Free => None,
+    }
+}
+
+/// Filter types of all slots of a model, `None` when a slot has a free type
+pub(crate) fn model_filter_types(
+    peq_model: PeqModel,
+    num_filters: usize,
+) -> Option<Vec<FilterType>> {
+    (0..num_filters)
+        .map(|i| slot_type(peq_model, i, num_filters))
+        .collect()
+}
+
+/// Parameter slot of each filter, by filter type
+///
+/// Each filter takes the first slot of its type not yet marked in `used`, and
+/// marks it. Models with free filter types have no fixed slots and are
+/// rejected.
+pub fn assign_slots(
+    filters: &[DecodedFilter],
+    peq_model: PeqModel,
+    used: &mut [bool],
+) -> Result<Vec<usize>, String> {
+    let num_filters = used.len();
+    if (0..num_filters).any(|i| slot_type(peq_model, i, num_filters).is_none()) {
+        return Err(format!(
+            "Placing given filters is not supported with the '{}' PEQ model",
+            peq_model
+        ));
+    }
+
+    filters
+        .iter()
+        .map(|filter| {
+            if filter.frequency <= 0.0 || filter.q <= 0.0 {
+                return Err(format!(
+                    "Invalid filter at {} Hz with Q {}",
+                    filter.frequency, filter.q
+                ));
+            }
+            let slot = (0..num_filters)
+                .find(|&i| {
+                    !used[i] && slot_type(peq_model, i, num_filters) == Some(filter.filter_type)
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "No free {} slot in the '{}' PEQ model for the filter at {:.0} Hz",
+                        filter.filter_type, peq_model, filter.frequency
+                    )
+                })?;
+            used[slot] = true;
+            Ok(slot)
+        })
+        .collect()
+}
+
+/// Write `filter` into `slot` of an optimizer parameter vector
+pub fn encode_filter(filter: &DecodedFilter, slot: usize, x: &mut [f64]) {
+    x[slot * 3] = filter.frequency.log10();
+    x[slot * 3 + 1] = filter.q;
+    x[slot * 3 + 2] = filter.gain;
+}
+
+/// Encode filters into an optimizer parameter vector
+///
+/// `fallback` is a full parameter vector (usually `autoeq`'s initial guess)
+/// that also sets the number of filters. Filters are placed with
+/// [`assign_slots`], so a preset sorted by frequency works as well as a
+/// previous result; slots left over keep their fallback values.
+pub fn encode_filters(
+    filters: &[DecodedFilter],
+    peq_model: PeqModel,
+    fallback: &[f64],
+) -> Result<Vec<f64>, String> {
+    let mut used = vec![false; fallback.len() / 3];
+    let slots = assign_slots(filters, peq_model, &mut used)?;
+    let mut x = fallback.to_vec();
+    for (filter, slot) in filters.iter().zip(slots) {
+        encode_filter(filter, slot, &mut x);
+    }
+    Ok(x)
+}
+
+/// Summed response in dB of `filters` at `freq`
+pub fn filters_response(
+    filters: &[DecodedFilter],
+    freq: &Array1<f64>,
+    sample_rate: f64,
+) -> Array1<f64> {
+    filters
+        .iter()
+        .fold(Array1::zeros(freq.len()), |acc, filter| {
+            acc + filter.biquad(sample_rate).np_log_result(freq)
+        })
+}
+
+/// Frequency points used to find the peak of an EQ response
+const PEAK_GRID_POINTS: usize = 1000;
+
+/// Largest boost in dB of the combined response of `filters`, 0 when it only cuts
+pub fn peak_boost_db(filters: &[DecodedFilter], sample_rate: f64) -> f64 {
+    let max_freq = (sample_rate / 2.0).min(20000.0);
+    let freq = Array1::logspace(10.0, 20f64.log10(), max_freq.log10(), PEAK_GRID_POINTS);
+    filters_response(filters, &freq, sample_rate)
+        .iter()
+        .fold(0.0_f64, |peak, gain| peak.max(*gain))
+}
+
+/// Preamp in dB that keeps the EQ from clipping, 0 when it only cuts
+pub fn recommended_preamp_db(filters: &[DecodedFilter], sample_rate: f64) -> f64 {
+    let peak = peak_boost_db(filters, sample_rate);
+    if peak > 0.0 { -peak } else { 0.0 }
+}
+
+/// Range a pinned filter may move in during optimization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterRange {
+    pub min_freq: f64, // Hz
+    pub max_freq: f64,
+    pub min_q: f64,
+    pub max_q: f64,
+    pub min_gain: f64, // dB
+    pub max_gain: f64,
+}
+
+impl FilterRange {
+    /// Bounds of one parameter slot, in optimizer units
+    pub(crate) fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        (
+            [self.min_freq.log10(), self.min_q, self.min_gain],
+            [self.max_freq.log10(), self.max_q, self.max_gain],
+        )
+    }
+}
+
+/// A user filter kept through the optimization
+///
+/// Without a range the filter is fixed: it is applied as part of the
+/// measurement and comes on top of the optimized filters. With a range it
+/// takes one of the PEQ slots of its type and only moves within the range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinnedFilter {
+    #[serde(flatten)]
+    pub filter: DecodedFilter,
+    #[serde(default)]
+    pub range: Option<FilterRange>,
+}
+
+/// How a filter of a result was optimized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterLock {
+    Free,    // Optimized freely
+    Bounded, // Pinned, optimized within its range
+    Fixed,   // Pinned, kept as given
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hp_pk_lp() {
+        let x = vec![1.5, 0.7, 0.0, 3.0, 2.0, -3.0, 4.0, 0.7, 0.0];
+        let filters = decode_filters(&x, 48000.0, PeqModel::HpPkLp);
+        let types: Vec<FilterType> = filters.iter().map(|f| f.filter_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                FilterType::HighpassVariableQ,
+                FilterType::Peak,
+                FilterType::Lowpass
+            ]
+        );
+        assert!((filters[1].frequency - 1000.0).abs() < 1e-6);
+        assert!((filters[1].gain + 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_response_matches_autoeq() {
+        let x = vec![1.6, 1.2, 0.0, 2.5, 2.0, 4.0, 3.4, 0.8, -3.0];
+        let freq = Array1::logspace(10.0, 20f64.log10(), 20000f64.log10(), 200);
+        let ours = filters_response(&decode_filters(&x, 48000.0, PeqModel::HpPk), &freq, 48000.0);
+        let autoeq =
+            autoeq::x2peq::compute_peq_response_from_x(&freq, &x, 48000.0, PeqModel::HpPk.into());
+        for (a, b) in ours.iter().zip(autoeq.iter()) {
+            assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let x = vec![1.5, 0.7, 0.0, 3.0, 2.0, -3.0, 4.0, 0.7, 0.0];
+        let mut filters = decode_filters(&x, 48000.0, PeqModel::HpPkLp);
+        // Slots follow the filter types, not the order of the list
+        filters.reverse();
+        let encoded = encode_filters(&filters, PeqModel::HpPkLp, &[0.0; 9]).unwrap();
+        for (a, b) in encoded.iter().zip(x.iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_encode_keeps_fallback_and_rejects_mismatches() {
+        let peak = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: 100.0,
+            q: 1.0,
+            gain: 2.0,
+        };
+        let fallback = [3.0, 1.0, 0.0, 3.5, 1.0, 0.0];
+        let encoded = encode_filters(std::slice::from_ref(&peak), PeqModel::Pk, &fallback).unwrap();
+        assert_eq!(encoded, vec![2.0, 1.0, 2.0, 3.5, 1.0, 0.0]);
+
+        let lowpass = DecodedFilter {
+            filter_type: FilterType::Lowpass,
+            ..peak.clone()
+        };
+        assert!(encode_filters(&[lowpass], PeqModel::Pk, &fallback).is_err());
+        let invalid = DecodedFilter {
+            q: 0.0,
+            ..peak.clone()
+        };
+        assert!(encode_filters(&[invalid], PeqModel::Pk, &fallback).is_err());
+        assert!(
+            encode_filters(
+                &[peak.clone(), peak.clone(), peak.clone()],
+                PeqModel::Pk,
+                &fallback
+            )
+            .is_err()
+        );
+        assert!(encode_filters(&[peak], PeqModel::Free, &fallback).is_err());
+    }
+
+    #[test]
+    fn test_recommended_preamp() {
+        let boost = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: 1000.0,
+            q: 1.0,
+            gain: 4.0,
+        };
+        let cut = DecodedFilter {
+            gain: -6.0,
+            frequency: 100.0,
+            ..boost.clone()
+        };
+        let preamp = recommended_preamp_db(&[boost, cut.clone()], 48000.0);
+        assert!((preamp + 4.0).abs() < 0.3, "preamp {}", preamp);
+        assert_eq!(recommended_preamp_db(&[cut], 48000.0), 0.0);
+        assert_eq!(recommended_preamp_db(&[], 48000.0), 0.0);
+    }
+
+    #[test]
+    fn test_pinned_filter_serialization() {
+        let json = r#"{"filter_type":"notch","frequency":3150.0,"q":8.0,"gain":0.0}"#;
+        let pinned: PinnedFilter = serde_json::from_str(json).unwrap();
+        assert_eq!(pinned.filter.filter_type, FilterType::Notch);
+        assert!(pinned.range.is_none());
+
+        let json = r#"{"filter_type":"highpass","frequency":80.0,"q":0.7,"gain":0.0,
+            "range":{"min_freq":40.0,"max_freq":120.0,"min_q":0.5,"max_q":1.0,"min_gain":0.0,"max_gain":0.0}}"#;
+        let pinned: PinnedFilter = serde_json::from_str(json).unwrap();
+        let (lower, upper) = pinned.range.unwrap().bounds();
+        assert!((lower[0] - 40f64.log10()).abs() < 1e-12);
+        assert_eq!(upper[1], 1.0);
+    }
+
+    #[test]
+    fn test_filter_type_conversions() {
+        for filter_type in [
+            FilterType::Peak,
+            FilterType::Lowpass,
+            FilterType::Highpass,
+            FilterType::HighpassVariableQ,
+            FilterType::Lowshelf,
+            FilterType::Highshelf,
+            FilterType::Bandpass,
+            FilterType::Notch,
+        ] {
+            assert_eq!(filter_type.code().parse::<FilterType>(), Ok(filter_type));
+            if filter_type != FilterType::HighpassVariableQ {
+                assert_eq!(
+                    filter_type.camilla_name().parse::<FilterType>(),
+                    Ok(filter_type)
+                );
+            }
+            assert_eq!(
+                FilterType::from_biquad(filter_type.to_biquad()),
+                filter_type
+            );
+        }
+        assert!("wobble".parse::<FilterType>().is_err());
+    }
+
+    #[test]
+    fn test_export_conversion() {
+        let filter = DecodedFilter {
+            filter_type: FilterType::Highshelf,
+            frequency: 8000.0,
+            q: 0.7,
+            gain: -2.0,
+        };
+        let export = FilterParam::from(&filter);
+        assert_eq!(export.filter_type, "HS");
+        assert_eq!(DecodedFilter::try_from(&export), Ok(filter));
+    }
+}
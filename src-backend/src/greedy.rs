@@ -0,0 +1,209 @@
+//! Greedy peak-picking EQ
+//!
+//! A deterministic alternative to the global optimizers: repeatedly take the
+//! largest remaining deviation and put a peak filter on it, sized from the
+//! deviation's height and half-height bandwidth. It runs in milliseconds, so
+//! it serves as an instant preview and as an initial guess for a full run.
+
+use crate::choices::PeqModel;
+use crate::filters::{
+    DecodedFilter, FilterType, decode_filters, encode_filter, filters_response, model_filter_types,
+};
+use crate::optim::SearchSpace;
+use ndarray::Array1;
+
+/// Deviations smaller than this in dB are left alone
+const MIN_CORRECTION_DB: f64 = 0.1;
+
+/// Q of a peak whose half-gain bandwidth is `octaves` wide
+fn q_from_octaves(octaves: f64) -> f64 {
+    let ratio = 2f64.powf(octaves);
+    ratio.sqrt() / (ratio - 1.0)
+}
+
+/// Q matching the width of the deviation around `peak`
+///
+/// The width is where the deviation stays above half the peak value. A peak
+/// one point wide gets an infinite Q, which the caller clamps to its bound.
+fn bandwidth_q(freq: &Array1<f64>, residual: &Array1<f64>, peak: usize) -> f64 {
+    let half = residual[peak] / 2.0;
+    let inside = |i: usize| residual[i] * half.signum() >= half.abs();
+    let mut lo = peak;
+    while lo > 0 && inside(lo - 1) {
+        lo -= 1;
+    }
+    let mut hi = peak;
+    while hi + 1 < freq.len() && inside(hi + 1) {
+        hi += 1;
+    }
+    let octaves = (freq[hi] / freq[lo]).log2();
+    if octaves > 0.0 {
+        q_from_octaves(octaves)
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Fit peak filters to `deviation`, the correction the EQ should apply
+///
+/// Starts from `space.initial_x`: highpass and lowpass slots and the slots of
+/// bounded pinned filters keep their values, every other slot gets a peak
+/// placed by the greedy search within its bounds. Peaks are only placed
+/// between `min_freq` and `max_freq`.
+pub(crate) fn greedy_peaks(
+    space: &SearchSpace,
+    freq: &Array1<f64>,
+    deviation: &Array1<f64>,
+    peq_model: PeqModel,
+    min_freq: f64,
+    max_freq: f64,
+    sample_rate: f64,
+) -> Result<Vec<f64>, String> {
+    let num_filters = space.initial_x.len() / 3;
+    let types = model_filter_types(peq_model, num_filters).ok_or_else(|| {
+        format!(
+            "Greedy peak picking needs a PEQ model with fixed filter types (got: {})",
+            peq_model
+        )
+    })?;
+    let slots: Vec<usize> = (0..num_filters)
+        .filter(|slot| types[*slot] == FilterType::Peak && !space.bounded_slots.contains(slot))
+        .collect();
+
+    // Silence the greedy slots, the rest is already part of the correction
+    let mut x = space.initial_x.clone();
+    for &slot in &slots {
+        x[slot * 3 + 2] = 0.0;
+    }
+    let kept = decode_filters(&x, sample_rate, peq_model);
+    let mut residual = deviation - &filters_response(&kept, freq, sample_rate);
+
+    let (lower, upper) = (&space.lower_bounds, &space.upper_bounds);
+    for slot in slots {
+        let lo_freq = min_freq.max(10f64.powf(lower[slot * 3]));
+        let hi_freq = max_freq.min(10f64.powf(upper[slot * 3]));
+        let Some(peak) = (0..freq.len())
+            .filter(|&i| freq[i] >= lo_freq && freq[i] <= hi_freq)
+            .max_by(|&a, &b| residual[a].abs().total_cmp(&residual[b].abs()))
+        else {
+            continue;
+        };
+        if residual[peak].abs() < MIN_CORRECTION_DB {
+            break;
+        }
+
+        let filter = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: freq[peak],
+            q: bandwidth_q(freq, &residual, peak).clamp(lower[slot * 3 + 1], upper[slot * 3 + 1]),
+            gain: residual[peak].clamp(lower[slot * 3 + 2], upper[slot * 3 + 2]),
+        };
+        residual -= &filters_response(std::slice::from_ref(&filter), freq, sample_rate);
+        encode_filter(&filter, slot, &mut x);
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space(num_filters: usize) -> SearchSpace {
+        let lower = [1.3, 0.5, -12.0];
+        let upper = [4.3, 10.0, 12.0];
+        SearchSpace {
+            lower_bounds: lower.repeat(num_filters),
+            upper_bounds: upper.repeat(num_filters),
+            initial_x: [3.0, 1.0, 0.0].repeat(num_filters),
+            bounded_slots: Vec::new(),
+        }
+    }
+
+    fn log_freq() -> Array1<f64> {
+        Array1::logspace(10.0, 20f64.log10(), 20000f64.log10(), 200)
+    }
+
+    #[test]
+    fn test_q_from_octaves() {
+        // One octave wide is the textbook Q of 1.41
+        assert!((q_from_octaves(1.0) - 2f64.sqrt()).abs() < 1e-12);
+        assert!(q_from_octaves(1.0 / 3.0) > 4.0);
+    }
+
+    #[test]
+    fn test_greedy_recovers_single_peak() {
+        let freq = log_freq();
+        let target = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: 1000.0,
+            q: 2.0,
+            gain: -6.0,
+        };
+        let deviation = filters_response(&[target], &freq, 48000.0);
+        let x = greedy_peaks(
+            &space(2),
+            &freq,
+            &deviation,
+            PeqModel::Pk,
+            20.0,
+            20000.0,
+            48000.0,
+        )
+        .unwrap();
+
+        let filters = decode_filters(&x, 48000.0, PeqModel::Pk);
+        assert!((filters[0].frequency / 1000.0).log2().abs() < 0.05);
+        assert!((filters[0].gain + 6.0).abs() < 0.1);
+        assert!((filters[0].q / 2.0 - 1.0).abs() < 0.3);
+        // Little is left for the second filter
+        assert!(filters[1].gain.abs() < 1.0);
+    }
+
+    #[test]
+    fn test_greedy_respects_range_and_bounds() {
+        let freq = log_freq();
+        let deviation = freq.mapv(|f| if f < 100.0 { 20.0 } else { 0.0 });
+        let x = greedy_peaks(
+            &space(1),
+            &freq,
+            &deviation,
+            PeqModel::Pk,
+            200.0,
+            20000.0,
+            48000.0,
+        )
+        .unwrap();
+        // Nothing to correct above 200 Hz
+        assert_eq!(x[2], 0.0);
+
+        let x = greedy_peaks(
+            &space(1),
+            &freq,
+            &deviation,
+            PeqModel::Pk,
+            20.0,
+            20000.0,
+            48000.0,
+        )
+        .unwrap();
+        assert_eq!(x[2], 12.0);
+    }
+
+    #[test]
+    fn test_greedy_rejects_free_models() {
+        let freq = log_freq();
+        let deviation = Array1::zeros(freq.len());
+        assert!(
+            greedy_peaks(
+                &space(3),
+                &freq,
+                &deviation,
+                PeqModel::Free,
+                20.0,
+                20000.0,
+                48000.0
+            )
+            .is_err()
+        );
+    }
+}
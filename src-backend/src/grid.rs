@@ -0,0 +1,104 @@
+//! Frequency grid of the analysis
+//!
+//! Curves are resampled onto this log-spaced grid before the optimization,
+//! and the result plots use the same grid so that what is shown is what was
+//! optimized. The default of 20 points per octave from 20 Hz to 20 kHz gives
+//! 200 points; room-mode work with high-Q filters needs a denser grid.
+
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+
+/// Densest grid accepted, in points per octave
+pub const MAX_POINTS_PER_OCTAVE: usize = 96;
+
+/// Log-spaced frequency grid
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrequencyGrid {
+    pub points_per_octave: usize,
+    pub min_freq: f64, // Hz, first point
+    pub max_freq: f64, // Hz, last point
+}
+
+impl Default for FrequencyGrid {
+    fn default() -> Self {
+        Self {
+            points_per_octave: 20,
+            min_freq: 20.0,
+            max_freq: 20000.0,
+        }
+    }
+}
+
+impl FrequencyGrid {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_POINTS_PER_OCTAVE).contains(&self.points_per_octave) {
+            return Err(format!(
+                "Grid points per octave must be between 1 and {} (got: {})",
+                MAX_POINTS_PER_OCTAVE, self.points_per_octave
+            ));
+        }
+        let in_range =
+            self.min_freq >= 1.0 && self.min_freq < self.max_freq && self.max_freq <= 24000.0;
+        if !in_range {
+            return Err(format!(
+                "Grid range must be within 1-24000 Hz (got: {}-{} Hz)",
+                self.min_freq, self.max_freq
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of points, both ends included
+    pub fn num_points(&self) -> usize {
+        let octaves = (self.max_freq / self.min_freq).log2();
+        (octaves * self.points_per_octave as f64).round() as usize + 1
+    }
+
+    /// The grid frequencies in Hz, increasing
+    pub fn frequencies(&self) -> Array1<f64> {
+        autoeq::read::create_log_frequency_grid(self.num_points(), self.min_freq, self.max_freq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_grid() {
+        let grid = FrequencyGrid::default();
+        assert!(grid.validate().is_ok());
+        assert_eq!(grid.num_points(), 200);
+        let freq = grid.frequencies();
+        assert!((freq[0] - 20.0).abs() < 1e-9);
+        assert!((freq[199] - 20000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_points_per_octave() {
+        let grid = FrequencyGrid {
+            points_per_octave: 48,
+            min_freq: 20.0,
+            max_freq: 320.0,
+        };
+        // Four octaves
+        assert_eq!(grid.num_points(), 4 * 48 + 1);
+        let freq = grid.frequencies();
+        assert!((freq[48] / freq[0] - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_validate() {
+        let too_dense = FrequencyGrid {
+            points_per_octave: 200,
+            ..FrequencyGrid::default()
+        };
+        assert!(too_dense.validate().is_err());
+        let inverted = FrequencyGrid {
+            min_freq: 1000.0,
+            max_freq: 100.0,
+            ..FrequencyGrid::default()
+        };
+        assert!(inverted.validate().is_err());
+    }
+}
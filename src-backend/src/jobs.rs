@@ -0,0 +1,454 @@
+//! Optimization job manager
+//!
+//! Jobs get an ID when they are submitted, wait in a queue and run at most
+//! `max_concurrent` at a time. Each job has its own cancellation token, so one
+//! job can be cancelled without touching the others.
+
+use crate::cancellation::{CancelReason, CancellationToken};
+use crate::optim::{
+    OptimizationParams, OptimizationPhase, OptimizationResult, ProgressCallback, ProgressUpdate,
+    run_optimization_internal,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::{Semaphore, watch};
+
+/// Number of optimizations running side by side unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 2;
+
+pub type JobId = u64;
+
+/// Lifecycle of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Succeeded | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+/// Snapshot of a job returned by status queries
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub state: JobState,
+    pub phase: Option<OptimizationPhase>, // Phase of a running job
+    pub progress: Option<ProgressUpdate>, // Latest progress update, if any
+    pub error_message: Option<String>,
+    pub run_time_secs: f64, // Time spent running, 0 while queued
+}
+
+struct JobEntry {
+    state: JobState,
+    cancellation_token: CancellationToken,
+    phase: Option<OptimizationPhase>,
+    progress: Option<ProgressUpdate>,
+    result: Option<OptimizationResult>,
+    started: Option<Instant>,
+    run_time: Option<Duration>,
+    state_tx: watch::Sender<JobState>,
+}
+
+impl JobEntry {
+    fn info(&self, id: JobId) -> JobInfo {
+        let run_time = match (self.run_time, self.started) {
+            (Some(run_time), _) => run_time,
+            (None, Some(started)) => started.elapsed(),
+            (None, None) => Duration::ZERO,
+        };
+        JobInfo {
+            id,
+            state: self.state,
+            phase: self.phase,
+            progress: self.progress.clone(),
+            error_message: self
+                .result
+                .as_ref()
+                .and_then(|result| result.error_message.clone()),
+            run_time_secs: run_time.as_secs_f64(),
+        }
+    }
+
+    fn set_state(&mut self, state: JobState) {
+        self.state = state;
+        self.state_tx.send_replace(state);
+    }
+}
+
+struct ManagerInner {
+    runtime: Handle,
+    permits: Arc<Semaphore>,
+    max_concurrent: usize,
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<JobId, JobEntry>>,
+}
+
+impl ManagerInner {
+    fn jobs(&self) -> MutexGuard<'_, HashMap<JobId, JobEntry>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Move a queued job to running; false if it was cancelled while queued
+    fn start(&self, id: JobId) -> bool {
+        let mut jobs = self.jobs();
+        match jobs.get_mut(&id) {
+            Some(entry) if entry.state == JobState::Queued => {
+                entry.started = Some(Instant::now());
+                entry.set_state(JobState::Running);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&self, id: JobId, result: OptimizationResult) {
+        let mut jobs = self.jobs();
+        let Some(entry) = jobs.get_mut(&id) else {
+            return;
+        };
+        let state = if result.success {
+            JobState::Succeeded
+        } else if result.cancelled {
+            JobState::Cancelled
+        } else {
+            JobState::Failed
+        };
+        println!("[RUST DEBUG] Job {} finished: {:?}", id, state);
+        entry.run_time = entry.started.map(|started| started.elapsed());
+        entry.phase = None;
+        entry.result = Some(result);
+        entry.set_state(state);
+    }
+
+    fn record_progress(&self, id: JobId, update: &ProgressUpdate) {
+        if let Some(entry) = self.jobs().get_mut(&id) {
+            entry.progress = Some(update.clone());
+        }
+    }
+
+    fn record_phase(&self, id: JobId, phase: OptimizationPhase) {
+        if let Some(entry) = self.jobs().get_mut(&id) {
+            entry.phase = Some(phase);
+        }
+    }
+}
+
+/// Records the latest phase and update of a job, then forwards them
+struct JobProgressCallback<P: ProgressCallback> {
+    id: JobId,
+    manager: Arc<ManagerInner>,
+    cancellation_token: CancellationToken,
+    forward: Arc<P>,
+}
+
+impl<P: ProgressCallback> ProgressCallback for JobProgressCallback<P> {
+    fn on_progress(&self, update: ProgressUpdate) -> bool {
+        self.manager.record_progress(self.id, &update);
+        self.forward.on_progress(update) && !self.cancellation_token.is_cancelled()
+    }
+
+    fn on_phase(&self, phase: OptimizationPhase) {
+        self.manager.record_phase(self.id, phase);
+        self.forward.on_phase(phase);
+    }
+}
+
+/// Runtime for jobs submitted outside of a tokio context (e.g. from GPUI)
+pub(crate) fn runtime_handle() -> Handle {
+    Handle::try_current().unwrap_or_else(|_| {
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+        RUNTIME
+            .get_or_init(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .thread_name("sotf-jobs")
+                    .build()
+                    .expect("failed to start the job runtime")
+            })
+            .handle()
+            .clone()
+    })
+}
+
+/// Queue of optimization jobs with bounded concurrency
+///
+/// Clones share the same queue. Finished jobs stay available for status and
+/// result queries until [`JobManager::remove`] or
+/// [`JobManager::clear_finished`] is called.
+#[derive(Clone)]
+pub struct JobManager {
+    inner: Arc<ManagerInner>,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_JOBS)
+    }
+}
+
+impl JobManager {
+    /// Manager running at most `max_concurrent` jobs at once (at least one)
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            inner: Arc::new(ManagerInner {
+                runtime: runtime_handle(),
+                permits: Arc::new(Semaphore::new(max_concurrent)),
+                max_concurrent,
+                next_id: AtomicU64::new(1),
+                jobs: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.inner.max_concurrent
+    }
+
+    /// Queue an optimization and return its ID
+    pub fn submit<P: ProgressCallback + 'static>(
+        &self,
+        params: OptimizationParams,
+        progress_callback: Arc<P>,
+    ) -> JobId {
+        self.submit_with_token(params, progress_callback, CancellationToken::new())
+    }
+
+    /// Queue an optimization cancelled through `cancellation_token`
+    ///
+    /// Use a child token or one with a deadline to tie the job to a larger
+    /// operation or give it a time budget.
+    pub fn submit_with_token<P: ProgressCallback + 'static>(
+        &self,
+        params: OptimizationParams,
+        progress_callback: Arc<P>,
+        cancellation_token: CancellationToken,
+    ) -> JobId {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let (state_tx, _) = watch::channel(JobState::Queued);
+        self.inner.jobs().insert(
+            id,
+            JobEntry {
+                state: JobState::Queued,
+                cancellation_token: cancellation_token.clone(),
+                phase: None,
+                progress: None,
+                result: None,
+                started: None,
+                run_time: None,
+                state_tx,
+            },
+        );
+        println!("[RUST DEBUG] Job {} queued (algo: {})", id, params.algo);
+
+        let inner = Arc::clone(&self.inner);
+        self.inner.runtime.spawn(async move {
+            let _permit = Arc::clone(&inner.permits)
+                .acquire_owned()
+                .await
+                .expect("job semaphore closed");
+            if !inner.start(id) {
+                return;
+            }
+            println!("[RUST DEBUG] Job {} started", id);
+
+            let progress_callback = Arc::new(JobProgressCallback {
+                id,
+                manager: Arc::clone(&inner),
+                cancellation_token: cancellation_token.clone(),
+                forward: progress_callback,
+            });
+            let runtime = inner.runtime.clone();
+
+            // The optimizers are CPU bound, keep them off the async workers
+            let outcome = tokio::task::spawn_blocking(move || {
+                runtime.block_on(run_optimization_internal(
+                    params,
+                    progress_callback,
+                    cancellation_token,
+                ))
+            })
+            .await;
+
+            let result = match outcome {
+                Ok(Ok(result)) => result,
+                Ok(Err(e)) => OptimizationResult::from_error(&e),
+                Err(e) => OptimizationResult::failure(format!("Job worker failed: {}", e)),
+            };
+            inner.finish(id, result);
+        });
+
+        id
+    }
+
+    /// Current state of a job, `None` for an unknown ID
+    pub fn status(&self, id: JobId) -> Option<JobInfo> {
+        self.inner.jobs().get(&id).map(|entry| entry.info(id))
+    }
+
+    /// All known jobs, oldest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self
+            .inner
+            .jobs()
+            .iter()
+            .map(|(id, entry)| entry.info(*id))
+            .collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    /// Result of a finished job
+    pub fn result(&self, id: JobId) -> Option<OptimizationResult> {
+        self.inner
+            .jobs()
+            .get(&id)
+            .and_then(|entry| entry.result.clone())
+    }
+
+    /// Wait for a job to finish and return its result
+    pub async fn wait(&self, id: JobId) -> Option<OptimizationResult> {
+        let mut state_rx = self.inner.jobs().get(&id)?.state_tx.subscribe();
+        // The sender lives as long as the entry; a removed job has no result
+        state_rx.wait_for(|state| state.is_finished()).await.ok()?;
+        self.result(id)
+    }
+
+    /// Cancel one job; false if the ID is unknown or the job already finished
+    ///
+    /// A queued job is cancelled right away, a running one stops at the next
+    /// check of its token.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut jobs = self.inner.jobs();
+        let Some(entry) = jobs.get_mut(&id) else {
+            return false;
+        };
+        if entry.state.is_finished() {
+            return false;
+        }
+        println!("[RUST DEBUG] Cancelling job {}", id);
+        entry.cancellation_token.cancel();
+        if entry.state == JobState::Queued {
+            entry.result = Some(OptimizationResult::cancelled(CancelReason::Requested));
+            entry.set_state(JobState::Cancelled);
+        }
+        true
+    }
+
+    /// Cancel every queued and running job, returning how many were cancelled
+    pub fn cancel_all(&self) -> usize {
+        let ids: Vec<JobId> = self
+            .inner
+            .jobs()
+            .iter()
+            .filter(|(_, entry)| !entry.state.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.into_iter().filter(|id| self.cancel(*id)).count()
+    }
+
+    /// Forget a finished job and return its result
+    pub fn remove(&self, id: JobId) -> Option<OptimizationResult> {
+        let mut jobs = self.inner.jobs();
+        if !jobs.get(&id)?.state.is_finished() {
+            return None;
+        }
+        jobs.remove(&id).and_then(|entry| entry.result)
+    }
+
+    /// Forget every finished job
+    pub fn clear_finished(&self) {
+        self.inner
+            .jobs()
+            .retain(|_, entry| !entry.state.is_finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mocks::mocks::create_minimal_optimization_params;
+
+    struct NoopProgressCallback;
+
+    impl ProgressCallback for NoopProgressCallback {
+        fn on_progress(&self, _update: ProgressUpdate) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_invalid_job_fails() {
+        let manager = JobManager::new(1);
+        let params = OptimizationParams {
+            num_filters: 0,
+            ..create_minimal_optimization_params()
+        };
+        let id = manager.submit(params, Arc::new(NoopProgressCallback));
+
+        let result = manager.wait(id).await.unwrap();
+        assert!(!result.success);
+        assert!(!result.cancelled);
+
+        let info = manager.status(id).unwrap();
+        assert_eq!(info.state, JobState::Failed);
+        assert!(info.error_message.unwrap().contains("Invalid parameters"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancelled_job() {
+        let manager = JobManager::new(1);
+        let token = CancellationToken::new();
+        token.cancel();
+        let id = manager.submit_with_token(
+            create_minimal_optimization_params(),
+            Arc::new(NoopProgressCallback),
+            token,
+        );
+
+        let result = manager.wait(id).await.unwrap();
+        assert!(result.cancelled);
+        assert_eq!(manager.status(id).unwrap().state, JobState::Cancelled);
+        assert!(!manager.cancel(id), "finished jobs cannot be cancelled");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ids_and_cleanup() {
+        let manager = JobManager::new(2);
+        let invalid = OptimizationParams {
+            num_filters: 0,
+            ..create_minimal_optimization_params()
+        };
+        let first = manager.submit(invalid.clone(), Arc::new(NoopProgressCallback));
+        let second = manager.submit(invalid, Arc::new(NoopProgressCallback));
+        assert!(second > first);
+
+        manager.wait(first).await;
+        manager.wait(second).await;
+        let ids: Vec<JobId> = manager.list().iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec![first, second]);
+
+        assert!(manager.remove(first).is_some());
+        assert!(manager.status(first).is_none());
+        manager.clear_finished();
+        assert!(manager.list().is_empty());
+
+        assert!(!manager.cancel(42));
+        assert!(manager.wait(42).await.is_none());
+    }
+}
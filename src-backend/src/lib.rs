@@ -4,20 +4,52 @@ pub use audio::SharedAudioState;
 pub mod camilla;
 pub use camilla::{
     AudioManager, AudioState, AudioStreamState, CamillaError, CamillaResult, FilterParams,
-    SharedAudioStreamState,
+    MeterReading, SharedAudioStreamState, SignalLevels, SignalPeaks, StopReason, WavInfo,
 };
+pub mod camilla_config;
 
+pub mod batch;
+pub mod cancellation;
+pub mod choices;
+pub mod devices;
+pub mod events;
+mod greedy;
+pub mod grid;
+pub mod jobs;
+pub mod loss;
+pub mod manifest;
+pub mod measurements;
+pub mod metrics;
 pub mod optim;
+mod optim_nlopt;
 pub mod plot;
 pub mod export;
+pub mod filters;
 pub mod spinorama_api;
+pub mod sweep;
+pub mod validation;
 
 // Re-export commonly used types and helpers for easier access in tests and consumers
 pub use optim::{
-    CancellationState, OptimizationParams, OptimizationResult, ProgressUpdate, validate_params,
+    CancelReason, CancellationToken, JobId, JobInfo, JobManager, JobState, OptimizationError,
+    OptimizationEvent, OptimizationParams, OptimizationPhase, OptimizationResult, ProgressUpdate,
+    optimization_events, validate_params,
 };
 pub use plot::{CurveData, PlotData, curve_data_to_curve};
 pub use export::{ExportFormat, FilterParam as ExportFilterParam};
+pub use filters::{DecodedFilter, FilterLock, FilterRange, FilterType, PinnedFilter};
+pub use devices::{DeviceProfile, device_profiles};
+pub use grid::FrequencyGrid;
+pub use loss::WeightingCurve;
+pub use sweep::{SweepOptions, SweepPoint, SweepResult};
+pub use validation::{ValidationError, ValidationErrors};
+pub use manifest::RunManifest;
+pub use measurements::CapturedMeasurement;
+pub use metrics::{BandMetrics, MeasurementSpread, ResidualMetrics, WorstFit};
+pub use choices::{
+    Algorithm, ChoiceInfo, CurveAggregate, Loss, OptimizationChoices, PeqModel,
+    optimization_choices,
+};
 pub use spinorama_api::{SpinAudioClient, SpeakerInfo, MeasurementInfo, Cea2034Data, FrequencyResponse};
 
 #[cfg(test)]
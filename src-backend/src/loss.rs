@@ -0,0 +1,300 @@
+//! Frequency weighting and boost penalty for the flat losses
+//!
+//! autoeq's flat losses add up the squared error over the points of the
+//! frequency grid, so a weighting curve is applied to the curves the
+//! objective sees: they are resampled so that the point density per octave
+//! follows the weight.
+//!
+//! The boost penalty is a term of its own on the EQ response `e`, added to
+//! autoeq's loss: `p * mean(max(e, 0)²)` over the points of the loss grid in
+//! the optimized band. Cuts cost nothing extra, boosts cost more the larger
+//! they are, wherever they come from, e.g. overlapping filters. autoeq's DE
+//! and metaheuristics only see their own loss, so their result is polished on
+//! the full objective afterwards.
+
+use crate::choices::PeqModel;
+use crate::filters::{DecodedFilter, decode_filters, filters_response};
+use crate::optim::{OptimizationParams, PreparedCurves};
+use autoeq::Curve;
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Largest boost penalty accepted
+pub const MAX_BOOST_PENALTY: f64 = 10.0;
+
+/// User-defined weight of the loss by frequency
+///
+/// Weights are interpolated linearly over log frequency and held constant
+/// beyond the first and last points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightingCurve {
+    pub frequencies: Vec<f64>, // Hz, increasing
+    pub weights: Vec<f64>,     // > 0, 1 = default weight
+}
+
+impl WeightingCurve {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frequencies.is_empty() || self.frequencies.len() != self.weights.len() {
+            return Err(format!(
+                "Weighting curve needs as many weights as frequencies (got: {} and {})",
+                self.frequencies.len(),
+                self.weights.len()
+            ));
+        }
+        if self.frequencies.iter().any(|f| !f.is_finite() || *f <= 0.0)
+            || self.frequencies.windows(2).any(|w| w[0] >= w[1])
+        {
+            return Err("Weighting curve frequencies must be positive and increasing".to_string());
+        }
+        if self.weights.iter().any(|w| !w.is_finite() || *w <= 0.0) {
+            return Err("Weighting curve weights must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    /// Weight at `freq`
+    pub fn weight_at(&self, freq: f64) -> f64 {
+        let i = self.frequencies.partition_point(|f| *f <= freq);
+        if i == 0 {
+            return self.weights[0];
+        }
+        if i == self.frequencies.len() {
+            return self.weights[i - 1];
+        }
+        let (f0, f1) = (self.frequencies[i - 1].ln(), self.frequencies[i].ln());
+        let t = (freq.ln() - f0) / (f1 - f0);
+        self.weights[i - 1] + t * (self.weights[i] - self.weights[i - 1])
+    }
+}
+
+impl FromStr for WeightingCurve {
+    type Err = String;
+
+    /// Parse "FREQ:WEIGHT,FREQ:WEIGHT,..." (e.g. "8000:1,12000:0.3")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (frequencies, weights) = s
+            .split(',')
+            .map(|point| {
+                let (freq, weight) = point.split_once(':').ok_or_else(|| {
+                    format!("Invalid weighting point '{}', expected FREQ:WEIGHT", point)
+                })?;
+                let freq: f64 = freq
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid frequency: {}", freq))?;
+                let weight: f64 = weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid weight: {}", weight))?;
+                Ok((freq, weight))
+            })
+            .collect::<Result<(Vec<f64>, Vec<f64>), String>>()?;
+        let curve = WeightingCurve {
+            frequencies,
+            weights,
+        };
+        curve.validate()?;
+        Ok(curve)
+    }
+}
+
+/// Grid over the span of `freq` with a point density per octave following `density`
+///
+/// `density` holds one value per point of `freq` and the grid keeps the
+/// number of points.
+fn weighted_grid(freq: &Array1<f64>, density: &[f64]) -> Array1<f64> {
+    let n = freq.len();
+    if n < 2 {
+        return freq.clone();
+    }
+    let log_freq: Vec<f64> = freq.iter().map(|f| f.ln()).collect();
+    let mut cumulative = vec![0.0; n];
+    for i in 1..n {
+        let width = log_freq[i] - log_freq[i - 1];
+        cumulative[i] = cumulative[i - 1] + width * (density[i - 1] + density[i]) / 2.0;
+    }
+    let total = cumulative[n - 1];
+    if total <= 0.0 {
+        return freq.clone();
+    }
+
+    let mut segment = 1;
+    Array1::from_iter((0..n).map(|k| {
+        let c = total * k as f64 / (n - 1) as f64;
+        while segment < n - 1 && cumulative[segment] < c {
+            segment += 1;
+        }
+        let (c0, c1) = (cumulative[segment - 1], cumulative[segment]);
+        let t = if c1 > c0 { (c - c0) / (c1 - c0) } else { 0.0 };
+        (log_freq[segment - 1] + t * (log_freq[segment] - log_freq[segment - 1])).exp()
+    }))
+}
+
+/// Curves reshaped so that the flat loss applies `weighting`
+pub(crate) fn shape_curves(curves: &PreparedCurves, weighting: &WeightingCurve) -> PreparedCurves {
+    let density: Vec<f64> = curves
+        .deviation_curve
+        .freq
+        .iter()
+        .map(|&f| weighting.weight_at(f))
+        .collect();
+    let grid = weighted_grid(&curves.deviation_curve.freq, &density);
+
+    let resample = |curve: &Curve| autoeq::read::interpolate_log_space(&grid, curve);
+    let spin_data = curves.spin_data.as_ref().map(|spin| {
+        spin.iter()
+            .map(|(name, curve)| (name.clone(), resample(curve)))
+            .collect()
+    });
+
+    PreparedCurves {
+        input_curve: resample(&curves.input_curve),
+        target_curve: resample(&curves.target_curve),
+        deviation_curve: resample(&curves.deviation_curve),
+        spin_data,
+    }
+}
+
+/// Extra cost of the boosts of the EQ
+#[derive(Debug, Clone)]
+pub(crate) struct BoostPenalty {
+    pub(crate) weight: f64,
+    pub(crate) freq: Array1<f64>, // Hz, points of the loss grid in the optimized band
+    pub(crate) fixed_response: Array1<f64>, // dB, response of the fixed filters on `freq`
+    pub(crate) sample_rate: f64,
+    pub(crate) peq_model: PeqModel,
+}
+
+impl BoostPenalty {
+    /// Penalty of `params` on the loss grid `freq`, `None` when it is 0
+    pub(crate) fn new(
+        params: &OptimizationParams,
+        sample_rate: f64,
+        freq: &Array1<f64>,
+        fixed_filters: &[DecodedFilter],
+    ) -> Option<Self> {
+        if params.boost_penalty <= 0.0 {
+            return None;
+        }
+        let freq = Array1::from_iter(
+            freq.iter()
+                .copied()
+                .filter(|f| (params.min_freq..=params.max_freq).contains(f)),
+        );
+        Some(Self {
+            weight: params.boost_penalty,
+            fixed_response: filters_response(fixed_filters, &freq, sample_rate),
+            freq,
+            sample_rate,
+            peq_model: params.peq_model,
+        })
+    }
+
+    /// Penalty of the EQ with the optimized filters `x`
+    pub(crate) fn cost(&self, x: &[f64]) -> f64 {
+        if self.freq.is_empty() {
+            return 0.0;
+        }
+        let filters = decode_filters(x, self.sample_rate, self.peq_model);
+        let response =
+            filters_response(&filters, &self.freq, self.sample_rate) + &self.fixed_response;
+        let boost = response.iter().map(|e| e.max(0.0).powi(2)).sum::<f64>();
+        self.weight * boost / self.freq.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight_at() {
+        let curve: WeightingCurve = "1000:1,10000:0.2".parse().unwrap();
+        assert_eq!(curve.weight_at(100.0), 1.0);
+        assert_eq!(curve.weight_at(20000.0), 0.2);
+        assert!((curve.weight_at(10f64.powf(3.5)) - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_curves() {
+        assert!("1000".parse::<WeightingCurve>().is_err());
+        assert!("1000:1,500:1".parse::<WeightingCurve>().is_err());
+        assert!("1000:0".parse::<WeightingCurve>().is_err());
+    }
+
+    #[test]
+    fn test_weighted_grid_density() {
+        let freq = Array1::logspace(10.0, 2.0, 4.0, 201);
+        // Uniform density keeps the grid
+        let uniform = weighted_grid(&freq, &[1.0; 201]);
+        for (a, b) in uniform.iter().zip(freq.iter()) {
+            assert!((a / b - 1.0).abs() < 1e-9);
+        }
+
+        // Three times the weight in the upper decade gives three times the points
+        let density: Vec<f64> = freq
+            .iter()
+            .map(|f| if *f > 1000.0 { 3.0 } else { 1.0 })
+            .collect();
+        let grid = weighted_grid(&freq, &density);
+        let upper = grid.iter().filter(|f| **f > 1000.0).count();
+        assert!((145..=155).contains(&upper), "got {} points", upper);
+        assert!((grid[0] - 100.0).abs() < 1e-9);
+        assert!((grid[200] - 10000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_shape_curves_resamples_onto_weighted_grid() {
+        let freq = Array1::logspace(10.0, 2.0, 4.0, 101);
+        let flat = |level: f64| Curve {
+            freq: freq.clone(),
+            spl: Array1::from_elem(freq.len(), level),
+        };
+        let curves = PreparedCurves {
+            input_curve: flat(0.0),
+            target_curve: flat(4.0),
+            deviation_curve: flat(4.0),
+            spin_data: None,
+        };
+        let weighting: WeightingCurve = "1000:1,1001:3".parse().unwrap();
+        let shaped = shape_curves(&curves, &weighting);
+        let upper = shaped
+            .deviation_curve
+            .freq
+            .iter()
+            .filter(|f| **f > 1000.0)
+            .count();
+        assert!(upper > 60, "got {} points above 1 kHz", upper);
+        assert!(
+            shaped
+                .deviation_curve
+                .spl
+                .iter()
+                .all(|d| (d - 4.0).abs() < 1e-9)
+        );
+    }
+
+    #[test]
+    fn test_boost_penalty_costs_boosts_only() {
+        let penalty = BoostPenalty {
+            weight: 2.0,
+            freq: Array1::logspace(10.0, 2.0, 4.0, 101),
+            fixed_response: Array1::zeros(101),
+            sample_rate: 48000.0,
+            peq_model: PeqModel::Pk,
+        };
+        // One peak at 1 kHz
+        let boost = penalty.cost(&[3.0, 2.0, 4.0]);
+        assert!(boost > 0.0);
+        assert_eq!(penalty.cost(&[3.0, 2.0, -4.0]), 0.0);
+        assert!(penalty.cost(&[3.0, 2.0, 8.0]) > 3.0 * boost);
+
+        // A fixed boost counts like one from the optimized filters
+        let fixed = BoostPenalty {
+            fixed_response: Array1::from_elem(101, 1.0),
+            ..penalty.clone()
+        };
+        assert!((fixed.cost(&[3.0, 2.0, 0.0]) - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::optim::{
+    CancellationToken, OptimizationParams, OptimizationResult, ProgressCallback,
+    build_autoeq_args, prepare_curves, run_optimization_internal,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Bump when the manifest layout changes
+pub const MANIFEST_VERSION: u32 = 2;
+
+/// Fingerprint of one curve used by the optimization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputHash {
+    pub name: String,
+    pub points: usize,
+    /// FNV-1a 64 of the frequency and SPL values, as hex
+    pub fnv1a: String,
+}
+
+/// Versions of the crates that influence the result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateVersions {
+    pub autoeq_backend: String,
+    pub autoeq: String,
+}
+
+impl CrateVersions {
+    pub fn current() -> Self {
+        Self {
+            autoeq_backend: env!("CARGO_PKG_VERSION").to_string(),
+            autoeq: env!("AUTOEQ_VERSION").to_string(),
+        }
+    }
+}
+
+/// Everything needed to reproduce an optimization run
+///
+/// The parameters are stored fully resolved (including the seed), so
+/// replaying with the same inputs and crate versions gives the same filters.
+/// A run cut short by its time budget stopped wherever the clock ran out, so
+/// it is recorded but cannot be replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub manifest_version: u32,
+    pub created_unix: u64,
+    pub crate_versions: CrateVersions,
+    pub params: OptimizationParams,
+    pub inputs: Vec<InputHash>,
+    pub filter_params: Vec<f64>,
+    #[serde(default)]
+    pub time_budget_secs: Option<f64>, // Time the run was given, if limited
+    #[serde(default)]
+    pub truncated: bool, // The budget ran out before the optimizer finished
+}
+
+/// Outcome of replaying a manifest
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub result: OptimizationResult,
+    /// Crate versions differ from the recorded ones
+    pub version_mismatch: bool,
+    /// Filters are bit for bit identical to the recorded ones
+    pub identical: bool,
+}
+
+impl RunManifest {
+    pub fn new(
+        params: OptimizationParams,
+        inputs: Vec<InputHash>,
+        filter_params: Vec<f64>,
+        time_budget: Option<Duration>,
+        truncated: bool,
+    ) -> Self {
+        let created_unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            manifest_version: MANIFEST_VERSION,
+            created_unix,
+            crate_versions: CrateVersions::current(),
+            params,
+            inputs,
+            filter_params,
+            time_budget_secs: time_budget.map(|budget| budget.as_secs_f64()),
+            truncated,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize manifest: {}", e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let manifest: RunManifest =
+            serde_json::from_str(json).map_err(|e| format!("Invalid run manifest: {}", e))?;
+        if manifest.manifest_version > MANIFEST_VERSION {
+            return Err(format!(
+                "Run manifest version {} is newer than supported version {}",
+                manifest.manifest_version, MANIFEST_VERSION
+            ));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_json()?)
+            .map_err(|e| format!("Failed to write manifest {:?}: {}", path, e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest {:?}: {}", path, e))?;
+        Self::from_json(&json)
+    }
+
+    /// Reload the inputs and report every curve whose hash changed
+    pub async fn check_inputs(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let args = build_autoeq_args(&self.params);
+        let curves = prepare_curves(&self.params, &args).await?;
+        let current = curve_hashes(
+            &curves.input_curve,
+            &curves.target_curve,
+            curves.spin_data.as_ref(),
+        );
+        Ok(diff_inputs(&self.inputs, &current))
+    }
+
+    /// Run the recorded optimization again and compare the filters
+    ///
+    /// Fails before optimizing if the run was truncated by its time budget or
+    /// an input curve no longer matches its hash. The replay runs without a
+    /// budget: a run that finished in time does not depend on it.
+    pub async fn replay<P: ProgressCallback + 'static>(
+        &self,
+        progress_callback: Arc<P>,
+        cancellation_token: CancellationToken,
+    ) -> Result<ReplayReport, Box<dyn std::error::Error + Send + Sync>> {
+        if self.truncated {
+            return Err(format!(
+                "The run was cut short by its time budget of {:.1} s and cannot be replayed",
+                self.time_budget_secs.unwrap_or(0.0)
+            )
+            .into());
+        }
+        let mismatches = self.check_inputs().await?;
+        if !mismatches.is_empty() {
+            return Err(format!("Inputs changed since the run: {}", mismatches.join("; ")).into());
+        }
+
+        let version_mismatch = self.crate_versions != CrateVersions::current();
+        if version_mismatch {
+            println!(
+                "[RUST DEBUG] Replaying with different crate versions: recorded {:?}, current {:?}",
+                self.crate_versions,
+                CrateVersions::current()
+            );
+        }
+
+        let result =
+            run_optimization_internal(self.params.clone(), progress_callback, cancellation_token)
+                .await?;
+        let identical = result.filter_params.as_ref().is_some_and(|x| {
+            x.len() == self.filter_params.len()
+                && x.iter()
+                    .zip(&self.filter_params)
+                    .all(|(a, b)| a.to_bits() == b.to_bits())
+        });
+
+        Ok(ReplayReport {
+            result,
+            version_mismatch,
+            identical,
+        })
+    }
+}
+
+/// Describe differences between recorded and current input hashes
+fn diff_inputs(recorded: &[InputHash], current: &[InputHash]) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    for expected in recorded {
+        match current.iter().find(|h| h.name == expected.name) {
+            Some(actual) if actual == expected => {}
+            Some(actual) => mismatches.push(format!(
+                "{} hash {} != recorded {}",
+                expected.name, actual.fnv1a, expected.fnv1a
+            )),
+            None => mismatches.push(format!("{} is missing", expected.name)),
+        }
+    }
+    for actual in current {
+        if !recorded.iter().any(|h| h.name == actual.name) {
+            mismatches.push(format!("{} is new", actual.name));
+        }
+    }
+    mismatches
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, &b| (h ^ u64::from(b)).wrapping_mul(FNV_PRIME))
+}
+
+/// Hash a curve over the exact bit patterns of its values
+pub fn hash_curve(name: &str, curve: &autoeq::Curve) -> InputHash {
+    let mut hash = FNV_OFFSET_BASIS;
+    for value in curve.freq.iter().chain(curve.spl.iter()) {
+        hash = fnv1a(hash, &value.to_le_bytes());
+    }
+    InputHash {
+        name: name.to_string(),
+        points: curve.freq.len(),
+        fnv1a: format!("{:016x}", hash),
+    }
+}
+
+/// Hashes of every curve fed to the objective, spin curves sorted by name
+pub fn curve_hashes(
+    input_curve: &autoeq::Curve,
+    target_curve: &autoeq::Curve,
+    spin_data: Option<&HashMap<String, autoeq::Curve>>,
+) -> Vec<InputHash> {
+    let mut hashes = vec![
+        hash_curve("input", input_curve),
+        hash_curve("target", target_curve),
+    ];
+    if let Some(spin_data) = spin_data {
+        let mut names: Vec<&String> = spin_data.keys().collect();
+        names.sort();
+        for name in names {
+            hashes.push(hash_curve(&format!("spin:{}", name), &spin_data[name]));
+        }
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optim::ProgressUpdate;
+    use ndarray::Array1;
+
+    struct NoopProgressCallback;
+
+    impl ProgressCallback for NoopProgressCallback {
+        fn on_progress(&self, _update: ProgressUpdate) -> bool {
+            true
+        }
+    }
+
+    fn curve(spl: Vec<f64>) -> autoeq::Curve {
+        autoeq::Curve {
+            freq: Array1::from_vec(vec![100.0, 1000.0, 10000.0]),
+            spl: Array1::from_vec(spl),
+        }
+    }
+
+    #[test]
+    fn test_fnv1a_reference_values() {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_hash_curve_detects_changes() {
+        let a = hash_curve("input", &curve(vec![0.0, 1.0, 2.0]));
+        let b = hash_curve("input", &curve(vec![0.0, 1.0, 2.0]));
+        let c = hash_curve("input", &curve(vec![0.0, 1.0, 2.000001]));
+        assert_eq!(a, b);
+        assert_ne!(a.fnv1a, c.fnv1a);
+        assert_eq!(a.points, 3);
+    }
+
+    #[test]
+    fn test_diff_inputs() {
+        let recorded = curve_hashes(&curve(vec![0.0; 3]), &curve(vec![1.0; 3]), None);
+        assert!(diff_inputs(&recorded, &recorded).is_empty());
+
+        let changed = curve_hashes(&curve(vec![0.5; 3]), &curve(vec![1.0; 3]), None);
+        let mismatches = diff_inputs(&recorded, &changed);
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].starts_with("input"));
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let mut params = OptimizationParams::default();
+        params.seed = Some(42);
+        let manifest = RunManifest::new(
+            params,
+            curve_hashes(&curve(vec![0.0; 3]), &curve(vec![1.0; 3]), None),
+            vec![3.0, 1.5, -2.25],
+            Some(Duration::from_millis(2500)),
+            false,
+        );
+
+        let restored = RunManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(restored.params.seed, Some(42));
+        assert_eq!(restored.inputs, manifest.inputs);
+        assert_eq!(restored.crate_versions, CrateVersions::current());
+        assert_eq!(restored.filter_params, vec![3.0, 1.5, -2.25]);
+        assert_eq!(restored.time_budget_secs, Some(2.5));
+        assert!(!restored.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_run_is_not_replayed() {
+        let manifest = RunManifest::new(
+            OptimizationParams::default(),
+            Vec::new(),
+            Vec::new(),
+            Some(Duration::from_secs(1)),
+            true,
+        );
+        let err = manifest
+            .replay(Arc::new(NoopProgressCallback), CancellationToken::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("time budget"));
+    }
+
+    #[test]
+    fn test_resolve_seed_is_stable() {
+        let mut params = OptimizationParams::default();
+        let seed = params.resolve_seed();
+        assert_eq!(params.seed, Some(seed));
+        assert_eq!(params.resolve_seed(), seed);
+        assert!(seed < (1u64 << 53));
+    }
+}
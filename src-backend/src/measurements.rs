@@ -0,0 +1,180 @@
+//! Several measurements of the same device
+//!
+//! Headphone reseats or microphone positions each give a slightly different
+//! curve. They are normalized onto a common grid and combined into the one
+//! curve the optimizer fits, so that the EQ corrects what they share rather
+//! than the noise of a single measurement:
+//!
+//! - the weighted mean curve minimizes the weighted mean of the squared
+//!   errors over the measurements;
+//! - the weighted median curve is robust to a few outlier measurements;
+//! - the midrange curve, halfway between the lowest and highest measurement,
+//!   minimizes the worst error over the measurements at each frequency.
+
+use crate::choices::CurveAggregate;
+use autoeq::Curve;
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+
+/// One measurement of a set, with its weight in the aggregate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedMeasurement {
+    pub frequencies: Vec<f64>,
+    pub magnitudes: Vec<f64>,
+    #[serde(default = "default_weight")]
+    pub weight: f64, // > 0, relative to the other measurements
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl CapturedMeasurement {
+    pub fn new(frequencies: Vec<f64>, magnitudes: Vec<f64>) -> Self {
+        Self {
+            frequencies,
+            magnitudes,
+            weight: default_weight(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frequencies.len() < 2 || self.frequencies.len() != self.magnitudes.len() {
+            return Err(format!(
+                "Measurement needs at least 2 points and as many magnitudes as frequencies (got: {} and {})",
+                self.frequencies.len(),
+                self.magnitudes.len()
+            ));
+        }
+        if !self.weight.is_finite() || self.weight <= 0.0 {
+            return Err(format!(
+                "Measurement weight must be greater than 0 (got: {})",
+                self.weight
+            ));
+        }
+        Ok(())
+    }
+
+    fn curve(&self) -> Curve {
+        Curve {
+            freq: Array1::from_vec(self.frequencies.clone()),
+            spl: Array1::from_vec(self.magnitudes.clone()),
+        }
+    }
+}
+
+/// Each measurement normalized and interpolated onto `grid`
+pub(crate) fn normalized_curves(
+    measurements: &[CapturedMeasurement],
+    grid: &Array1<f64>,
+) -> Vec<Curve> {
+    measurements
+        .iter()
+        .map(|m| autoeq::read::normalize_and_interpolate_response(grid, &m.curve()))
+        .collect()
+}
+
+/// Value splitting `weights` in two equal halves
+fn weighted_median(mut values: Vec<(f64, f64)>) -> f64 {
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let half = values.iter().map(|(_, w)| w).sum::<f64>() / 2.0;
+    let mut cumulative = 0.0;
+    for (i, (value, weight)) in values.iter().enumerate() {
+        cumulative += weight;
+        if (cumulative - half).abs() < 1e-12 {
+            // Exactly half the weight below: average with the next value
+            return values
+                .get(i + 1)
+                .map_or(*value, |next| (value + next.0) / 2.0);
+        }
+        if cumulative > half {
+            return *value;
+        }
+    }
+    values.last().map_or(0.0, |(value, _)| *value)
+}
+
+/// Combine `curves`, all on the same grid, into one curve
+///
+/// `weights` holds one weight per curve. The worst case ignores the weights:
+/// every measurement counts fully.
+pub fn aggregate_curves(curves: &[Curve], weights: &[f64], aggregate: CurveAggregate) -> Curve {
+    let freq = curves[0].freq.clone();
+    let spl = Array1::from_iter((0..freq.len()).map(|i| {
+        let values = curves.iter().map(|c| c.spl[i]);
+        match aggregate {
+            CurveAggregate::Mean => {
+                values.zip(weights).map(|(v, w)| v * w).sum::<f64>() / weights.iter().sum::<f64>()
+            }
+            CurveAggregate::Median => {
+                weighted_median(values.zip(weights.iter().copied()).collect())
+            }
+            CurveAggregate::WorstCase => {
+                let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                });
+                (lo + hi) / 2.0
+            }
+        }
+    }));
+    Curve { freq, spl }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(levels: &[f64]) -> Vec<Curve> {
+        let freq = Array1::from_vec(vec![100.0, 1000.0]);
+        levels
+            .iter()
+            .map(|&level| Curve {
+                freq: freq.clone(),
+                spl: Array1::from_elem(2, level),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_weighted_mean() {
+        let curves = flat(&[0.0, 3.0]);
+        let mean = aggregate_curves(&curves, &[2.0, 1.0], CurveAggregate::Mean);
+        assert!(mean.spl.iter().all(|v| (v - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_median_ignores_outlier() {
+        let curves = flat(&[0.0, 0.5, 1.0, 12.0]);
+        let median = aggregate_curves(&curves, &[1.0, 1.0, 1.0, 1.0], CurveAggregate::Median);
+        assert!(median.spl.iter().all(|v| (v - 0.75).abs() < 1e-12));
+        // A heavy weight pulls the median onto its measurement
+        let median = aggregate_curves(&curves, &[1.0, 1.0, 5.0, 1.0], CurveAggregate::Median);
+        assert!(median.spl.iter().all(|v| (v - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_worst_case_midrange() {
+        let curves = flat(&[-2.0, 0.0, 4.0]);
+        let midrange = aggregate_curves(&curves, &[1.0, 10.0, 1.0], CurveAggregate::WorstCase);
+        assert!(midrange.spl.iter().all(|v| (v - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(
+            CapturedMeasurement::new(vec![100.0, 1000.0], vec![0.0, 1.0])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            CapturedMeasurement::new(vec![100.0], vec![0.0])
+                .validate()
+                .is_err()
+        );
+        let unweighted = CapturedMeasurement {
+            weight: 0.0,
+            ..CapturedMeasurement::new(vec![100.0, 1000.0], vec![0.0, 1.0])
+        };
+        assert!(unweighted.validate().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Residual error statistics of an EQ result
+//!
+//! The residual is what the EQ leaves uncorrected: `target - (input + eq)`
+//! in dB. Positive values mean the corrected response is below the target.
+
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+
+/// Upper edge of the bass band in Hz
+pub const BASS_MAX_FREQ: f64 = 250.0;
+/// Lower edge of the treble band in Hz
+pub const TREBLE_MIN_FREQ: f64 = 4000.0;
+/// Number of worst-fit frequencies reported
+pub const WORST_FIT_COUNT: usize = 5;
+
+/// Deviation statistics over one frequency band
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandMetrics {
+    pub min_freq: f64,
+    pub max_freq: f64,
+    pub points: usize,
+    pub rms_db: f64,
+    pub max_abs_db: f64, // Largest absolute deviation
+}
+
+/// A frequency where the result is furthest from the target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorstFit {
+    pub freq: f64,
+    pub deviation_db: f64, // Signed residual, positive below target
+}
+
+/// Residual error of an EQ result within the optimization range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResidualMetrics {
+    pub overall: BandMetrics,
+    pub bass: Option<BandMetrics>, // None when the range has no point in the band
+    pub mid: Option<BandMetrics>,
+    pub treble: Option<BandMetrics>,
+    pub worst_fit: Vec<WorstFit>, // Largest residual peaks, worst first
+}
+
+/// How the EQ result varies across several measurements of the same device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeasurementSpread {
+    pub residual_rms_db: Vec<f64>, // Residual RMS of each measurement after EQ, in input order
+    pub worst_rms_db: f64,         // Largest of `residual_rms_db`
+    pub mean_std_db: f64,          // Std across measurements, mean over frequency
+    pub max_std_db: f64,           // Std across measurements, largest over frequency
+}
+
+fn band_metrics(
+    freq: &Array1<f64>,
+    residual: &Array1<f64>,
+    lo: f64,
+    hi: f64,
+) -> Option<BandMetrics> {
+    let values: Vec<f64> = freq
+        .iter()
+        .zip(residual.iter())
+        .filter(|(f, _)| **f >= lo && **f <= hi)
+        .map(|(_, r)| *r)
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    let rms_db = (values.iter().map(|r| r * r).sum::<f64>() / values.len() as f64).sqrt();
+    let max_abs_db = values.iter().fold(0.0_f64, |acc, r| acc.max(r.abs()));
+    Some(BandMetrics {
+        min_freq: lo,
+        max_freq: hi,
+        points: values.len(),
+        rms_db,
+        max_abs_db,
+    })
+}
+
+/// Local maxima of the absolute residual, largest first
+///
+/// Using peaks rather than the largest points avoids reporting several
+/// neighbouring frequencies of the same resonance.
+fn worst_fit(freq: &Array1<f64>, residual: &Array1<f64>, lo: f64, hi: f64) -> Vec<WorstFit> {
+    let points: Vec<(f64, f64)> = freq
+        .iter()
+        .zip(residual.iter())
+        .filter(|(f, _)| **f >= lo && **f <= hi)
+        .map(|(f, r)| (*f, *r))
+        .collect();
+
+    let mut peaks: Vec<WorstFit> = (0..points.len())
+        .filter(|&i| {
+            let magnitude = points[i].1.abs();
+            let left = i.checked_sub(1).map_or(0.0, |j| points[j].1.abs());
+            let right = points.get(i + 1).map_or(0.0, |p| p.1.abs());
+            magnitude >= left && magnitude > right
+        })
+        .map(|i| WorstFit {
+            freq: points[i].0,
+            deviation_db: points[i].1,
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.deviation_db.abs().total_cmp(&a.deviation_db.abs()));
+    peaks.truncate(WORST_FIT_COUNT);
+    peaks
+}
+
+/// Residual statistics between `min_freq` and `max_freq`
+///
+/// Returns `None` when no frequency falls inside the range.
+pub fn residual_metrics(
+    freq: &Array1<f64>,
+    residual: &Array1<f64>,
+    min_freq: f64,
+    max_freq: f64,
+) -> Option<ResidualMetrics> {
+    let overall = band_metrics(freq, residual, min_freq, max_freq)?;
+    let band = |lo: f64, hi: f64| {
+        if lo >= hi {
+            None
+        } else {
+            band_metrics(freq, residual, lo, hi)
+        }
+    };
+    Some(ResidualMetrics {
+        overall,
+        bass: band(min_freq, max_freq.min(BASS_MAX_FREQ)),
+        mid: band(min_freq.max(BASS_MAX_FREQ), max_freq.min(TREBLE_MIN_FREQ)),
+        treble: band(min_freq.max(TREBLE_MIN_FREQ), max_freq),
+        worst_fit: worst_fit(freq, residual, min_freq, max_freq),
+    })
+}
+
+/// Spread of the corrected response across measurements, between `min_freq` and `max_freq`
+///
+/// `residuals` holds one residual per measurement and `weights` their weights.
+/// Returns `None` when no frequency falls inside the range.
+pub fn measurement_spread(
+    freq: &Array1<f64>,
+    residuals: &[Array1<f64>],
+    weights: &[f64],
+    min_freq: f64,
+    max_freq: f64,
+) -> Option<MeasurementSpread> {
+    let residual_rms_db = residuals
+        .iter()
+        .map(|r| band_metrics(freq, r, min_freq, max_freq).map(|m| m.rms_db))
+        .collect::<Option<Vec<f64>>>()?;
+    let total_weight: f64 = weights.iter().sum();
+    let std_db: Vec<f64> = (0..freq.len())
+        .filter(|&i| freq[i] >= min_freq && freq[i] <= max_freq)
+        .map(|i| {
+            let mean = residuals
+                .iter()
+                .zip(weights)
+                .map(|(r, w)| r[i] * w)
+                .sum::<f64>()
+                / total_weight;
+            let variance = residuals
+                .iter()
+                .zip(weights)
+                .map(|(r, w)| w * (r[i] - mean).powi(2))
+                .sum::<f64>()
+                / total_weight;
+            variance.sqrt()
+        })
+        .collect();
+    Some(MeasurementSpread {
+        worst_rms_db: residual_rms_db.iter().fold(0.0_f64, |acc, r| acc.max(*r)),
+        residual_rms_db,
+        mean_std_db: std_db.iter().sum::<f64>() / std_db.len() as f64,
+        max_std_db: std_db.iter().fold(0.0_f64, |acc, s| acc.max(*s)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_residual() {
+        let freq = Array1::from_vec(vec![100.0, 1000.0, 10000.0]);
+        let residual = Array1::from_vec(vec![1.0, -1.0, 1.0]);
+        let metrics = residual_metrics(&freq, &residual, 20.0, 20000.0).unwrap();
+        assert_eq!(metrics.overall.points, 3);
+        assert!((metrics.overall.rms_db - 1.0).abs() < 1e-12);
+        assert_eq!(metrics.overall.max_abs_db, 1.0);
+        assert_eq!(metrics.bass.unwrap().points, 1);
+        assert_eq!(metrics.mid.unwrap().points, 1);
+        assert_eq!(metrics.treble.unwrap().points, 1);
+    }
+
+    #[test]
+    fn test_bands_outside_range() {
+        let freq = Array1::from_vec(vec![500.0, 1000.0, 2000.0]);
+        let residual = Array1::from_vec(vec![0.5, 2.0, 0.5]);
+        let metrics = residual_metrics(&freq, &residual, 300.0, 3000.0).unwrap();
+        assert!(metrics.bass.is_none());
+        assert!(metrics.treble.is_none());
+        assert_eq!(metrics.mid.unwrap().max_abs_db, 2.0);
+
+        assert!(residual_metrics(&freq, &residual, 5000.0, 8000.0).is_none());
+    }
+
+    #[test]
+    fn test_worst_fit_reports_peaks() {
+        let freq = Array1::from_vec(vec![100.0, 200.0, 300.0, 400.0, 500.0, 600.0]);
+        let residual = Array1::from_vec(vec![0.0, 2.0, 3.0, 2.5, 0.0, -4.0]);
+        let metrics = residual_metrics(&freq, &residual, 20.0, 20000.0).unwrap();
+        let worst: Vec<(f64, f64)> = metrics
+            .worst_fit
+            .iter()
+            .map(|w| (w.freq, w.deviation_db))
+            .collect();
+        assert_eq!(worst, vec![(600.0, -4.0), (300.0, 3.0)]);
+    }
+
+    #[test]
+    fn test_measurement_spread() {
+        let freq = Array1::from_vec(vec![100.0, 1000.0, 10000.0]);
+        let residuals = vec![
+            Array1::from_vec(vec![1.0, 0.0, 2.0]),
+            Array1::from_vec(vec![-1.0, 0.0, 2.0]),
+        ];
+        let spread = measurement_spread(&freq, &residuals, &[1.0, 1.0], 20.0, 20000.0).unwrap();
+        assert!((spread.residual_rms_db[0] - (5.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(spread.worst_rms_db, spread.residual_rms_db[0]);
+        // Only the 100 Hz point differs, by one dB either side of the mean
+        assert!((spread.max_std_db - 1.0).abs() < 1e-12);
+        assert!((spread.mean_std_db - 1.0 / 3.0).abs() < 1e-12);
+
+        assert!(measurement_spread(&freq, &residuals, &[1.0, 1.0], 200.0, 500.0).is_none());
+    }
+}
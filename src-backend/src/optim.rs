@@ -1,51 +1,68 @@
+use crate::choices::{Algorithm, CurveAggregate, Loss, PeqModel};
+use crate::devices::DeviceProfile;
+use crate::filters::{
+    DecodedFilter, FilterLock, PinnedFilter, assign_slots, decode_filters, encode_filter,
+    filters_response, peak_boost_db, recommended_preamp_db,
+};
+use crate::greedy::greedy_peaks;
+use crate::grid::FrequencyGrid;
+use crate::loss::{BoostPenalty, WeightingCurve, shape_curves};
+use crate::manifest::{RunManifest, curve_hashes};
+use crate::measurements::{CapturedMeasurement, aggregate_curves, normalized_curves};
+use crate::metrics::{MeasurementSpread, ResidualMetrics, measurement_spread, residual_metrics};
+use crate::optim_nlopt::{
+    BoostLimit, PenalizedObjective, refine_with_callback, run_nlopt_optimization_with_callback,
+};
 use crate::plot::{OptimizationPlotParams, PlotData, generate_optimization_plots};
 use autoeq::{LossType, cli::Args as AutoEQArgs};
 use ndarray::Array1;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 
-// Global cancellation state
-pub struct CancellationState {
-    pub cancelled: AtomicBool,
+pub use crate::cancellation::{CancelReason, CancellationToken};
+pub use crate::events::{OptimizationEvent, optimization_events};
+pub use crate::jobs::{JobId, JobInfo, JobManager, JobState};
+pub use crate::validation::validate_params;
+
+/// Error returned by [`run_optimization_internal`]
+#[derive(Debug, Clone)]
+pub enum OptimizationError {
+    /// The run was stopped through its cancellation token
+    Cancelled(CancelReason),
+    InvalidParams(String),
+    Failed(String),
 }
 
-impl Clone for CancellationState {
-    fn clone(&self) -> Self {
-        Self {
-            cancelled: AtomicBool::new(self.cancelled.load(Ordering::Relaxed)),
+impl std::fmt::Display for OptimizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptimizationError::Cancelled(reason) => write!(f, "{}", reason),
+            OptimizationError::InvalidParams(msg) => write!(f, "Invalid parameters: {}", msg),
+            OptimizationError::Failed(msg) => write!(f, "Optimization failed: {}", msg),
         }
     }
 }
 
-impl Default for CancellationState {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+impl std::error::Error for OptimizationError {}
 
-impl CancellationState {
-    pub fn new() -> Self {
-        Self {
-            cancelled: AtomicBool::new(false),
+impl From<Box<dyn std::error::Error + Send + Sync>> for OptimizationError {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        match err.downcast::<OptimizationError>() {
+            Ok(err) => *err,
+            Err(err) => OptimizationError::Failed(err.to_string()),
         }
     }
+}
 
-    pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::Relaxed);
-    }
-
-    pub fn reset(&self) {
-        self.cancelled.store(false, Ordering::Relaxed);
-    }
-
+impl OptimizationError {
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::Relaxed)
+        matches!(self, OptimizationError::Cancelled(_))
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationParams {
     pub num_filters: usize,
     pub curve_path: Option<String>,
@@ -61,7 +78,7 @@ pub struct OptimizationParams {
     pub version: Option<String>,
     pub measurement: Option<String>,
     pub curve_name: String,
-    pub algo: String,
+    pub algo: Algorithm,
     pub population: usize,
     pub maxeval: usize,
     pub refine: bool,
@@ -70,8 +87,9 @@ pub struct OptimizationParams {
     pub spacing_weight: f64,
     pub smooth: bool,
     pub smooth_n: usize,
-    pub loss: String,              // "flat", "score", or "mixed"
-    pub peq_model: Option<String>, // New PEQ model system: "pk", "hp-pk", "hp-pk-lp", etc.
+    pub loss: Loss,
+    #[serde(default)]
+    pub peq_model: PeqModel,
     // DE-specific parameters
     pub strategy: Option<String>,
     pub de_f: Option<f64>,
@@ -86,14 +104,141 @@ pub struct OptimizationParams {
     pub captured_magnitudes: Option<Vec<f64>>,
     pub target_frequencies: Option<Vec<f64>>,
     pub target_magnitudes: Option<Vec<f64>>,
+    // Several captured measurements of the same device, fitted through their aggregate
+    #[serde(default)]
+    pub captured_measurements: Vec<CapturedMeasurement>,
+    #[serde(default)]
+    pub curve_aggregate: CurveAggregate,
+    // Reproducibility: a fixed seed and thread setup give identical runs
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub parallel_threads: usize, // 0 = use all cores
+    #[serde(default)]
+    pub no_parallel: bool,
+    // Warm start: seed the optimizer with these filters instead of autoeq's guess
+    #[serde(default)]
+    pub initial_filters: Option<Vec<DecodedFilter>>,
+    // Seed the optimizer with the greedy peak-picking fit
+    #[serde(default)]
+    pub greedy_init: bool,
+    // Filters kept through the optimization, fixed or within a range
+    #[serde(default)]
+    pub pinned_filters: Vec<PinnedFilter>,
+    // Largest boost of the combined EQ in dB, None = unlimited
+    #[serde(default)]
+    pub max_boost_db: Option<f64>,
+    // Hardware EQ the result must play on, None = unconstrained
+    #[serde(default)]
+    pub device_profile: Option<DeviceProfile>,
+    // Flat losses only: weight of the error by frequency, None = uniform
+    #[serde(default)]
+    pub loss_weighting: Option<WeightingCurve>,
+    // Flat losses only: extra cost of EQ boosts over cuts, 0 = symmetric
+    #[serde(default)]
+    pub boost_penalty: f64,
+    // Grid the curves are resampled onto, for the optimization and the plots
+    #[serde(default)]
+    pub frequency_grid: FrequencyGrid,
+}
+
+impl OptimizationParams {
+    /// Whether the flat loss is weighted or penalizes boosts
+    pub(crate) fn shapes_loss(&self) -> bool {
+        self.loss_weighting.is_some() || self.boost_penalty > 0.0
+    }
+
+    /// Weight of each of `captured_measurements`
+    fn measurement_weights(&self) -> Vec<f64> {
+        self.captured_measurements
+            .iter()
+            .map(|m| m.weight)
+            .collect()
+    }
+
+    /// Pick a random seed if none is set and return the seed in use
+    pub fn resolve_seed(&mut self) -> u64 {
+        *self.seed.get_or_insert_with(|| {
+            use std::hash::{BuildHasher, Hasher};
+            let random = std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish();
+            // Keep the seed exactly representable as a JavaScript number
+            random & ((1u64 << 53) - 1)
+        })
+    }
+}
+
+impl Default for OptimizationParams {
+    /// Defaults match OPTIMIZATION_DEFAULTS from optimization-constants.ts
+    fn default() -> Self {
+        Self {
+            num_filters: 5,
+            curve_path: None,
+            target_path: None,
+            sample_rate: 48000.0,
+            max_db: 3.0,
+            min_db: 1.0,
+            max_q: 3.0,
+            min_q: 1.0,
+            min_freq: 60.0,
+            max_freq: 16000.0,
+            speaker: None,
+            version: None,
+            measurement: None,
+            curve_name: "Listening Window".to_string(),
+            algo: Algorithm::AutoeqDe,
+            population: 30,
+            maxeval: 20000,
+            refine: false,
+            local_algo: "cobyla".to_string(),
+            min_spacing_oct: 0.5,
+            spacing_weight: 20.0,
+            smooth: true,
+            smooth_n: 1,
+            loss: Loss::SpeakerFlat,
+            peq_model: PeqModel::Pk,
+            strategy: Some("currenttobest1bin".to_string()),
+            de_f: Some(0.8),
+            de_cr: Some(0.9),
+            adaptive_weight_f: Some(0.8),
+            adaptive_weight_cr: Some(0.7),
+            tolerance: Some(1e-3),
+            atolerance: Some(1e-4),
+            captured_frequencies: None,
+            captured_magnitudes: None,
+            target_frequencies: None,
+            target_magnitudes: None,
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            frequency_grid: FrequencyGrid::default(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct OptimizationResult {
     pub success: bool,
+    pub cancelled: bool,
+    pub truncated: bool, // Time budget ran out, the filters are the best found by then
     pub error_message: Option<String>,
-    pub filter_params: Option<Vec<f64>>,
-    pub objective_value: Option<f64>,
+    pub filter_params: Option<Vec<f64>>, // Raw optimizer vector, decoded in `filters`
+    pub filters: Option<Vec<DecodedFilter>>, // Typed filters, in optimizer order
+    pub peq_model: Option<PeqModel>,     // Model used to decode `filters`
+    pub filter_locks: Option<Vec<FilterLock>>, // How each of `filters` was optimized
+    pub preamp_db: Option<f64>,          // Gain before the EQ that avoids clipping, <= 0
+    pub objective_value: Option<f64>,    // Final loss of the optimized filters
+    pub residual_metrics: Option<ResidualMetrics>, // Deviation from the target after EQ
+    pub measurement_spread: Option<MeasurementSpread>, // Across `captured_measurements`, when set
     pub preference_score_before: Option<f64>,
     pub preference_score_after: Option<f64>,
     pub filter_response: Option<PlotData>,
@@ -101,6 +246,32 @@ pub struct OptimizationResult {
     pub filter_plots: Option<PlotData>, // Individual filter responses and sum
     pub input_curve: Option<PlotData>,  // Original normalized input curve
     pub deviation_curve: Option<PlotData>, // Target - Input (what needs to be corrected)
+    pub run_manifest: Option<RunManifest>, // Everything needed to replay this run
+}
+
+impl OptimizationResult {
+    /// Result of a run that failed with `message`
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self {
+            error_message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Result of a run stopped through its cancellation token
+    pub fn cancelled(reason: CancelReason) -> Self {
+        Self {
+            cancelled: true,
+            ..Self::failure(reason.to_string())
+        }
+    }
+
+    pub fn from_error(err: &OptimizationError) -> Self {
+        match err {
+            OptimizationError::Cancelled(reason) => Self::cancelled(*reason),
+            _ => Self::failure(err.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,181 +280,50 @@ pub struct ProgressUpdate {
     pub fitness: f64,
     pub params: Vec<f64>,
     pub convergence: f64,
+    #[serde(default)]
+    pub evaluations: Option<usize>, // Objective evaluations so far, when the algorithm reports them
 }
 
-pub fn validate_params(
-    params: &OptimizationParams,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Validate number of filters
-    if params.num_filters == 0 {
-        return Err("Number of filters must be at least 1".into());
-    }
-    if params.num_filters > 50 {
-        return Err(format!(
-            "Number of filters must be between 1 and 50 (got: {})",
-            params.num_filters
-        )
-        .into());
-    }
-
-    // Validate tolerance (lower bound 1e-12, no upper bound)
-    if let Some(tol) = params.tolerance
-        && tol < 1e-12
-    {
-        return Err(format!("Tolerance must be >= 1e-12 (got: {})", tol).into());
-    }
-
-    // Validate absolute tolerance (lower bound 0, no upper bound)
-    if let Some(atol) = params.atolerance
-        && atol < 1e-15
-    {
-        return Err(format!("Absolute tolerance must be >= 1e-15 (got: {})", atol).into());
-    }
-
-    // Validate frequency range
-    if params.min_freq >= params.max_freq {
-        return Err(format!(
-            "Minimum frequency ({} Hz) must be less than maximum frequency ({} Hz)",
-            params.min_freq, params.max_freq
-        )
-        .into());
-    }
-    if params.min_freq < 20.0 {
-        return Err(format!(
-            "Minimum frequency must be >= 20 Hz (got: {} Hz)",
-            params.min_freq
-        )
-        .into());
-    }
-    if params.max_freq > 20000.0 {
-        return Err(format!(
-            "Maximum frequency must be <= 20,000 Hz (got: {} Hz)",
-            params.max_freq
-        )
-        .into());
-    }
-
-    // Validate Q range
-    if params.min_q >= params.max_q {
-        return Err(format!(
-            "Minimum Q ({}) must be less than maximum Q ({})",
-            params.min_q, params.max_q
-        )
-        .into());
-    }
-    if params.min_q < 0.1 {
-        return Err(format!("Minimum Q must be >= 0.1 (got: {})", params.min_q).into());
-    }
-    if params.max_q > 20.0 {
-        return Err(format!("Maximum Q must be <= 100 (got: {})", params.max_q).into());
-    }
-
-    // Validate dB range
-    if params.min_db > params.max_db {
-        return Err(format!(
-            "Minimum dB ({}) must be <= maximum dB ({})",
-            params.min_db, params.max_db
-        )
-        .into());
-    }
-    if params.min_db < 0.25 {
-        return Err(format!("Minimum dB must be >= 0.25 (got: {})", params.min_db).into());
-    }
-    if params.max_db > 20.0 {
-        return Err(format!("Maximum dB must be <= 20 (got: {})", params.max_db).into());
-    }
-
-    // Validate sample rate
-    if params.sample_rate < 8000.0 || params.sample_rate > 192000.0 {
-        return Err(format!(
-            "Sample rate must be between 8,000 and 192,000 Hz (got: {} Hz)",
-            params.sample_rate
-        )
-        .into());
-    }
-
-    // Validate population size
-    if params.population == 0 {
-        return Err("Population size must be at least 1".into());
-    }
-    if params.population > 10000 {
-        return Err(format!(
-            "Population size must be between 1 and 10,000 (got: {})",
-            params.population
-        )
-        .into());
-    }
-
-    // Validate max evaluations
-    if params.maxeval == 0 {
-        return Err("Maximum evaluations must be at least 1".into());
-    }
-
-    // Validate smoothing N
-    if params.smooth_n < 1 || params.smooth_n > 24 {
-        return Err(format!(
-            "Smoothing N must be between 1 and 24 (got: {})",
-            params.smooth_n
-        )
-        .into());
-    }
-
-    // Validate DE parameters if present
-    if let Some(de_f) = params.de_f
-        && !(0.0..=2.0).contains(&de_f)
-    {
-        return Err(format!(
-            "Mutation factor (F) must be between 0 and 2 (got: {})",
-            de_f
-        )
-        .into());
-    }
-
-    if let Some(de_cr) = params.de_cr
-        && !(0.0..=1.0).contains(&de_cr)
-    {
-        return Err(format!(
-            "Recombination probability (CR) must be between 0 and 1 (got: {})",
-            de_cr
-        )
-        .into());
-    }
-
-    // Validate adaptive weights
-    if let Some(w) = params.adaptive_weight_f
-        && !(0.0..=1.0).contains(&w)
-    {
-        return Err(format!("Adaptive weight F must be between 0 and 1 (got: {})", w).into());
-    }
-
-    if let Some(w) = params.adaptive_weight_cr
-        && !(0.0..=1.0).contains(&w)
-    {
-        return Err(format!("Adaptive weight CR must be between 0 and 1 (got: {})", w).into());
-    }
-
-    Ok(())
+/// Stage of an optimization run, reported through [`ProgressCallback::on_phase`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizationPhase {
+    /// Loading the measurement and target curves
+    Loading,
+    /// Curves are loaded and the objective is set up
+    TargetBuilt,
+    /// The optimizer is running; progress updates follow
+    Optimizing,
+    /// Computing scores and plots of the result
+    Scoring,
 }
 
 /// Trait for receiving progress updates during optimization
 pub trait ProgressCallback: Send + Sync {
     fn on_progress(&self, update: ProgressUpdate) -> bool;
+
+    /// Called when the run enters a new phase
+    fn on_phase(&self, _phase: OptimizationPhase) {}
 }
 
 /// Helper function to run metaheuristics optimization with progress callbacks
 fn run_mh_optimization_with_callback<P: ProgressCallback + 'static>(
     args: &AutoEQArgs,
     objective_data: &autoeq::optim::ObjectiveData,
+    search_space: Option<SearchSpace>,
     progress_callback: Arc<P>,
-    cancellation_state: Arc<CancellationState>,
+    cancellation_token: CancellationToken,
 ) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
     use autoeq::optim::AlgorithmCategory;
     use autoeq::optim::parse_algorithm_name;
     use autoeq::optim_mh::{MHIntermediate, optimize_filters_mh_with_callback};
-    use autoeq::workflow::{initial_guess, setup_bounds};
 
-    let (lower_bounds, upper_bounds) = setup_bounds(args);
-    let mut x = initial_guess(args, &lower_bounds, &upper_bounds);
+    let SearchSpace {
+        lower_bounds,
+        upper_bounds,
+        initial_x: mut x,
+        ..
+    } = search_space.unwrap_or_else(|| SearchSpace::from_args(args));
 
     // Parse algorithm name to extract MH algorithm type
     let algo_name = if let Some(AlgorithmCategory::Metaheuristics(mh_name)) =
@@ -297,7 +337,7 @@ fn run_mh_optimization_with_callback<P: ProgressCallback + 'static>(
     let mut progress_count = 0;
     let callback = Box::new(move |intermediate: &MHIntermediate| {
         // Check for cancellation
-        if cancellation_state.is_cancelled() {
+        if cancellation_token.is_cancelled() {
             println!(
                 "[RUST DEBUG] MH optimization cancelled during iteration {}",
                 intermediate.iter
@@ -320,6 +360,7 @@ fn run_mh_optimization_with_callback<P: ProgressCallback + 'static>(
             fitness: intermediate.fun,
             params: intermediate.x.to_vec(),
             convergence: 0.0, // MH doesn't provide convergence info
+            evaluations: None,
         });
 
         if !continue_optimization {
@@ -355,28 +396,84 @@ fn run_mh_optimization_with_callback<P: ProgressCallback + 'static>(
     }
 }
 
-pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
-    params: OptimizationParams,
-    progress_callback: Arc<P>,
-    cancellation_state: Arc<CancellationState>,
-) -> Result<OptimizationResult, Box<dyn std::error::Error + Send + Sync>> {
-    println!("[RUST DEBUG] run_optimization_internal started");
+/// Bounds and starting point of the optimizer
+#[derive(Clone)]
+pub(crate) struct SearchSpace {
+    pub(crate) lower_bounds: Vec<f64>,
+    pub(crate) upper_bounds: Vec<f64>,
+    pub(crate) initial_x: Vec<f64>,
+    pub(crate) bounded_slots: Vec<usize>, // Slots of the pinned filters with a range
+}
 
-    // Check for cancellation at start
-    if cancellation_state.is_cancelled() {
-        return Err("Optimization cancelled before start".into());
+impl SearchSpace {
+    /// autoeq's bounds and initial guess
+    pub(crate) fn from_args(args: &AutoEQArgs) -> Self {
+        use autoeq::workflow::{initial_guess, setup_bounds};
+
+        let (lower_bounds, upper_bounds) = setup_bounds(args);
+        let initial_x = initial_guess(args, &lower_bounds, &upper_bounds);
+        Self {
+            lower_bounds,
+            upper_bounds,
+            initial_x,
+            bounded_slots: Vec::new(),
+        }
     }
 
-    // Validate parameters first
-    println!("[RUST DEBUG] Validating parameters...");
-    validate_params(&params)?;
-    println!("[RUST DEBUG] Parameters validated successfully");
+    /// Search space of a warm-started run or a run with bounded pinned filters
+    ///
+    /// Returns `None` when neither is set, so autoeq's own defaults apply.
+    /// Pinned filters get their slots first; initial filters fill the others,
+    /// and slots left over keep autoeq's initial guess.
+    fn for_params(args: &AutoEQArgs, params: &OptimizationParams) -> Result<Option<Self>, String> {
+        let bounded: Vec<&PinnedFilter> = params
+            .pinned_filters
+            .iter()
+            .filter(|pinned| pinned.range.is_some())
+            .collect();
+        if params.initial_filters.is_none() && bounded.is_empty() {
+            return Ok(None);
+        }
 
-    // Convert parameters to AutoEQ Args structure
-    let args = AutoEQArgs {
+        let mut space = Self::from_args(args);
+        let mut used = vec![false; args.num_filters];
+
+        let pinned: Vec<DecodedFilter> = bounded.iter().map(|p| p.filter.clone()).collect();
+        space.bounded_slots = assign_slots(&pinned, params.peq_model, &mut used)?;
+        for (pinned, &slot) in bounded.iter().zip(&space.bounded_slots) {
+            if let Some(range) = &pinned.range {
+                let (lower, upper) = range.bounds();
+                space.lower_bounds[slot * 3..slot * 3 + 3].copy_from_slice(&lower);
+                space.upper_bounds[slot * 3..slot * 3 + 3].copy_from_slice(&upper);
+            }
+            encode_filter(&pinned.filter, slot, &mut space.initial_x);
+        }
+
+        if let Some(filters) = &params.initial_filters {
+            let slots = assign_slots(filters, params.peq_model, &mut used)?;
+            for (filter, slot) in filters.iter().zip(slots) {
+                encode_filter(filter, slot, &mut space.initial_x);
+            }
+        }
+
+        for ((value, lower), upper) in space
+            .initial_x
+            .iter_mut()
+            .zip(&space.lower_bounds)
+            .zip(&space.upper_bounds)
+        {
+            *value = value.max(*lower).min(*upper);
+        }
+        Ok(Some(space))
+    }
+}
+
+/// Convert UI/CLI parameters to the AutoEQ argument structure
+pub(crate) fn build_autoeq_args(params: &OptimizationParams) -> AutoEQArgs {
+    AutoEQArgs {
         num_filters: params.num_filters,
-        curve: params.curve_path.map(PathBuf::from),
-        target: params.target_path.map(PathBuf::from),
+        curve: params.curve_path.as_ref().map(PathBuf::from),
+        target: params.target_path.as_ref().map(PathBuf::from),
         sample_rate: params.sample_rate,
         max_db: params.max_db,
         min_db: params.min_db,
@@ -385,36 +482,21 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         min_freq: params.min_freq,
         max_freq: params.max_freq,
         output: None, // We'll handle plotting in the frontend
-        speaker: params.speaker,
-        version: params.version,
-        measurement: params.measurement,
-        curve_name: params.curve_name,
-        algo: params.algo,
+        speaker: params.speaker.clone(),
+        version: params.version.clone(),
+        measurement: params.measurement.clone(),
+        curve_name: params.curve_name.clone(),
+        algo: params.algo.to_string(),
         population: params.population,
         maxeval: params.maxeval,
         refine: params.refine,
-        local_algo: params.local_algo,
+        local_algo: params.local_algo.clone(),
         min_spacing_oct: params.min_spacing_oct,
         spacing_weight: params.spacing_weight,
         smooth: params.smooth,
         smooth_n: params.smooth_n,
-        loss: match params.loss.as_str() {
-            "flat" => LossType::SpeakerFlat,
-            "score" => LossType::SpeakerScore,
-            "speaker-flat" => LossType::SpeakerFlat,
-            "speaker-score" => LossType::SpeakerScore,
-            "headphone-flat" => LossType::HeadphoneFlat,
-            "headphone-score" => LossType::HeadphoneScore,
-            _ => LossType::SpeakerFlat,
-        },
-        peq_model: match params.peq_model.as_deref() {
-            Some("hp-pk") => autoeq::cli::PeqModel::HpPk,
-            Some("hp-pk-lp") => autoeq::cli::PeqModel::HpPkLp,
-            Some("free-pk-free") => autoeq::cli::PeqModel::FreePkFree,
-            Some("free") => autoeq::cli::PeqModel::Free,
-            Some("pk") => autoeq::cli::PeqModel::Pk,
-            _ => autoeq::cli::PeqModel::Pk, // Default to Pk
-        },
+        loss: params.loss.into(),
+        peq_model: params.peq_model.into(),
         peq_model_list: false,
         algo_list: false, // UI doesn't need to list algorithms
         tolerance: params.tolerance.unwrap_or(1e-3), // Use provided tolerance or default
@@ -422,19 +504,85 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         recombination: params.de_cr.unwrap_or(0.9), // DE crossover probability
         strategy: params
             .strategy
+            .clone()
             .unwrap_or_else(|| "currenttobest1bin".to_string()), // DE strategy
         strategy_list: false, // UI doesn't need to list strategies
         adaptive_weight_f: params.adaptive_weight_f.unwrap_or(0.8), // Adaptive weight for F
         adaptive_weight_cr: params.adaptive_weight_cr.unwrap_or(0.7), // Adaptive weight for CR
-        no_parallel: false,
-        parallel_threads: 0,
-        seed: None, // Random seed for deterministic optimization (None = random)
+        no_parallel: params.no_parallel,
+        parallel_threads: params.parallel_threads,
+        seed: params.seed, // Random seed for deterministic optimization (None = random)
         qa: None, // Quality assurance mode disabled for UI (None = disabled)
-    };
+    }
+}
 
+/// Input, target and deviation curves on the analysis frequency grid
+pub(crate) struct PreparedCurves {
+    pub(crate) input_curve: autoeq::Curve,
+    pub(crate) target_curve: autoeq::Curve,
+    pub(crate) deviation_curve: autoeq::Curve,
+    pub(crate) spin_data: Option<HashMap<String, autoeq::Curve>>,
+}
+
+/// CEA2034 directivity indices, differences of two curves that an EQ leaves unchanged
+const DIRECTIVITY_CURVES: [&str; 2] = ["Early Reflections DI", "Sound Power DI"];
+
+impl PreparedCurves {
+    /// Curves as measured through `filters`, except the directivity indices
+    fn with_filters(&self, filters: &[DecodedFilter], sample_rate: f64) -> Self {
+        let apply = |curve: &autoeq::Curve| autoeq::Curve {
+            freq: curve.freq.clone(),
+            spl: &curve.spl + &filters_response(filters, &curve.freq, sample_rate),
+        };
+        let response = filters_response(filters, &self.input_curve.freq, sample_rate);
+        Self {
+            input_curve: apply(&self.input_curve),
+            target_curve: self.target_curve.clone(),
+            deviation_curve: autoeq::Curve {
+                freq: self.deviation_curve.freq.clone(),
+                spl: &self.deviation_curve.spl - &response,
+            },
+            spin_data: self.spin_data.as_ref().map(|spin| {
+                spin.iter()
+                    .map(|(name, curve)| {
+                        let curve = if DIRECTIVITY_CURVES.contains(&name.as_str()) {
+                            curve.clone()
+                        } else {
+                            apply(curve)
+                        };
+                        (name.clone(), curve)
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Load the input curve (captured data, file or API) and build the target curve
+pub(crate) async fn prepare_curves(
+    params: &OptimizationParams,
+    args: &AutoEQArgs,
+) -> Result<PreparedCurves, Box<dyn std::error::Error + Send + Sync>> {
     // Load input data (following autoeq.rs pattern)
     println!("[RUST DEBUG] Loading input curve...");
-    let (input_curve_raw, spin_data_raw) = if let (Some(captured_freqs), Some(captured_mags)) =
+    let grid_freq = params.frequency_grid.frequencies();
+    let (input_curve_raw, spin_data_raw) = if !params.captured_measurements.is_empty() {
+        // Fit the aggregate of the normalized measurements
+        println!(
+            "[RUST DEBUG] Using the {} of {} captured measurements",
+            params.curve_aggregate,
+            params.captured_measurements.len()
+        );
+        let curves = normalized_curves(&params.captured_measurements, &grid_freq);
+        (
+            aggregate_curves(
+                &curves,
+                &params.measurement_weights(),
+                params.curve_aggregate,
+            ),
+            None,
+        )
+    } else if let (Some(captured_freqs), Some(captured_mags)) =
         (&params.captured_frequencies, &params.captured_magnitudes)
     {
         // Use captured audio data
@@ -449,7 +597,7 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         (captured_curve, None)
     } else {
         // Load from file or API
-        autoeq::workflow::load_input_curve(&args).await.map_err(
+        autoeq::workflow::load_input_curve(args).await.map_err(
             |e| -> Box<dyn std::error::Error + Send + Sync> {
                 println!("[RUST DEBUG] Failed to load input curve: {}", e);
                 Box::new(std::io::Error::other(e.to_string()))
@@ -461,15 +609,6 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         input_curve_raw.freq.len()
     );
 
-    // Check for cancellation after data loading
-    if cancellation_state.is_cancelled() {
-        return Err("Optimization cancelled during data loading".into());
-    }
-
-    // Resample everything to standard frequency grid
-    println!("[RUST DEBUG] Creating standard frequency grid...");
-    let standard_freq = autoeq::read::create_log_frequency_grid(200, 20.0, 20000.0);
-
     // Build/Get target curve
     let target_curve = if let (Some(target_freqs), Some(target_mags)) =
         (&params.target_frequencies, &params.target_magnitudes)
@@ -483,17 +622,17 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
             freq: Array1::from_vec(target_freqs.clone()),
             spl: Array1::from_vec(target_mags.clone()),
         };
-        autoeq::read::normalize_and_interpolate_response(&standard_freq, &target_curve_raw)
+        autoeq::read::normalize_and_interpolate_response(&grid_freq, &target_curve_raw)
     } else {
         // Build target using RAW input curve (before normalization)
         println!("[RUST DEBUG] Building target curve using raw input...");
-        autoeq::workflow::build_target_curve(&args, &standard_freq, &input_curve_raw)
+        autoeq::workflow::build_target_curve(args, &grid_freq, &input_curve_raw)
     };
 
     // Normalize input curve AFTER building target
     println!("[RUST DEBUG] Normalizing and interpolating input curve...");
     let input_curve =
-        autoeq::read::normalize_and_interpolate_response(&standard_freq, &input_curve_raw);
+        autoeq::read::normalize_and_interpolate_response(&grid_freq, &input_curve_raw);
 
     // Create deviation curve
     let deviation_curve = autoeq::Curve {
@@ -510,7 +649,7 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         spin_data
             .into_iter()
             .map(|(name, curve)| {
-                let interpolated = autoeq::read::interpolate_log_space(&standard_freq, &curve);
+                let interpolated = autoeq::read::interpolate_log_space(&grid_freq, &curve);
                 (name, interpolated)
             })
             .collect()
@@ -518,194 +657,720 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
 
     println!("[RUST DEBUG] Target curve and data processing completed");
 
-    // Setup objective data
-    println!("[RUST DEBUG] Setting up objective data...");
-    let (objective_data, use_cea) = autoeq::workflow::setup_objective_data(
-        &args,
-        &input_curve,
-        &target_curve,
-        &deviation_curve,
-        &spin_data,
-    );
-    println!(
-        "[RUST DEBUG] Objective data setup complete, use_cea: {}",
-        use_cea
-    );
+    Ok(PreparedCurves {
+        input_curve,
+        target_curve,
+        deviation_curve,
+        spin_data,
+    })
+}
 
-    // Get preference score before optimization if applicable
-    let mut pref_score_before: Option<f64> = None;
+/// Compute the preference score of the input curve, optionally corrected by an EQ response
+///
+/// Returns `None` when the loss has no associated preference model.
+async fn compute_preference_score(
+    args: &AutoEQArgs,
+    curves: &PreparedCurves,
+    use_cea: bool,
+    peq_response: Option<&Array1<f64>>,
+) -> Option<f64> {
     if use_cea
+        && let Some(spin_data) = curves.spin_data.as_ref()
         && let Ok(metrics) = autoeq::cea2034::compute_cea2034_metrics(
-            &input_curve.freq,
-            spin_data.as_ref().unwrap(),
-            None,
+            &curves.input_curve.freq,
+            spin_data,
+            peq_response,
         )
         .await
     {
-        pref_score_before = Some(metrics.pref_score);
+        Some(metrics.pref_score)
     } else if args.loss == LossType::HeadphoneFlat || args.loss == LossType::HeadphoneScore {
         // Calculate headphone preference score using Olive et al. model
-        println!("[RUST DEBUG] Calculating headphone preference score before optimization");
+        let corrected_curve = match peq_response {
+            Some(response) => autoeq::Curve {
+                freq: curves.input_curve.freq.clone(),
+                spl: &curves.input_curve.spl + response,
+            },
+            None => curves.input_curve.clone(),
+        };
         let headphone_data = autoeq::loss::HeadphoneLossData::new(args.smooth, args.smooth_n);
-        let loss_value =
-            autoeq::loss::headphone_loss_with_target(&headphone_data, &input_curve, &target_curve);
+        let loss_value = autoeq::loss::headphone_loss_with_target(
+            &headphone_data,
+            &corrected_curve,
+            &curves.target_curve,
+        );
         // Negate the loss value to convert to preference score (higher is better)
-        pref_score_before = Some(-loss_value);
+        Some(-loss_value)
+    } else {
+        None
+    }
+}
+
+/// Compute the preference score of a measurement without running an optimization
+///
+/// Uses the same loading and target-building path as [`run_optimization_internal`],
+/// so the value matches `preference_score_before` of a full run.
+pub async fn score_curve(
+    params: &OptimizationParams,
+) -> Result<Option<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    let args = build_autoeq_args(params);
+    let curves = prepare_curves(params, &args).await?;
+    let (_objective_data, use_cea) = autoeq::workflow::setup_objective_data(
+        &args,
+        &curves.input_curve,
+        &curves.target_curve,
+        &curves.deviation_curve,
+        &curves.spin_data,
+    );
+    Ok(compute_preference_score(&args, &curves, use_cea, None).await)
+}
+
+/// Run the selected algorithm with progress reporting and cancellation
+///
+/// DE and metaheuristics report through their own callbacks, NLopt through a
+/// wrapped `objective`.
+fn run_algorithm<P: ProgressCallback + 'static>(
+    algorithm: Algorithm,
+    args: &AutoEQArgs,
+    objective_data: &autoeq::optim::ObjectiveData,
+    objective: &PenalizedObjective,
+    search_space: Option<SearchSpace>,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    println!(
+        "[RUST DEBUG] Using algorithm with progress reporting: {}",
+        args.algo
+    );
+    let mut progress_count = 0;
+    let progress_callback_clone = Arc::clone(&progress_callback);
+
+    if algorithm == Algorithm::AutoeqDe {
+        // Use DE-specific callback
+        let callback = Box::new(move |intermediate: &autoeq::de::DEIntermediate| {
+            // Check for cancellation in callback
+            if cancellation_token.is_cancelled() {
+                println!(
+                    "[RUST DEBUG] Optimization cancelled during iteration {}",
+                    intermediate.iter
+                );
+                return autoeq::de::CallbackAction::Stop;
+            }
+            progress_count += 1;
+            if progress_count % 10 == 0 || progress_count <= 5 {
+                println!(
+                    "[RUST DEBUG] Progress update #{}: iter={}, fitness={:.6}, convergence={:.4}",
+                    progress_count, intermediate.iter, intermediate.fun, intermediate.convergence
+                );
+            }
+            let continue_opt = progress_callback_clone.on_progress(ProgressUpdate {
+                iteration: intermediate.iter,
+                fitness: intermediate.fun,
+                params: intermediate.x.to_vec(),
+                convergence: intermediate.convergence,
+                evaluations: None,
+            });
+            if !continue_opt {
+                println!("[RUST DEBUG] Optimization stopped by progress callback");
+                return autoeq::de::CallbackAction::Stop;
+            }
+            if progress_count % 50 == 0 {
+                println!(
+                    "[RUST DEBUG] Progress callback invoked successfully (count: {})",
+                    progress_count
+                );
+            }
+            autoeq::de::CallbackAction::Continue
+        });
+        let refine_token = cancellation_token.clone();
+        match search_space {
+            None => {
+                autoeq::workflow::perform_optimization_with_callback(args, objective_data, callback)
+                    .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
+                        println!("[RUST DEBUG] DE optimization failed: {}", e);
+                        Box::new(std::io::Error::other(e.to_string()))
+                    })?
+            }
+            Some(space) => {
+                // The workflow always uses autoeq's bounds and guess, run DE directly
+                println!("[RUST DEBUG] Running DE in a custom search space");
+                let mut x = space.initial_x.clone();
+                autoeq::optim::optimize_filters_autoeq_with_callback(
+                    &mut x,
+                    &space.lower_bounds,
+                    &space.upper_bounds,
+                    objective_data.clone(),
+                    args,
+                    callback,
+                )
+                .map_err(
+                    |(e, _final_value)| -> Box<dyn std::error::Error + Send + Sync> {
+                        println!("[RUST DEBUG] DE optimization failed: {}", e);
+                        Box::new(std::io::Error::other(e))
+                    },
+                )?;
+
+                // Keep the start if DE ends worse, so a warm start is never lost
+                let fitness = |x: &[f64]| {
+                    autoeq::optim::compute_fitness_penalties(x, None, &mut objective_data.clone())
+                };
+                if fitness(&space.initial_x) < fitness(&x) {
+                    println!("[RUST DEBUG] DE did not improve on its starting point");
+                    x = space.initial_x.clone();
+                }
+
+                // Refine like the workflow does after its own DE run
+                if args.refine && !refine_token.is_cancelled() {
+                    refine_with_callback(
+                        args,
+                        objective,
+                        SearchSpace {
+                            initial_x: x,
+                            ..space
+                        },
+                        progress_callback,
+                        refine_token,
+                    )?
+                } else {
+                    x
+                }
+            }
+        }
+    } else if algorithm.is_metaheuristic() {
+        // Use metaheuristics-specific optimization path
+        println!("[RUST DEBUG] Using metaheuristics algorithm with progress reporting");
+        run_mh_optimization_with_callback(
+            args,
+            objective_data,
+            search_space,
+            progress_callback,
+            cancellation_token,
+        )?
+    } else {
+        // Use NLopt driver with a wrapped objective
+        println!("[RUST DEBUG] Using NLopt algorithm with progress reporting");
+        run_nlopt_optimization_with_callback(
+            args,
+            objective,
+            algorithm,
+            search_space,
+            progress_callback,
+            cancellation_token,
+        )?
+    }
+}
+
+/// Boost over `max_boost_db` the final filters may keep, in dB
+const BOOST_LIMIT_TOLERANCE: f64 = 0.01;
+
+/// Local pass on `objective` after an algorithm that only saw autoeq's loss
+///
+/// DE, metaheuristics and greedy peak picking optimize autoeq's objective, so
+/// the terms SotF adds to it, like the maximum total boost, are enforced here.
+fn polish_with_objective<P: ProgressCallback + 'static>(
+    args: &AutoEQArgs,
+    objective: &PenalizedObjective,
+    space: SearchSpace,
+    x: Vec<f64>,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    if !objective.extends_autoeq() || cancellation_token.is_cancelled() {
+        return Ok(x);
+    }
+    println!(
+        "[RUST DEBUG] Polishing with {} on the full objective",
+        args.local_algo
+    );
+    refine_with_callback(
+        args,
+        objective,
+        SearchSpace {
+            initial_x: x,
+            ..space
+        },
+        progress_callback,
+        cancellation_token,
+    )
+}
+
+/// Lower the largest boost one gain step at a time until the EQ is within `limit`
+///
+/// Snapping rounds gains to the nearest step, which can undo the margin the
+/// optimizer kept below the limit. Gains stay on the device's steps; pinned
+/// filters in `locked_slots` keep theirs.
+fn step_down_boosts(
+    profile: &DeviceProfile,
+    x: &mut [f64],
+    args: &AutoEQArgs,
+    peq_model: PeqModel,
+    locked_slots: &[usize],
+    limit: &BoostLimit,
+) {
+    while limit.excess_db(x) > 0.0 {
+        let step = profile
+            .gain_step
+            .filter(|step| *step > 0.0)
+            .unwrap_or_else(|| limit.excess_db(x).max(BOOST_LIMIT_TOLERANCE));
+        let filters = decode_filters(x, args.sample_rate, peq_model);
+        let Some((slot, filter)) = filters
+            .iter()
+            .enumerate()
+            .filter(|(slot, filter)| {
+                filter.filter_type.has_gain() && filter.gain > 0.0 && !locked_slots.contains(slot)
+            })
+            .max_by(|(_, a), (_, b)| a.gain.total_cmp(&b.gain))
+        else {
+            return;
+        };
+        let lowered = DecodedFilter {
+            gain: (filter.gain - step).max(0.0),
+            ..filter.clone()
+        };
         println!(
-            "[RUST DEBUG] Headphone preference score before: {:.2}",
-            pref_score_before.unwrap()
+            "[RUST DEBUG] Lowering filter {} to {:.2} dB to keep the boost limit",
+            slot, lowered.gain
         );
+        encode_filter(&lowered, slot, x);
     }
+}
 
-    // Check for cancellation before optimization
-    if cancellation_state.is_cancelled() {
-        return Err("Optimization cancelled before starting".into());
+/// Snap the optimized filters to values `profile` accepts
+///
+/// The fixed filters are snapped before the optimization and keep their
+/// bands. Everything scored afterwards uses the snapped filters.
+fn snap_to_device(
+    profile: &DeviceProfile,
+    x: &mut [f64],
+    args: &AutoEQArgs,
+    peq_model: PeqModel,
+    fixed_filters: &[DecodedFilter],
+    objective: &mut PenalizedObjective,
+) {
+    let loss_before = objective.fitness(x);
+    let mut filters = decode_filters(x, args.sample_rate, peq_model);
+    let taken: Vec<f64> = fixed_filters.iter().map(|f| f.frequency).collect();
+    profile.snap_filters(&mut filters, &taken);
+    for (slot, filter) in filters.iter().enumerate() {
+        encode_filter(filter, slot, x);
+    }
+    let loss_after = objective.fitness(x);
+    println!(
+        "[RUST DEBUG] Snapped filters to device '{}', loss {:.6} -> {:.6}",
+        profile.id, loss_before, loss_after
+    );
+}
+
+/// Search space that only moves the gains of snapped filters
+///
+/// Frequencies, Q and the slots in `locked_slots` stay where they are; gains
+/// stay within what both the device and the optimizer bounds allow.
+fn gain_refit_space(
+    profile: &DeviceProfile,
+    x: &[f64],
+    args: &AutoEQArgs,
+    peq_model: PeqModel,
+    locked_slots: &[usize],
+) -> SearchSpace {
+    let mut space = SearchSpace {
+        lower_bounds: x.to_vec(),
+        upper_bounds: x.to_vec(),
+        initial_x: x.to_vec(),
+        bounded_slots: locked_slots.to_vec(),
+    };
+    let (min_gain, max_gain) = (
+        profile.min_gain.max(-args.max_db),
+        profile.max_gain.min(args.max_db),
+    );
+    for (slot, filter) in decode_filters(x, args.sample_rate, peq_model)
+        .iter()
+        .enumerate()
+    {
+        if filter.filter_type.has_gain() && !locked_slots.contains(&slot) {
+            let gain = slot * 3 + 2;
+            space.lower_bounds[gain] = min_gain;
+            space.upper_bounds[gain] = max_gain;
+            space.initial_x[gain] = x[gain].clamp(min_gain, max_gain);
+        }
+    }
+    space
+}
+
+/// How the corrected response varies across the captured measurements
+///
+/// `residual` is the residual of the aggregate curve; a measurement differs
+/// from it by how far the measurement is from the aggregate.
+fn spread_across_measurements(
+    params: &OptimizationParams,
+    curves: &PreparedCurves,
+    residual: &Array1<f64>,
+) -> Option<MeasurementSpread> {
+    if params.captured_measurements.is_empty() {
+        return None;
+    }
+    let freq = &curves.input_curve.freq;
+    let residuals: Vec<Array1<f64>> = normalized_curves(&params.captured_measurements, freq)
+        .iter()
+        .map(|curve| residual + &curves.input_curve.spl - &curve.spl)
+        .collect();
+    let spread = measurement_spread(
+        freq,
+        &residuals,
+        &params.measurement_weights(),
+        params.min_freq,
+        params.max_freq,
+    );
+    if let Some(spread) = &spread {
+        println!(
+            "[RUST DEBUG] Residual RMS across measurements: worst {:.2} dB, spread {:.2} dB",
+            spread.worst_rms_db, spread.mean_std_db
+        );
+    }
+    spread
+}
+
+/// Fail with [`OptimizationError::Cancelled`] once the token is cancelled
+fn check_cancelled(cancellation_token: &CancellationToken) -> Result<(), OptimizationError> {
+    match cancellation_token.cancel_reason() {
+        Some(reason) => Err(OptimizationError::Cancelled(reason)),
+        None => Ok(()),
+    }
+}
+
+pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
+    params: OptimizationParams,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<OptimizationResult, OptimizationError> {
+    println!("[RUST DEBUG] run_optimization_internal started");
+
+    // Check for cancellation at start
+    check_cancelled(&cancellation_token)?;
+
+    // Validate parameters first
+    println!("[RUST DEBUG] Validating parameters...");
+    validate_params(&params).map_err(|e| OptimizationError::InvalidParams(e.to_string()))?;
+    println!("[RUST DEBUG] Parameters validated successfully");
+
+    // Fix the seed so the run can be replayed from its manifest
+    let time_budget = cancellation_token.remaining();
+    let mut params = params;
+    let seed = params.resolve_seed();
+    println!("[RUST DEBUG] Using seed {}", seed);
+
+    // Keep the search within what the device can play
+    if let Some(profile) = params.device_profile.clone() {
+        println!(
+            "[RUST DEBUG] Limiting parameters to device '{}'",
+            profile.id
+        );
+        profile.limit_params(&mut params);
+    }
+
+    // Convert parameters to AutoEQ Args structure
+    let args = build_autoeq_args(&params);
+
+    // Warm start and bounded pinned filters change where the optimizer searches
+    let search_space =
+        SearchSpace::for_params(&args, &params).map_err(OptimizationError::InvalidParams)?;
+    let bounded_slots = search_space
+        .as_ref()
+        .map(|space| space.bounded_slots.clone())
+        .unwrap_or_default();
+    let mut fixed_filters: Vec<DecodedFilter> = params
+        .pinned_filters
+        .iter()
+        .filter(|pinned| pinned.range.is_none())
+        .map(|pinned| pinned.filter.clone())
+        .collect();
+    if let Some(profile) = &params.device_profile {
+        profile.snap_filters(&mut fixed_filters, &[]);
     }
 
+    progress_callback.on_phase(OptimizationPhase::Loading);
+    let curves = prepare_curves(&params, &args).await?;
+
+    // Fixed filters are corrected for like part of the measurement
+    let fixed_curves = (!fixed_filters.is_empty()).then(|| {
+        println!(
+            "[RUST DEBUG] Applying {} fixed filters",
+            fixed_filters.len()
+        );
+        curves.with_filters(&fixed_filters, args.sample_rate)
+    });
+    let eq_curves = fixed_curves.as_ref().unwrap_or(&curves);
+
+    // Weighting acts on the curves the flat loss sees
+    let shaped_curves = params.loss_weighting.as_ref().map(|weighting| {
+        println!("[RUST DEBUG] Weighting the flat loss by frequency");
+        shape_curves(eq_curves, weighting)
+    });
+    let loss_curves = shaped_curves.as_ref().unwrap_or(eq_curves);
+
+    // Check for cancellation after data loading
+    check_cancelled(&cancellation_token)?;
+
+    // Setup objective data
+    println!("[RUST DEBUG] Setting up objective data...");
+    let (objective_data, use_cea) = autoeq::workflow::setup_objective_data(
+        &args,
+        &loss_curves.input_curve,
+        &loss_curves.target_curve,
+        &loss_curves.deviation_curve,
+        &loss_curves.spin_data,
+    );
+    println!(
+        "[RUST DEBUG] Objective data setup complete, use_cea: {}",
+        use_cea
+    );
+
+    progress_callback.on_phase(OptimizationPhase::TargetBuilt);
+
+    // Get preference score before optimization if applicable
+    let pref_score_before = compute_preference_score(&args, &curves, use_cea, None).await;
+    if let Some(score) = pref_score_before {
+        println!("[RUST DEBUG] Preference score before: {:.2}", score);
+    }
+
+    // Check for cancellation before optimization
+    check_cancelled(&cancellation_token)?;
+
     // Run optimization with progress reporting
     println!(
         "[RUST DEBUG] Starting optimization with algorithm: {}",
         args.algo
     );
 
-    // Determine if algorithm supports callbacks
-    let supports_callbacks = args.algo == "autoeq:de" || args.algo.starts_with("mh:");
-
-    let filter_params = if supports_callbacks {
+    progress_callback.on_phase(OptimizationPhase::Optimizing);
+    let greedy = |space: &SearchSpace| {
+        greedy_peaks(
+            space,
+            &loss_curves.deviation_curve.freq,
+            &loss_curves.deviation_curve.spl,
+            params.peq_model,
+            params.min_freq,
+            params.max_freq,
+            args.sample_rate,
+        )
+    };
+    let boost_limit = params.max_boost_db.map(|max_boost_db| BoostLimit {
+        max_boost_db,
+        sample_rate: args.sample_rate,
+        peq_model: params.peq_model,
+        fixed_filters: fixed_filters.clone(),
+    });
+    if let Some(limit) = &boost_limit {
+        let fixed_peak = peak_boost_db(&fixed_filters, args.sample_rate);
+        if fixed_peak > limit.max_boost_db {
+            return Err(OptimizationError::InvalidParams(format!(
+                "The fixed filters boost {:.2} dB on their own, more than the maximum total boost of {:.2} dB",
+                fixed_peak, limit.max_boost_db
+            )));
+        }
+    }
+    let boost_penalty = BoostPenalty::new(
+        &params,
+        args.sample_rate,
+        &loss_curves.deviation_curve.freq,
+        &fixed_filters,
+    );
+    if boost_penalty.is_some() {
         println!(
-            "[RUST DEBUG] Using algorithm with progress reporting: {}",
-            args.algo
+            "[RUST DEBUG] Penalizing EQ boosts, weight {}",
+            params.boost_penalty
         );
-        let mut progress_count = 0;
-        let cancellation_state_clone = Arc::clone(&cancellation_state);
-        let progress_callback_clone = Arc::clone(&progress_callback);
-
-        if args.algo == "autoeq:de" {
-            // Use DE-specific callback
-            autoeq::workflow::perform_optimization_with_callback(
-                &args,
-                &objective_data,
-                Box::new(move |intermediate| {
-                    // Check for cancellation in callback
-                    if cancellation_state_clone.is_cancelled() {
-                        println!("[RUST DEBUG] Optimization cancelled during iteration {}", intermediate.iter);
-                        return autoeq::de::CallbackAction::Stop;
-                    }
-                    progress_count += 1;
-                    if progress_count % 10 == 0 || progress_count <= 5 {
-                        println!("[RUST DEBUG] Progress update #{}: iter={}, fitness={:.6}, convergence={:.4}",
-                                 progress_count, intermediate.iter, intermediate.fun, intermediate.convergence);
-                    }
-                    let continue_opt = progress_callback_clone.on_progress(ProgressUpdate {
-                        iteration: intermediate.iter,
-                        fitness: intermediate.fun,
-                        params: intermediate.x.to_vec(),
-                        convergence: intermediate.convergence,
-                    });
-                    if !continue_opt {
-                        println!("[RUST DEBUG] Optimization stopped by progress callback");
-                        return autoeq::de::CallbackAction::Stop;
-                    }
-                    if progress_count % 50 == 0 {
-                        println!("[RUST DEBUG] Progress callback invoked successfully (count: {})", progress_count);
-                    }
-                    autoeq::de::CallbackAction::Continue
-                }),
-            )
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> {
-                println!("[RUST DEBUG] DE optimization failed: {}", e);
-                Box::new(std::io::Error::other(e.to_string()))
-            })?
+    }
+    let mut objective = PenalizedObjective::new(&args, &objective_data)
+        .with_boost_limit(boost_limit.clone())
+        .with_boost_penalty(boost_penalty);
+    let polish_space = search_space
+        .clone()
+        .unwrap_or_else(|| SearchSpace::from_args(&args));
+    let optimization = if params.algo == Algorithm::GreedyPeaks {
+        let space = search_space.unwrap_or_else(|| SearchSpace::from_args(&args));
+        greedy(&space).map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })
+    } else {
+        let search_space = if params.greedy_init {
+            let mut space = search_space.unwrap_or_else(|| SearchSpace::from_args(&args));
+            space.initial_x = greedy(&space).map_err(OptimizationError::InvalidParams)?;
+            println!("[RUST DEBUG] Starting from the greedy peak-picking fit");
+            Some(space)
         } else {
-            // Use metaheuristics-specific optimization path
-            println!("[RUST DEBUG] Using metaheuristics algorithm with progress reporting");
-            run_mh_optimization_with_callback(
-                &args,
-                &objective_data,
-                progress_callback,
-                cancellation_state_clone,
-            )?
+            search_space
+        };
+        run_algorithm(
+            params.algo,
+            &args,
+            &objective_data,
+            &objective,
+            search_space,
+            Arc::clone(&progress_callback),
+            cancellation_token.clone(),
+        )
+    };
+    // NLopt already minimised the full objective
+    let optimization = match optimization {
+        Ok(x) if !params.algo.is_nlopt() => polish_with_objective(
+            &args,
+            &objective,
+            polish_space,
+            x,
+            Arc::clone(&progress_callback),
+            cancellation_token.clone(),
+        ),
+        other => other,
+    };
+
+    // Optimizers stop early on cancellation; don't report that as a result.
+    // An exhausted time budget keeps the best filters found so far.
+    let truncated = match cancellation_token.cancel_reason() {
+        Some(CancelReason::DeadlineExceeded) => {
+            println!("[RUST DEBUG] Time budget exceeded, keeping the best result so far");
+            true
         }
-    } else {
-        println!(
-            "[RUST DEBUG] Using algorithm without progress reporting: {}",
-            args.algo
-        );
-        autoeq::workflow::perform_optimization(&args, &objective_data).map_err(
-            |e| -> Box<dyn std::error::Error + Send + Sync> {
-                println!("[RUST DEBUG] Optimization failed: {}", e);
-                Box::new(std::io::Error::other(e.to_string()))
-            },
-        )?
+        Some(reason) => return Err(OptimizationError::Cancelled(reason)),
+        None => false,
     };
+    let mut filter_params = optimization?;
     println!(
         "[RUST DEBUG] Optimization completed, got {} filter parameters",
         filter_params.len()
     );
-
-    // Calculate preference score after optimization
-    let mut pref_score_after: Option<f64> = None;
-    if use_cea {
-        let peq_response = autoeq::x2peq::compute_peq_response_from_x(
-            &input_curve.freq,
-            &filter_params,
-            args.sample_rate,
-            args.peq_model,
+    if let Some(profile) = &params.device_profile {
+        snap_to_device(
+            profile,
+            &mut filter_params,
+            &args,
+            params.peq_model,
+            &fixed_filters,
+            &mut objective,
         );
-        if let Ok(metrics) = autoeq::cea2034::compute_cea2034_metrics(
-            &input_curve.freq,
-            spin_data.as_ref().unwrap(),
-            Some(&peq_response),
-        )
-        .await
-        {
-            pref_score_after = Some(metrics.pref_score);
-        }
-    } else if args.loss == LossType::HeadphoneFlat || args.loss == LossType::HeadphoneScore {
-        // Calculate headphone preference score after applying EQ
-        println!("[RUST DEBUG] Calculating headphone preference score after optimization");
-        let peq_response = autoeq::x2peq::compute_peq_response_from_x(
-            &input_curve.freq,
+        // Snapping can move a filter to another band, refit the gains there
+        let space = gain_refit_space(
+            profile,
             &filter_params,
-            args.sample_rate,
-            args.peq_model,
+            &args,
+            params.peq_model,
+            &bounded_slots,
         );
-        // Create corrected curve by adding PEQ response to input
-        let corrected_curve = autoeq::Curve {
-            freq: input_curve.freq.clone(),
-            spl: &input_curve.spl + &peq_response,
-        };
-        let headphone_data = autoeq::loss::HeadphoneLossData::new(args.smooth, args.smooth_n);
-        let loss_value = autoeq::loss::headphone_loss_with_target(
-            &headphone_data,
-            &corrected_curve,
-            &target_curve,
-        );
-        // Negate the loss value to convert to preference score (higher is better)
-        pref_score_after = Some(-loss_value);
-        println!(
-            "[RUST DEBUG] Headphone preference score after: {:.2}",
-            pref_score_after.unwrap()
+        let mut refit = refine_with_callback(
+            &args,
+            &objective.gains_only(),
+            space,
+            Arc::clone(&progress_callback),
+            cancellation_token.clone(),
+        )?;
+        snap_to_device(
+            profile,
+            &mut refit,
+            &args,
+            params.peq_model,
+            &fixed_filters,
+            &mut objective,
         );
+        if objective.fitness(&refit) < objective.fitness(&filter_params) {
+            filter_params = refit;
+        }
+    }
+    if let Some(limit) = &boost_limit {
+        if let Some(profile) = &params.device_profile {
+            step_down_boosts(
+                profile,
+                &mut filter_params,
+                &args,
+                params.peq_model,
+                &bounded_slots,
+                limit,
+            );
+        }
+        if limit.excess_db(&filter_params) > BOOST_LIMIT_TOLERANCE {
+            return Err(OptimizationError::Failed(format!(
+                "The EQ boosts {:.2} dB, more than the maximum total boost of {:.2} dB",
+                limit.peak_db(&filter_params),
+                limit.max_boost_db
+            )));
+        }
     }
 
+    progress_callback.on_phase(OptimizationPhase::Scoring);
+
+    // Calculate preference score after optimization
+    let peq_response = autoeq::x2peq::compute_peq_response_from_x(
+        &eq_curves.input_curve.freq,
+        &filter_params,
+        args.sample_rate,
+        args.peq_model,
+    );
+    let pref_score_after =
+        compute_preference_score(&args, eq_curves, use_cea, Some(&peq_response)).await;
+    if let Some(score) = pref_score_after {
+        println!("[RUST DEBUG] Preference score after: {:.2}", score);
+    }
+
+    // Optimized slots first, then the fixed filters
+    let mut filters = decode_filters(&filter_params, args.sample_rate, params.peq_model);
+    let mut filter_locks: Vec<FilterLock> = (0..filters.len())
+        .map(|slot| {
+            if bounded_slots.contains(&slot) {
+                FilterLock::Bounded
+            } else {
+                FilterLock::Free
+            }
+        })
+        .collect();
+    filter_locks.extend(fixed_filters.iter().map(|_| FilterLock::Fixed));
+    filters.extend(fixed_filters);
+    let peq_model = params.peq_model;
+    let preamp_db = recommended_preamp_db(&filters, args.sample_rate);
+
+    // Final loss and what the EQ leaves uncorrected
+    let objective_value = objective.fitness(&filter_params);
+    println!("[RUST DEBUG] Final objective value: {:.6}", objective_value);
+    let residual = &eq_curves.deviation_curve.spl - &peq_response;
+    let residual_metrics = residual_metrics(
+        &eq_curves.input_curve.freq,
+        &residual,
+        params.min_freq,
+        params.max_freq,
+    );
+    let measurement_spread = spread_across_measurements(&params, &curves, &residual);
+
+    let run_manifest = RunManifest::new(
+        params,
+        curve_hashes(
+            &curves.input_curve,
+            &curves.target_curve,
+            curves.spin_data.as_ref(),
+        ),
+        filter_params.clone(),
+        time_budget,
+        truncated,
+    );
+
     // Generate plot data
     let plots = generate_optimization_plots(OptimizationPlotParams {
-        filter_params: &filter_params,
-        target_curve: &target_curve,
-        input_curve: &input_curve,
-        deviation_curve: &deviation_curve,
-        spin_data: spin_data.as_ref(),
+        frequencies: &curves.input_curve.freq,
+        filters: &filters,
+        target_curve: &curves.target_curve,
+        input_curve: &curves.input_curve,
+        deviation_curve: &curves.deviation_curve,
+        spin_data: curves.spin_data.as_ref(),
         sample_rate: args.sample_rate,
-        num_filters: args.num_filters,
-        peq_model: args.peq_model,
     });
 
     Ok(OptimizationResult {
         success: true,
+        cancelled: false,
+        truncated,
         error_message: None,
         filter_params: Some(filter_params),
-        objective_value: None, // We could calculate this if needed
+        filters: Some(filters),
+        peq_model: Some(peq_model),
+        filter_locks: Some(filter_locks),
+        preamp_db: Some(preamp_db),
+        objective_value: Some(objective_value),
+        residual_metrics,
+        measurement_spread,
         preference_score_before: pref_score_before,
         preference_score_after: pref_score_after,
         filter_response: Some(plots.filter_response),
@@ -713,5 +1378,6 @@ pub async fn run_optimization_internal<P: ProgressCallback + 'static>(
         filter_plots: Some(plots.filter_plots),
         input_curve: Some(plots.input_curve),
         deviation_curve: Some(plots.deviation_curve),
+        run_manifest: Some(run_manifest),
     })
 }
@@ -0,0 +1,553 @@
+//! NLopt driver with progress reporting and cancellation
+//!
+//! `autoeq::workflow::perform_optimization` runs NLopt algorithms as a black
+//! box. This driver runs them itself around a wrapped objective, so every
+//! evaluation is counted, the best fitness is reported and cancellation is
+//! checked. The objective is autoeq's own `compute_fitness_penalties`, with
+//! the penalty weights autoeq uses for its ceiling, minimum gain and spacing
+//! limits; the limits themselves stay in autoeq. Every algorithm sees them as
+//! penalties, including those autoeq would give inequality constraints.
+//! SotF's own terms, the maximum total boost and the boost penalty, are
+//! added on top.
+//!
+//! NLopt's random generator is global to the process, so runs are serialized
+//! to keep a seeded run reproducible while other jobs optimize.
+
+use crate::choices::{Algorithm, PeqModel};
+use crate::filters::{DecodedFilter, decode_filters, peak_boost_db};
+use crate::loss::BoostPenalty;
+use crate::optim::{
+    CancelReason, CancellationToken, OptimizationError, ProgressCallback, ProgressUpdate,
+    SearchSpace,
+};
+use autoeq::cli::Args as AutoEQArgs;
+use autoeq::optim::ObjectiveData;
+use nlopt::{FailState, Nlopt, Target};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
+
+/// Report progress every this many evaluations
+const REPORT_EVERY: usize = 50;
+
+/// Returned by the objective to make NLopt stop; below `STOP_THRESHOLD`
+const STOP_VALUE: f64 = -f64::MAX;
+const STOP_THRESHOLD: f64 = -1e300;
+
+/// Relative step for finite-difference gradients
+const GRADIENT_STEP: f64 = 1e-6;
+
+/// Penalty weights of autoeq's limits, as autoeq sets them for penalty mode
+const CEILING_PENALTY: f64 = 1e4;
+const MIN_GAIN_PENALTY: f64 = 1e3;
+const SPACING_PENALTY: f64 = 1e3; // Times `spacing_weight`
+
+/// Penalty weight of a boost over the maximum total boost, like the ceiling
+const BOOST_LIMIT_PENALTY: f64 = 1e4;
+
+/// Held while an NLopt run seeds and uses NLopt's global generator
+static NLOPT_RUN: Mutex<()> = Mutex::new(());
+
+/// How often a queued run checks for cancellation
+const QUEUE_POLL: Duration = Duration::from_millis(50);
+
+/// Value of `f` at `x`, with a forward-difference gradient when NLopt asks for one
+fn with_gradient(mut f: impl FnMut(&[f64]) -> f64, x: &[f64], gradient: Option<&mut [f64]>) -> f64 {
+    let value = f(x);
+    if let Some(gradient) = gradient {
+        let mut shifted = x.to_vec();
+        for (i, g) in gradient.iter_mut().enumerate() {
+            let step = GRADIENT_STEP * x[i].abs().max(1.0);
+            shifted[i] = x[i] + step;
+            *g = (f(&shifted) - value) / step;
+            shifted[i] = x[i];
+        }
+    }
+    value
+}
+
+/// Ceiling on the combined boost of the optimized and the fixed filters
+#[derive(Debug, Clone)]
+pub(crate) struct BoostLimit {
+    pub(crate) max_boost_db: f64,
+    pub(crate) sample_rate: f64,
+    pub(crate) peq_model: PeqModel,
+    pub(crate) fixed_filters: Vec<DecodedFilter>,
+}
+
+impl BoostLimit {
+    /// Peak boost of the EQ with the optimized filters `x`
+    pub(crate) fn peak_db(&self, x: &[f64]) -> f64 {
+        let mut filters = decode_filters(x, self.sample_rate, self.peq_model);
+        filters.extend_from_slice(&self.fixed_filters);
+        peak_boost_db(&filters, self.sample_rate)
+    }
+
+    /// Boost over the limit, 0 within it
+    pub(crate) fn excess_db(&self, x: &[f64]) -> f64 {
+        (self.peak_db(x) - self.max_boost_db).max(0.0)
+    }
+}
+
+/// autoeq's loss plus autoeq's penalties for the violated limits
+///
+/// With a [`BoostLimit`], boosting past it is penalized like autoeq's ceiling.
+/// A [`BoostPenalty`] adds its cost of the EQ's boosts.
+#[derive(Clone)]
+pub(crate) struct PenalizedObjective {
+    data: ObjectiveData,
+    boost_limit: Option<BoostLimit>,
+    boost_penalty: Option<BoostPenalty>,
+}
+
+impl PenalizedObjective {
+    pub(crate) fn new(args: &AutoEQArgs, objective_data: &ObjectiveData) -> Self {
+        let mut data = objective_data.clone();
+        data.penalty_w_ceiling = CEILING_PENALTY;
+        data.penalty_w_mingain = MIN_GAIN_PENALTY;
+        data.penalty_w_spacing = SPACING_PENALTY * args.spacing_weight.max(0.0);
+        Self {
+            data,
+            boost_limit: None,
+            boost_penalty: None,
+        }
+    }
+
+    pub(crate) fn with_boost_limit(mut self, boost_limit: Option<BoostLimit>) -> Self {
+        self.boost_limit = boost_limit;
+        self
+    }
+
+    pub(crate) fn with_boost_penalty(mut self, boost_penalty: Option<BoostPenalty>) -> Self {
+        self.boost_penalty = boost_penalty;
+        self
+    }
+
+    /// Whether this objective adds terms autoeq's own algorithms don't see
+    pub(crate) fn extends_autoeq(&self) -> bool {
+        self.boost_limit.is_some() || self.boost_penalty.is_some()
+    }
+
+    /// Objective for refitting gains on fixed frequencies
+    ///
+    /// Spacing and minimum gain only shape where filters go and whether they
+    /// are worth having, neither applies once the device bands are set.
+    pub(crate) fn gains_only(&self) -> Self {
+        let mut data = self.data.clone();
+        data.penalty_w_mingain = 0.0;
+        data.penalty_w_spacing = 0.0;
+        Self {
+            data,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn fitness(&mut self, x: &[f64]) -> f64 {
+        let mut fitness = autoeq::optim::compute_fitness_penalties(x, None, &mut self.data);
+        if let Some(limit) = &self.boost_limit {
+            fitness += BOOST_LIMIT_PENALTY * limit.excess_db(x).powi(2);
+        }
+        if let Some(penalty) = &self.boost_penalty {
+            fitness += penalty.cost(x);
+        }
+        fitness
+    }
+}
+
+/// Wait for other NLopt runs to finish, unless cancelled meanwhile
+fn wait_for_nlopt(
+    cancellation_token: &CancellationToken,
+) -> Result<MutexGuard<'static, ()>, OptimizationError> {
+    loop {
+        match NLOPT_RUN.try_lock() {
+            Ok(guard) => return Ok(guard),
+            // The generator holds no invariant a panicking run could break
+            Err(TryLockError::Poisoned(poisoned)) => return Ok(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => {
+                if let Some(reason) = cancellation_token.cancel_reason() {
+                    return Err(OptimizationError::Cancelled(reason));
+                }
+                std::thread::sleep(QUEUE_POLL);
+            }
+        }
+    }
+}
+
+/// Why the tracked objective asked NLopt to stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopReason {
+    Cancelled(CancelReason),
+    Callback,
+}
+
+/// Objective wrapper counting evaluations and tracking the best point
+struct TrackedObjective<P: ProgressCallback> {
+    objective: PenalizedObjective,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+    evaluations: usize,
+    last_report: usize,
+    best_fitness: f64,
+    best_x: Vec<f64>,
+    stop_reason: Option<StopReason>,
+}
+
+impl<P: ProgressCallback> TrackedObjective<P> {
+    fn fitness(&mut self, x: &[f64]) -> f64 {
+        let fitness = self.objective.fitness(x);
+        self.evaluations += 1;
+        if fitness < self.best_fitness {
+            self.best_fitness = fitness;
+            self.best_x = x.to_vec();
+        }
+        fitness
+    }
+
+    fn evaluate(&mut self, x: &[f64], gradient: Option<&mut [f64]>) -> f64 {
+        if self.stop_reason.is_some() {
+            return STOP_VALUE;
+        }
+        if let Some(reason) = self.cancellation_token.cancel_reason() {
+            println!(
+                "[RUST DEBUG] NLopt optimization cancelled after {} evaluations",
+                self.evaluations
+            );
+            self.stop_reason = Some(StopReason::Cancelled(reason));
+            return STOP_VALUE;
+        }
+
+        // Gradient-based algorithms get a forward-difference estimate
+        let fitness = with_gradient(|x| self.fitness(x), x, gradient);
+
+        if self.evaluations - self.last_report >= REPORT_EVERY {
+            self.last_report = self.evaluations;
+            let continue_optimization = self.progress_callback.on_progress(ProgressUpdate {
+                iteration: self.evaluations,
+                fitness: self.best_fitness,
+                params: self.best_x.clone(),
+                convergence: 0.0, // NLopt doesn't expose convergence
+                evaluations: Some(self.evaluations),
+            });
+            if !continue_optimization {
+                println!("[RUST DEBUG] NLopt optimization stopped by progress callback");
+                self.stop_reason = Some(StopReason::Callback);
+            }
+        }
+
+        fitness
+    }
+}
+
+fn nlopt_algorithm(algorithm: Algorithm) -> Option<nlopt::Algorithm> {
+    Some(match algorithm {
+        Algorithm::NloptIsres => nlopt::Algorithm::Isres,
+        Algorithm::NloptAgs => nlopt::Algorithm::Ags,
+        Algorithm::NloptOrigDirect => nlopt::Algorithm::OrigDirect,
+        Algorithm::NloptCrs2Lm => nlopt::Algorithm::Crs2Lm,
+        Algorithm::NloptDirect => nlopt::Algorithm::Direct,
+        Algorithm::NloptDirectL => nlopt::Algorithm::DirectL,
+        Algorithm::NloptGmlsl => nlopt::Algorithm::GMlsl,
+        Algorithm::NloptGmlslLds => nlopt::Algorithm::GMlslLds,
+        Algorithm::NloptStogo => nlopt::Algorithm::StoGo,
+        Algorithm::NloptStogoRand => nlopt::Algorithm::StoGoRand,
+        Algorithm::NloptCobyla => nlopt::Algorithm::Cobyla,
+        Algorithm::NloptBobyqa => nlopt::Algorithm::Bobyqa,
+        Algorithm::NloptNelderMead => nlopt::Algorithm::Neldermead,
+        Algorithm::NloptSbplx => nlopt::Algorithm::Sbplx,
+        Algorithm::NloptSlsqp => nlopt::Algorithm::Slsqp,
+        _ => return None,
+    })
+}
+
+fn local_nlopt_algorithm(name: &str) -> Result<nlopt::Algorithm, String> {
+    match name {
+        "cobyla" => Ok(nlopt::Algorithm::Cobyla),
+        "bobyqa" => Ok(nlopt::Algorithm::Bobyqa),
+        "newuoa" => Ok(nlopt::Algorithm::NewuoaBound),
+        "neldermead" => Ok(nlopt::Algorithm::Neldermead),
+        "sbplx" => Ok(nlopt::Algorithm::Sbplx),
+        _ => Err(format!(
+            "Unknown local algorithm '{}' (expected cobyla, bobyqa, newuoa, neldermead or sbplx)",
+            name
+        )),
+    }
+}
+
+type SharedObjective<P> = Arc<Mutex<TrackedObjective<P>>>;
+
+/// Run one NLopt pass from `x`, sharing the tracker with other passes
+fn run_nlopt_pass<P: ProgressCallback + 'static>(
+    algorithm: nlopt::Algorithm,
+    args: &AutoEQArgs,
+    lower_bounds: &[f64],
+    upper_bounds: &[f64],
+    x: &mut [f64],
+    tracker: SharedObjective<P>,
+) -> Result<(), String> {
+    let mut optimizer = Nlopt::new(
+        algorithm,
+        x.len(),
+        |x: &[f64], gradient: Option<&mut [f64]>, tracker: &mut SharedObjective<P>| {
+            tracker
+                .lock()
+                .map(|mut t| t.evaluate(x, gradient))
+                .unwrap_or(STOP_VALUE)
+        },
+        Target::Minimize,
+        tracker,
+    );
+
+    let setup_error = |e: FailState| format!("Failed to configure NLopt: {:?}", e);
+    optimizer
+        .set_lower_bounds(lower_bounds)
+        .map_err(setup_error)?;
+    optimizer
+        .set_upper_bounds(upper_bounds)
+        .map_err(setup_error)?;
+    optimizer
+        .set_maxeval(args.maxeval as u32)
+        .map_err(setup_error)?;
+    optimizer
+        .set_ftol_rel(args.tolerance)
+        .map_err(setup_error)?;
+    optimizer
+        .set_ftol_abs(args.atolerance)
+        .map_err(setup_error)?;
+    optimizer.set_stopval(STOP_THRESHOLD).map_err(setup_error)?;
+    optimizer
+        .set_population(args.population as u32)
+        .map_err(setup_error)?;
+
+    // Multi-level single-linkage needs a local optimizer for its searches
+    if matches!(
+        algorithm,
+        nlopt::Algorithm::GMlsl | nlopt::Algorithm::GMlslLds
+    ) {
+        let local = Nlopt::new(
+            nlopt::Algorithm::Cobyla,
+            x.len(),
+            |_: &[f64], _: Option<&mut [f64]>, _: &mut ()| 0.0,
+            Target::Minimize,
+            (),
+        );
+        optimizer.set_local_optimizer(local).map_err(setup_error)?;
+    }
+
+    match optimizer.optimize(x) {
+        Ok((state, value)) => {
+            println!(
+                "[RUST DEBUG] NLopt finished: {:?}, value={:.6}",
+                state, value
+            );
+            Ok(())
+        }
+        // Round-off limits still leave a usable best point
+        Err((FailState::RoundoffLimited, value)) => {
+            println!("[RUST DEBUG] NLopt round-off limited, value={:.6}", value);
+            Ok(())
+        }
+        Err((state, _value)) => Err(format!("NLopt optimization failed: {:?}", state)),
+    }
+}
+
+/// Run an NLopt algorithm with progress reporting and cancellation
+///
+/// Progress is reported every few evaluations with the best fitness so far.
+/// When `args.refine` is set, a local pass with `args.local_algo` follows.
+/// Without a `search_space`, autoeq's bounds and initial guess are used.
+pub(crate) fn run_nlopt_optimization_with_callback<P: ProgressCallback + 'static>(
+    args: &AutoEQArgs,
+    objective: &PenalizedObjective,
+    algorithm: Algorithm,
+    search_space: Option<SearchSpace>,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    let global_algorithm = nlopt_algorithm(algorithm)
+        .ok_or_else(|| format!("Not an NLopt algorithm: {}", algorithm))?;
+    let space = search_space.unwrap_or_else(|| SearchSpace::from_args(args));
+    run_nlopt_passes(
+        args,
+        objective,
+        Some(global_algorithm),
+        space,
+        progress_callback,
+        cancellation_token,
+    )
+}
+
+/// Refine `space.initial_x` with `args.local_algo`, as autoeq does after a global search
+pub(crate) fn refine_with_callback<P: ProgressCallback + 'static>(
+    args: &AutoEQArgs,
+    objective: &PenalizedObjective,
+    space: SearchSpace,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    run_nlopt_passes(
+        args,
+        objective,
+        None,
+        space,
+        progress_callback,
+        cancellation_token,
+    )
+}
+
+/// Run the global pass if any, then the local one when refining
+///
+/// A finished run returns where NLopt ended, like autoeq's driver; a run
+/// stopped by the progress callback returns the best feasible point so far.
+fn run_nlopt_passes<P: ProgressCallback + 'static>(
+    args: &AutoEQArgs,
+    objective: &PenalizedObjective,
+    global_algorithm: Option<nlopt::Algorithm>,
+    space: SearchSpace,
+    progress_callback: Arc<P>,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    // Seed NLopt's generator for the stochastic algorithms, no other run may reseed it meanwhile
+    let _nlopt_run = wait_for_nlopt(&cancellation_token)?;
+    Nlopt::<fn(&[f64], Option<&mut [f64]>, &mut ()) -> f64, ()>::srand_seed(args.seed);
+
+    let SearchSpace {
+        lower_bounds,
+        upper_bounds,
+        initial_x: mut x,
+        ..
+    } = space;
+
+    let tracker = Arc::new(Mutex::new(TrackedObjective {
+        objective: objective.clone(),
+        progress_callback,
+        cancellation_token,
+        evaluations: 0,
+        last_report: 0,
+        best_fitness: f64::INFINITY,
+        best_x: x.clone(),
+        stop_reason: None,
+    }));
+    let stopped = |tracker: &SharedObjective<P>| {
+        tracker
+            .lock()
+            .map(|t| t.stop_reason.is_some())
+            .unwrap_or(true)
+    };
+
+    if let Some(algorithm) = global_algorithm {
+        run_nlopt_pass(
+            algorithm,
+            args,
+            &lower_bounds,
+            &upper_bounds,
+            &mut x,
+            Arc::clone(&tracker),
+        )?;
+    }
+
+    if (global_algorithm.is_none() || args.refine) && !stopped(&tracker) {
+        println!(
+            "[RUST DEBUG] Refining with local algorithm: {}",
+            args.local_algo
+        );
+        run_nlopt_pass(
+            local_nlopt_algorithm(&args.local_algo)?,
+            args,
+            &lower_bounds,
+            &upper_bounds,
+            &mut x,
+            Arc::clone(&tracker),
+        )?;
+    }
+
+    let tracker = tracker
+        .lock()
+        .map_err(|_| "NLopt objective state poisoned".to_string())?;
+    println!(
+        "[RUST DEBUG] NLopt used {} evaluations, best fitness {:.6}",
+        tracker.evaluations, tracker.best_fitness
+    );
+    match tracker.stop_reason {
+        // Like DE, stopping from the callback or at the deadline keeps the best result so far
+        Some(StopReason::Callback | StopReason::Cancelled(CancelReason::DeadlineExceeded)) => {
+            Ok(tracker.best_x.clone())
+        }
+        Some(StopReason::Cancelled(reason)) => Err(Box::new(OptimizationError::Cancelled(reason))),
+        None => Ok(x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optim::{OptimizationParams, build_autoeq_args, prepare_curves};
+    use crate::test_mocks::mocks::create_captured_curve_params;
+
+    struct NoopProgressCallback;
+
+    impl ProgressCallback for NoopProgressCallback {
+        fn on_progress(&self, _update: ProgressUpdate) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_boost_limit_excess() {
+        let limit = BoostLimit {
+            max_boost_db: 3.0,
+            sample_rate: 48000.0,
+            peq_model: PeqModel::Pk,
+            fixed_filters: Vec::new(),
+        };
+        // One peak at 1 kHz
+        assert!((limit.excess_db(&[3.0, 1.0, 6.0]) - 3.0).abs() < 0.05);
+        assert_eq!(limit.excess_db(&[3.0, 1.0, 2.0]), 0.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_matches_stock_autoeq() {
+        let params = OptimizationParams {
+            algo: Algorithm::NloptCobyla,
+            maxeval: 2000,
+            seed: Some(7),
+            ..create_captured_curve_params()
+        };
+        let args = build_autoeq_args(&params);
+        let curves = prepare_curves(&params, &args).await.unwrap();
+        let (objective_data, _) = autoeq::workflow::setup_objective_data(
+            &args,
+            &curves.input_curve,
+            &curves.target_curve,
+            &curves.deviation_curve,
+            &curves.spin_data,
+        );
+
+        let stock = autoeq::workflow::perform_optimization(&args, &objective_data).unwrap();
+        let ours = run_nlopt_optimization_with_callback(
+            &args,
+            &PenalizedObjective::new(&args, &objective_data),
+            params.algo,
+            None,
+            Arc::new(NoopProgressCallback),
+            CancellationToken::new(),
+        )
+        .unwrap();
+
+        let loss = |x: &[f64]| {
+            autoeq::optim::compute_fitness_penalties(x, None, &mut objective_data.clone())
+        };
+        let (ours_loss, stock_loss) = (loss(&ours), loss(&stock));
+        assert!(
+            ours_loss <= stock_loss + 0.05 * stock_loss.abs() + 1e-3,
+            "loss {} is worse than autoeq's {}",
+            ours_loss,
+            stock_loss
+        );
+        // Within autoeq's limits, so its penalties add nothing
+        let penalized = PenalizedObjective::new(&args, &objective_data).fitness(&ours);
+        assert!(
+            penalized - ours_loss <= 1e-3,
+            "limits violated, penalty {}",
+            penalized - ours_loss
+        );
+    }
+}
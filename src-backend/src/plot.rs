@@ -1,16 +1,33 @@
+use crate::choices::PeqModel;
+use crate::filters::{DecodedFilter, filters_response};
 use autoeq::Curve;
 use ndarray::Array1;
 use plotly::Plot;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlotData {
     pub frequencies: Vec<f64>,
-    pub curves: HashMap<String, Vec<f64>>,
+    #[serde(deserialize_with = "deserialize_curves")]
+    pub curves: HashMap<String, Vec<f64>>, // NaN where a value is missing, `null` in JSON
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Curves with `null` read back as NaN, the way serde_json writes NaN
+fn deserialize_curves<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Vec<f64>>, D::Error> {
+    let curves = HashMap::<String, Vec<Option<f64>>>::deserialize(deserializer)?;
+    Ok(curves
+        .into_iter()
+        .map(|(name, values)| {
+            let values = values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect();
+            (name, values)
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PlotFiltersParams {
     pub input_curve: CurveData,
@@ -19,7 +36,8 @@ pub struct PlotFiltersParams {
     pub optimized_params: Vec<f64>,
     pub sample_rate: f64,
     pub num_filters: usize,
-    pub peq_model: Option<String>, // "pk", "hp-pk", etc.
+    #[serde(default)]
+    pub peq_model: PeqModel,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -43,14 +61,13 @@ pub struct OptimizationPlots {
 }
 
 pub struct OptimizationPlotParams<'a> {
-    pub filter_params: &'a [f64],
+    pub frequencies: &'a Array1<f64>, // Analysis grid, see `FrequencyGrid`
+    pub filters: &'a [DecodedFilter],
     pub target_curve: &'a Curve,
     pub input_curve: &'a Curve,
     pub deviation_curve: &'a Curve,
     pub spin_data: Option<&'a HashMap<String, Curve>>,
     pub sample_rate: f64,
-    pub num_filters: usize,
-    pub peq_model: autoeq::cli::PeqModel,
 }
 
 // Helper function to convert CurveData to autoeq::Curve
@@ -71,19 +88,12 @@ pub fn plot_to_json(plot: Plot) -> Result<serde_json::Value, String> {
 
 /// Generate all plot data for optimization results
 pub fn generate_optimization_plots(params: OptimizationPlotParams) -> OptimizationPlots {
-    // Generate plot frequencies
-    let plot_freqs: Vec<f64> = (0..200)
-        .map(|i| 20.0 * (1.0355_f64.powf(i as f64)))
-        .collect();
-    let plot_freqs_array = Array1::from(plot_freqs.clone());
-
-    // Generate filter response data
-    let eq_response = autoeq::x2peq::compute_peq_response_from_x(
-        &plot_freqs_array,
-        params.filter_params,
-        params.sample_rate,
-        params.peq_model,
-    );
+    // Plot on the grid the optimization used
+    let plot_freqs_array = params.frequencies.clone();
+    let plot_freqs = plot_freqs_array.to_vec();
+
+    // Generate filter response data, including filters pinned by the user
+    let eq_response = filters_response(params.filters, &plot_freqs_array, params.sample_rate);
 
     let mut filter_curves = HashMap::new();
     filter_curves.insert("EQ Response".to_string(), eq_response.to_vec());
@@ -105,36 +115,26 @@ pub fn generate_optimization_plots(params: OptimizationPlotParams) -> Optimizati
     let mut combined_response = Array1::zeros(plot_freqs_array.len());
 
     // Sort filters by frequency for consistent display
-    let mut filters: Vec<(usize, f64, f64, f64)> = (0..params.num_filters)
-        .map(|i| {
-            (
-                i,
-                10f64.powf(params.filter_params[i * 3]), // Convert from log to linear frequency
-                params.filter_params[i * 3 + 1],         // Q
-                params.filter_params[i * 3 + 2],         // Gain
-            )
-        })
-        .collect();
-    filters.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    let mut filters: Vec<(usize, &DecodedFilter)> = params.filters.iter().enumerate().collect();
+    filters.sort_by(|a, b| {
+        a.1.frequency
+            .partial_cmp(&b.1.frequency)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
 
     // Generate response for each filter
-    for (orig_i, f0, q, gain) in filters.into_iter() {
-        use autoeq::iir::{Biquad, BiquadFilterType};
-
-        let (ftype, label_prefix) = match params.peq_model {
-            autoeq::cli::PeqModel::HpPk if orig_i == 0 => (BiquadFilterType::Highpass, "HPQ"),
-            autoeq::cli::PeqModel::HpPkLp if orig_i == 0 => (BiquadFilterType::Highpass, "HPQ"),
-            autoeq::cli::PeqModel::HpPkLp if orig_i == params.num_filters - 1 => {
-                (BiquadFilterType::Lowpass, "LP")
-            }
-            _ => (BiquadFilterType::Peak, "PK"),
-        };
-
-        let filter = Biquad::new(ftype, f0, params.sample_rate, q, gain);
-        let filter_response = filter.np_log_result(&plot_freqs_array);
+    for (orig_i, filter) in filters.into_iter() {
+        let filter_response = filter
+            .biquad(params.sample_rate)
+            .np_log_result(&plot_freqs_array);
         combined_response = &combined_response + &filter_response;
 
-        let label = format!("{} {} at {:.0}Hz", label_prefix, orig_i + 1, f0);
+        let label = format!(
+            "{} {} at {:.0}Hz",
+            filter.filter_type.code(),
+            orig_i + 1,
+            filter.frequency
+        );
 
         individual_filter_curves.insert(label, filter_response.to_vec());
     }
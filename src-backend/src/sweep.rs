@@ -0,0 +1,356 @@
+//! Filter-count and PEQ-model sweep
+//!
+//! Runs the same optimization for a range of filter counts and PEQ models and
+//! recommends the smallest configuration whose loss is within a tolerance of
+//! the best one.
+
+use crate::choices::PeqModel;
+use crate::optim::{
+    CancellationToken, JobManager, OptimizationParams, ProgressCallback, ProgressUpdate,
+};
+use crate::plot::PlotData;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Range of configurations to try
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepOptions {
+    pub min_filters: usize,
+    pub max_filters: usize,
+    pub peq_models: Vec<PeqModel>, // Tried in this order for each filter count
+    pub tolerance: f64,            // Relative loss tolerance, 0.05 = within 5% of the best
+}
+
+impl Default for SweepOptions {
+    fn default() -> Self {
+        Self {
+            min_filters: 3,
+            max_filters: 10,
+            peq_models: vec![PeqModel::Pk, PeqModel::HpPk],
+            tolerance: 0.05,
+        }
+    }
+}
+
+impl SweepOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_filters == 0 || self.min_filters > self.max_filters || self.max_filters > 50 {
+            return Err(format!(
+                "Filter count range must be within 1-50 (got: {}-{})",
+                self.min_filters, self.max_filters
+            ));
+        }
+        if self.peq_models.is_empty() {
+            return Err("At least one PEQ model is required".to_string());
+        }
+        if self.tolerance.is_nan() || self.tolerance < 0.0 {
+            return Err(format!("Tolerance must be >= 0 (got: {})", self.tolerance));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one configuration of the sweep
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepPoint {
+    pub num_filters: usize,
+    pub peq_model: PeqModel,
+    pub objective_value: Option<f64>, // Final loss, lower is better
+    pub preference_score: Option<f64>,
+    pub residual_rms_db: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Score-versus-complexity table of a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepResult {
+    pub points: Vec<SweepPoint>,    // By filter count, then PEQ model
+    pub best: Option<usize>,        // Index of the lowest loss
+    pub recommended: Option<usize>, // Index of the smallest configuration within tolerance
+    pub tolerance: f64,
+    pub plot: PlotData, // Loss per filter count, one curve per PEQ model
+}
+
+impl SweepResult {
+    pub fn recommended_point(&self) -> Option<&SweepPoint> {
+        self.recommended.map(|i| &self.points[i])
+    }
+
+    /// Human readable table, the recommended configuration is marked with `*`
+    pub fn to_table(&self) -> String {
+        let mut table = String::new();
+        writeln!(
+            table,
+            "   {:>7}  {:<12}  {:>10}  {:>8}  {:>8}",
+            "Filters", "PEQ model", "Loss", "Score", "RMS (dB)"
+        )
+        .unwrap();
+        for (i, point) in self.points.iter().enumerate() {
+            let mark = if Some(i) == self.recommended {
+                "*"
+            } else {
+                " "
+            };
+            let values = match &point.error {
+                None => format!(
+                    "{:>10}  {:>8}  {:>8}",
+                    format_value(point.objective_value, 6),
+                    format_value(point.preference_score, 2),
+                    format_value(point.residual_rms_db, 2)
+                ),
+                Some(error) => format!("failed: {}", error),
+            };
+            writeln!(
+                table,
+                "{}  {:>7}  {:<12}  {}",
+                mark, point.num_filters, point.peq_model, values
+            )
+            .unwrap();
+        }
+        table
+    }
+}
+
+fn format_value(value: Option<f64>, precision: usize) -> String {
+    value
+        .map(|v| format!("{:.precision$}", v))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Index of the lowest loss and of the first point within `tolerance` of it
+///
+/// Points are expected in order of increasing complexity.
+pub fn recommend(points: &[SweepPoint], tolerance: f64) -> (Option<usize>, Option<usize>) {
+    let losses = || {
+        points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| p.objective_value.map(|loss| (i, loss)))
+    };
+    let Some((best, best_loss)) = losses().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+        return (None, None);
+    };
+    let threshold = best_loss + tolerance * best_loss.abs();
+    let recommended = losses()
+        .find(|(_, loss)| *loss <= threshold)
+        .map(|(i, _)| i);
+    (Some(best), recommended)
+}
+
+/// Loss against filter count, one curve per PEQ model
+///
+/// Failed configurations are NaN, `null` in JSON, so every curve matches the
+/// x axis.
+pub fn sweep_plot_data(points: &[SweepPoint], recommended: Option<usize>) -> PlotData {
+    let mut counts: Vec<usize> = points.iter().map(|p| p.num_filters).collect();
+    counts.sort_unstable();
+    counts.dedup();
+
+    let mut curves: HashMap<String, Vec<f64>> = HashMap::new();
+    for point in points {
+        let curve = curves
+            .entry(point.peq_model.to_string())
+            .or_insert_with(|| vec![f64::NAN; counts.len()]);
+        if let Ok(i) = counts.binary_search(&point.num_filters) {
+            curve[i] = point.objective_value.unwrap_or(f64::NAN);
+        }
+    }
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "x_label".to_string(),
+        serde_json::json!("Number of filters"),
+    );
+    metadata.insert("y_label".to_string(), serde_json::json!("Loss"));
+    if let Some(point) = recommended.map(|i| &points[i]) {
+        metadata.insert(
+            "recommended".to_string(),
+            serde_json::json!({
+                "num_filters": point.num_filters,
+                "peq_model": point.peq_model,
+            }),
+        );
+    }
+
+    PlotData {
+        frequencies: counts.iter().map(|&n| n as f64).collect(),
+        curves,
+        metadata,
+    }
+}
+
+/// Progress callback for sweep runs: only forwards cancellation
+struct SweepProgressCallback {
+    cancellation_token: CancellationToken,
+}
+
+impl ProgressCallback for SweepProgressCallback {
+    fn on_progress(&self, _update: ProgressUpdate) -> bool {
+        !self.cancellation_token.is_cancelled()
+    }
+}
+
+/// Optimize every configuration of `options` with the `template` parameters
+///
+/// Each configuration runs as a job of `job_manager`, so it shares the
+/// manager's concurrency limit and [`JobManager::cancel_all`] stops the sweep.
+/// All runs share one seed so their losses compare fairly. A failing
+/// configuration is recorded in the table and does not stop the sweep;
+/// cancelling `cancellation_token` or one of the jobs does.
+pub async fn run_sweep(
+    job_manager: &JobManager,
+    template: &OptimizationParams,
+    options: &SweepOptions,
+    cancellation_token: CancellationToken,
+) -> Result<SweepResult, String> {
+    options.validate()?;
+    let mut template = template.clone();
+    let seed = template.resolve_seed();
+    println!(
+        "[RUST DEBUG] Sweeping {}-{} filters over {} PEQ models with seed {}",
+        options.min_filters,
+        options.max_filters,
+        options.peq_models.len(),
+        seed
+    );
+
+    let mut points = Vec::new();
+    for num_filters in options.min_filters..=options.max_filters {
+        for &peq_model in &options.peq_models {
+            if cancellation_token.is_cancelled() {
+                return Err("Sweep cancelled".to_string());
+            }
+            let params = OptimizationParams {
+                num_filters,
+                peq_model,
+                ..template.clone()
+            };
+            let token = cancellation_token.child();
+            let progress_callback = Arc::new(SweepProgressCallback {
+                cancellation_token: token.clone(),
+            });
+
+            let job_id = job_manager.submit_with_token(params, progress_callback, token);
+            let result = job_manager.wait(job_id).await;
+            job_manager.remove(job_id);
+            let result = result.ok_or_else(|| format!("Sweep job {} was lost", job_id))?;
+            if result.cancelled {
+                return Err("Sweep cancelled".to_string());
+            }
+
+            let point = match result.error_message {
+                None => SweepPoint {
+                    num_filters,
+                    peq_model,
+                    objective_value: result.objective_value,
+                    preference_score: result.preference_score_after,
+                    residual_rms_db: result.residual_metrics.map(|m| m.overall.rms_db),
+                    error: None,
+                },
+                Some(error) => SweepPoint {
+                    num_filters,
+                    peq_model,
+                    objective_value: None,
+                    preference_score: None,
+                    residual_rms_db: None,
+                    error: Some(error),
+                },
+            };
+            println!(
+                "[RUST DEBUG] Sweep {} filters, {}: loss {}",
+                num_filters,
+                peq_model,
+                format_value(point.objective_value, 6)
+            );
+            points.push(point);
+        }
+    }
+
+    let (best, recommended) = recommend(&points, options.tolerance);
+    let plot = sweep_plot_data(&points, recommended);
+    Ok(SweepResult {
+        points,
+        best,
+        recommended,
+        tolerance: options.tolerance,
+        plot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(num_filters: usize, peq_model: PeqModel, loss: Option<f64>) -> SweepPoint {
+        SweepPoint {
+            num_filters,
+            peq_model,
+            objective_value: loss,
+            preference_score: None,
+            residual_rms_db: None,
+            error: loss.is_none().then(|| "failed".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_recommend_smallest_within_tolerance() {
+        let points = vec![
+            point(3, PeqModel::Pk, Some(2.0)),
+            point(3, PeqModel::HpPk, None),
+            point(5, PeqModel::Pk, Some(1.04)),
+            point(5, PeqModel::HpPk, Some(1.1)),
+            point(7, PeqModel::Pk, Some(1.0)),
+        ];
+        let (best, recommended) = recommend(&points, 0.05);
+        assert_eq!(best, Some(4));
+        assert_eq!(recommended, Some(2));
+
+        let (_, strict) = recommend(&points, 0.0);
+        assert_eq!(strict, Some(4));
+
+        assert_eq!(recommend(&points[1..2], 0.05), (None, None));
+    }
+
+    #[test]
+    fn test_sweep_plot_data() {
+        let points = vec![
+            point(3, PeqModel::Pk, Some(2.0)),
+            point(3, PeqModel::HpPk, None),
+            point(4, PeqModel::Pk, Some(1.5)),
+            point(4, PeqModel::HpPk, Some(1.4)),
+        ];
+        let plot = sweep_plot_data(&points, Some(3));
+        assert_eq!(plot.frequencies, vec![3.0, 4.0]);
+        assert_eq!(plot.curves["pk"], vec![2.0, 1.5]);
+        assert!(plot.curves["hp-pk"][0].is_nan());
+        assert_eq!(plot.curves["hp-pk"][1], 1.4);
+        assert_eq!(plot.metadata["recommended"]["num_filters"], 4);
+        assert_eq!(plot.metadata["recommended"]["peq_model"], "hp-pk");
+
+        // Failed configurations are null in JSON and read back as NaN
+        let json = serde_json::to_string(&plot).unwrap();
+        assert!(json.contains("[null,1.4]"), "{}", json);
+        let decoded: PlotData = serde_json::from_str(&json).unwrap();
+        assert!(decoded.curves["hp-pk"][0].is_nan());
+        assert_eq!(decoded.curves["hp-pk"][1], 1.4);
+        assert_eq!(decoded.curves["pk"], plot.curves["pk"]);
+    }
+
+    #[test]
+    fn test_validate_options() {
+        assert!(SweepOptions::default().validate().is_ok());
+        let inverted = SweepOptions {
+            min_filters: 8,
+            max_filters: 4,
+            ..SweepOptions::default()
+        };
+        assert!(inverted.validate().is_err());
+        let no_models = SweepOptions {
+            peq_models: vec![],
+            ..SweepOptions::default()
+        };
+        assert!(no_models.validate().is_err());
+    }
+}
@@ -1,7 +1,10 @@
 #[cfg(test)]
 #[allow(dead_code)]
 pub mod mocks {
-    use crate::OptimizationParams;
+    use crate::{
+        Algorithm, CapturedMeasurement, CurveAggregate, FrequencyGrid, Loss, OptimizationParams,
+        PeqModel,
+    };
     use std::collections::HashMap;
 
     // Mock HTTP client for testing API calls
@@ -82,7 +85,7 @@ pub mod mocks {
             version: Some("v1.0".to_string()),
             measurement: Some("On Axis".to_string()),
             curve_name: "Listening Window".to_string(),
-            algo: "nlopt:cobyla".to_string(),
+            algo: Algorithm::NloptCobyla,
             population: 10,
             maxeval: 50,
             refine: false,
@@ -91,8 +94,8 @@ pub mod mocks {
             spacing_weight: 1.0,
             smooth: false,
             smooth_n: 1,
-            loss: "speaker-flat".to_string(),
-            peq_model: Some("pk".to_string()),
+            loss: Loss::SpeakerFlat,
+            peq_model: PeqModel::Pk,
             strategy: None,
             de_f: None,
             de_cr: None,
@@ -104,6 +107,40 @@ pub mod mocks {
             captured_magnitudes: None,
             target_frequencies: None,
             target_magnitudes: None,
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            frequency_grid: FrequencyGrid::default(),
+        }
+    }
+
+    // Helper to create a runnable optimization: a bump and a dip against a flat target
+    pub fn create_captured_curve_params() -> OptimizationParams {
+        let freqs: Vec<f64> = (0..200)
+            .map(|i| 20.0 * 1000f64.powf(i as f64 / 199.0))
+            .collect();
+        let bump =
+            |f: f64, center: f64, gain: f64| gain * (-(f / center).log2().powi(2) / 0.08).exp();
+        OptimizationParams {
+            captured_magnitudes: Some(
+                freqs
+                    .iter()
+                    .map(|&f| bump(f, 300.0, 4.0) - bump(f, 3000.0, 5.0))
+                    .collect(),
+            ),
+            target_magnitudes: Some(vec![0.0; freqs.len()]),
+            captured_frequencies: Some(freqs.clone()),
+            target_frequencies: Some(freqs),
+            ..create_minimal_optimization_params()
         }
     }
 
@@ -289,7 +326,7 @@ pub mod mocks {
                     max_q: 25.0,
                     ..base.clone()
                 },
-                "Maximum Q must be <= 100",
+                "Maximum Q must be <= 20",
             ),
             (
                 "invalid_db_range".to_string(),
@@ -420,6 +457,63 @@ pub mod mocks {
                 },
                 "Absolute tolerance must be >= 1e-15",
             ),
+            (
+                "max_boost_negative".to_string(),
+                OptimizationParams {
+                    max_boost_db: Some(-1.0),
+                    ..base.clone()
+                },
+                "Maximum total boost must be between 0 and 20 dB",
+            ),
+            (
+                "device_too_many_filters".to_string(),
+                OptimizationParams {
+                    num_filters: 12,
+                    device_profile: Some("graphic-10".parse().unwrap()),
+                    ..base.clone()
+                },
+                "has 10 bands but 12 filters are requested",
+            ),
+            (
+                "greedy_free_model".to_string(),
+                OptimizationParams {
+                    algo: Algorithm::GreedyPeaks,
+                    peq_model: PeqModel::Free,
+                    ..base.clone()
+                },
+                "Greedy peak picking needs a PEQ model with fixed filter types",
+            ),
+            (
+                "boost_penalty_score_loss".to_string(),
+                OptimizationParams {
+                    loss: Loss::SpeakerScore,
+                    boost_penalty: 1.0,
+                    ..base.clone()
+                },
+                "need a flat loss",
+            ),
+            (
+                "measurement_zero_weight".to_string(),
+                OptimizationParams {
+                    captured_measurements: vec![CapturedMeasurement {
+                        weight: 0.0,
+                        ..CapturedMeasurement::new(vec![100.0, 1000.0], vec![0.0, 1.0])
+                    }],
+                    ..base.clone()
+                },
+                "Measurement weight must be greater than 0",
+            ),
+            (
+                "grid_misses_range".to_string(),
+                OptimizationParams {
+                    frequency_grid: FrequencyGrid {
+                        min_freq: 200.0,
+                        ..FrequencyGrid::default()
+                    },
+                    ..base.clone()
+                },
+                "must cover the optimization range",
+            ),
         ]
     }
 }
@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod tests {
     use crate::{
-        CurveData, OptimizationParams, OptimizationResult, PlotData, ProgressUpdate,
-        curve_data_to_curve, validate_params,
+        Algorithm, CurveAggregate, CurveData, DecodedFilter, FilterRange, FilterType,
+        FrequencyGrid, Loss, OptimizationParams, OptimizationResult, PeqModel, PinnedFilter,
+        PlotData, ProgressUpdate, curve_data_to_curve, optimization_choices, validate_params,
     };
     use std::collections::HashMap;
 
@@ -33,7 +34,7 @@ mod tests {
             version: Some("v1.0".to_string()),
             measurement: Some("On Axis".to_string()),
             curve_name: "Listening Window".to_string(),
-            algo: "nlopt:cobyla".to_string(),
+            algo: Algorithm::NloptCobyla,
             population: 50,
             maxeval: 100,
             refine: false,
@@ -42,8 +43,8 @@ mod tests {
             spacing_weight: 20.0,
             smooth: true,
             smooth_n: 2,
-            loss: "speaker-flat".to_string(),
-            peq_model: Some("pk".to_string()),
+            loss: Loss::SpeakerFlat,
+            peq_model: PeqModel::Pk,
             strategy: None,
             de_f: None,
             de_cr: None,
@@ -55,6 +56,19 @@ mod tests {
             captured_magnitudes: None,
             target_frequencies: None,
             target_magnitudes: None,
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            frequency_grid: FrequencyGrid::default(),
         }
     }
 
@@ -116,6 +130,69 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("Minimum dB"));
     }
 
+    #[test]
+    fn test_validate_params_pinned_filters() {
+        let mut params = create_test_optimization_params();
+        let highpass = DecodedFilter {
+            filter_type: FilterType::Highpass,
+            frequency: 80.0,
+            q: 0.7,
+            gain: 0.0,
+        };
+        params.pinned_filters = vec![PinnedFilter {
+            filter: highpass.clone(),
+            range: None,
+        }];
+        assert!(validate_params(&params).is_ok());
+
+        params.pinned_filters = vec![PinnedFilter {
+            filter: highpass.clone(),
+            range: Some(FilterRange {
+                min_freq: 120.0,
+                max_freq: 40.0,
+                min_q: 0.5,
+                max_q: 1.0,
+                min_gain: 0.0,
+                max_gain: 0.0,
+            }),
+        }];
+        let result = validate_params(&params);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Invalid range"));
+
+        let range = FilterRange {
+            min_freq: 40.0,
+            max_freq: 120.0,
+            min_q: 0.5,
+            max_q: 1.0,
+            min_gain: 0.0,
+            max_gain: 0.0,
+        };
+        params.pinned_filters = vec![PinnedFilter {
+            filter: DecodedFilter {
+                frequency: 150.0,
+                ..highpass.clone()
+            },
+            range: Some(range.clone()),
+        }];
+        let result = validate_params(&params);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("outside its own range")
+        );
+
+        params.min_freq = 60.0;
+        params.pinned_filters = vec![PinnedFilter {
+            filter: highpass,
+            range: Some(range),
+        }];
+        let errors = validate_params(&params).unwrap_err();
+        assert!(errors.has_field("pinned_filters[0]"));
+        assert_eq!(errors.0[0].min, Some(60.0));
+    }
+
     #[test]
     fn test_validate_params_invalid_sample_rate() {
         let mut params = create_test_optimization_params();
@@ -324,9 +401,16 @@ mod tests {
     fn test_optimization_result_serialization() {
         let result = OptimizationResult {
             success: true,
+            cancelled: false,
+            truncated: false,
             error_message: None,
             filter_params: Some(vec![1.0, 2.0, 3.0]),
             objective_value: Some(0.5),
+            residual_metrics: None,
+            filters: None,
+            peq_model: None,
+            filter_locks: None,
+            preamp_db: None,
             preference_score_before: Some(7.5),
             preference_score_after: Some(8.2),
             filter_response: None,
@@ -334,6 +418,8 @@ mod tests {
             filter_plots: None,
             input_curve: None,
             deviation_curve: None,
+            run_manifest: None,
+            measurement_spread: None,
         };
 
         // Test that the struct can be serialized (important for Tauri commands)
@@ -352,6 +438,7 @@ mod tests {
             fitness: 0.123,
             params: vec![1.0, 2.0, 3.0],
             convergence: 0.001,
+            evaluations: None,
         };
 
         let serialized = serde_json::to_string(&update);
@@ -362,6 +449,86 @@ mod tests {
         assert!(json_str.contains("\"fitness\":0.123"));
     }
 
+    #[test]
+    fn test_choice_enums_parse_and_serialize() {
+        assert_eq!("speaker-score".parse::<Loss>(), Ok(Loss::SpeakerScore));
+        assert_eq!("flat".parse::<Loss>(), Ok(Loss::SpeakerFlat)); // legacy alias
+        assert_eq!("mh:pso".parse::<Algorithm>(), Ok(Algorithm::MhPso));
+        assert_eq!("hp-pk-lp".parse::<PeqModel>(), Ok(PeqModel::HpPkLp));
+
+        let err = "speaker-flatt".parse::<Loss>().unwrap_err();
+        assert!(err.contains("Unknown loss 'speaker-flatt'"));
+        assert!(err.contains("headphone-score"));
+        assert!("nlopt:cobila".parse::<Algorithm>().is_err());
+        assert!("pk-hp".parse::<PeqModel>().is_err());
+
+        assert_eq!(
+            serde_json::to_string(&Algorithm::NloptCobyla).unwrap(),
+            "\"nlopt:cobyla\""
+        );
+        for algo in Algorithm::ALL {
+            let json = serde_json::to_string(algo).unwrap();
+            assert_eq!(serde_json::from_str::<Algorithm>(&json).unwrap(), *algo);
+        }
+    }
+
+    #[test]
+    fn test_optimization_params_reject_unknown_choices() {
+        let params = create_test_optimization_params();
+        let mut json = serde_json::to_value(&params).unwrap();
+        assert!(serde_json::from_value::<OptimizationParams>(json.clone()).is_ok());
+
+        json["loss"] = serde_json::Value::String("speaker-flatt".to_string());
+        let err = serde_json::from_value::<OptimizationParams>(json.clone()).unwrap_err();
+        assert!(err.to_string().contains("Unknown loss"));
+
+        json["loss"] = serde_json::Value::String("speaker-flat".to_string());
+        json["peq_model"] = serde_json::Value::String("hp-pk-lpp".to_string());
+        let err = serde_json::from_value::<OptimizationParams>(json.clone()).unwrap_err();
+        assert!(err.to_string().contains("Unknown PEQ model"));
+
+        // peq_model is optional in the UI and defaults to all peak filters
+        json.as_object_mut().unwrap().remove("peq_model");
+        let params = serde_json::from_value::<OptimizationParams>(json).unwrap();
+        assert_eq!(params.peq_model, PeqModel::Pk);
+    }
+
+    #[test]
+    fn test_optimization_choices_cover_all_variants() {
+        let choices = optimization_choices();
+        assert_eq!(choices.losses.len(), Loss::ALL.len());
+        assert_eq!(choices.algorithms.len(), Algorithm::ALL.len());
+        assert_eq!(choices.peq_models.len(), PeqModel::ALL.len());
+        assert_eq!(choices.aggregates.len(), CurveAggregate::ALL.len());
+        assert_eq!(choices.algorithms[0].value, "autoeq:de");
+        assert!(
+            choices
+                .peq_models
+                .iter()
+                .all(|c| !c.label.is_empty() && !c.description.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_progress_update_evaluations() {
+        let update = ProgressUpdate {
+            iteration: 150,
+            fitness: 0.5,
+            params: vec![],
+            convergence: 0.0,
+            evaluations: Some(150),
+        };
+        let json_str = serde_json::to_string(&update).unwrap();
+        assert!(json_str.contains("\"evaluations\":150"));
+
+        // Updates without an evaluation count still deserialize
+        let update: ProgressUpdate = serde_json::from_str(
+            r#"{"iteration": 3, "fitness": 1.0, "params": [], "convergence": 0.1}"#,
+        )
+        .unwrap();
+        assert_eq!(update.evaluations, None);
+    }
+
     #[test]
     fn test_plot_data_serialization() {
         let mut curves = HashMap::new();
@@ -413,6 +580,7 @@ mod tests {
             fitness: 2.456,
             params: vec![100.0, 1.5, 2.0, 200.0, 2.5, -1.5],
             convergence: 0.05,
+            evaluations: None,
         };
 
         // Test serialization (this is what gets sent as event payload)
@@ -450,6 +618,7 @@ mod tests {
             fitness: 1.234,
             params: vec![100.0, 1.5, 2.0, 200.0, 2.5, -1.5],
             convergence: 0.001,
+            evaluations: None,
         };
 
         // Test serialization (this is what gets sent as event payload)
@@ -468,22 +637,269 @@ mod tests {
     }
 
     #[test]
-    fn test_cancellation_state() {
-        use crate::CancellationState;
+    fn test_cancellation_token() {
+        use crate::CancellationToken;
 
-        let state = CancellationState::new();
+        let token = CancellationToken::new();
 
         // Initially not cancelled
-        assert!(!state.is_cancelled());
+        assert!(!token.is_cancelled());
 
         // Cancel and check
-        state.cancel();
-        assert!(state.is_cancelled());
+        token.cancel();
+        assert!(token.is_cancelled());
 
         // Reset and check
-        state.reset();
-        assert!(!state.is_cancelled());
+        token.reset();
+        assert!(!token.is_cancelled());
+
+        println!("[TEST] ✅ Cancellation token works correctly");
+    }
+
+    #[test]
+    fn test_cancellation_token_clones_share_state() {
+        use crate::CancellationToken;
+
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        clone.cancel();
+        assert!(token.is_cancelled());
 
-        println!("[TEST] ✅ Cancellation state works correctly");
+        token.reset();
+        assert!(!clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancellation_token_child() {
+        use crate::{CancelReason, CancellationToken};
+
+        let parent = CancellationToken::new();
+        let first = parent.child();
+        let second = parent.child();
+
+        // Cancelling a child leaves the parent and its siblings running
+        first.cancel();
+        assert!(first.is_cancelled());
+        assert!(!parent.is_cancelled());
+        assert!(!second.is_cancelled());
+
+        // Cancelling the parent reaches every child
+        parent.cancel();
+        assert_eq!(second.cancel_reason(), Some(CancelReason::Requested));
+    }
+
+    #[test]
+    fn test_cancellation_token_deadline() {
+        use crate::{CancelReason, CancellationToken};
+        use std::time::Duration;
+
+        let expired = CancellationToken::with_timeout(Duration::ZERO);
+        assert_eq!(
+            expired.cancel_reason(),
+            Some(CancelReason::DeadlineExceeded)
+        );
+        assert_eq!(expired.remaining(), Some(Duration::ZERO));
+
+        let parent = CancellationToken::new();
+        let child = parent.child_with_timeout(Duration::from_secs(3600));
+        assert!(!child.is_cancelled());
+        assert!(child.remaining().is_some());
+        assert!(parent.remaining().is_none());
+
+        // A child inherits the deadline of its parent
+        let child = expired.child();
+        assert_eq!(child.cancel_reason(), Some(CancelReason::DeadlineExceeded));
+    }
+
+    #[test]
+    fn test_cancelled_result_serialization() {
+        use crate::{CancelReason, OptimizationError};
+
+        let result = OptimizationResult::from_error(&OptimizationError::Cancelled(
+            CancelReason::DeadlineExceeded,
+        ));
+        let serialized = serde_json::to_value(&result).unwrap();
+        assert_eq!(serialized["success"], false);
+        assert_eq!(serialized["cancelled"], true);
+        assert_eq!(
+            serialized["error_message"],
+            "Optimization time budget exceeded"
+        );
+
+        let result = OptimizationResult::from_error(&OptimizationError::Failed("boom".to_string()));
+        assert!(!result.cancelled);
+        assert_eq!(
+            result.error_message.as_deref(),
+            Some("Optimization failed: boom")
+        );
+    }
+
+    struct NoopProgressCallback;
+
+    impl crate::optim::ProgressCallback for NoopProgressCallback {
+        fn on_progress(&self, _update: ProgressUpdate) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_warm_start_seeds_de() {
+        use crate::CancellationToken;
+        use crate::optim::run_optimization_internal;
+        use std::sync::Arc;
+
+        let params = OptimizationParams {
+            algo: Algorithm::AutoeqDe,
+            population: 20,
+            maxeval: 2000,
+            seed: Some(1),
+            ..test_mocks::mocks::create_captured_curve_params()
+        };
+        let cold = run_optimization_internal(
+            params.clone(),
+            Arc::new(NoopProgressCallback),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        let cold_loss = cold.objective_value.unwrap();
+
+        // Too few evaluations to get anywhere from a random population
+        for refine in [false, true] {
+            let warm = OptimizationParams {
+                maxeval: 10,
+                seed: Some(2),
+                refine,
+                initial_filters: cold.filters.clone(),
+                ..params.clone()
+            };
+            let result = run_optimization_internal(
+                warm,
+                Arc::new(NoopProgressCallback),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+            let loss = result.objective_value.unwrap();
+            assert!(
+                loss <= cold_loss + 1e-9,
+                "warm start (refine: {}) lost its start: {} > {}",
+                refine,
+                loss,
+                cold_loss
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fixed_filters_over_boost_limit() {
+        use crate::optim::run_optimization_internal;
+        use crate::{CancellationToken, OptimizationError};
+        use std::sync::Arc;
+
+        let boost = DecodedFilter {
+            filter_type: FilterType::Peak,
+            frequency: 1000.0,
+            q: 1.0,
+            gain: 6.0,
+        };
+        let params = OptimizationParams {
+            max_boost_db: Some(3.0),
+            pinned_filters: vec![PinnedFilter {
+                filter: boost,
+                range: None,
+            }],
+            ..test_mocks::mocks::create_captured_curve_params()
+        };
+        let result = run_optimization_internal(
+            params,
+            Arc::new(NoopProgressCallback),
+            CancellationToken::new(),
+        )
+        .await;
+        match result {
+            Err(OptimizationError::InvalidParams(message)) => {
+                assert!(message.contains("maximum total boost"), "{}", message)
+            }
+            other => panic!(
+                "expected an invalid parameters error, got {:?}",
+                other.map(|r| r.success)
+            ),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_device_snapping_keeps_boost_limit() {
+        use crate::filters::peak_boost_db;
+        use crate::optim::run_optimization_internal;
+        use crate::{CancellationToken, DeviceProfile};
+        use std::sync::Arc;
+
+        let profile: DeviceProfile = "graphic-10".parse().unwrap();
+        let params = OptimizationParams {
+            maxeval: 500,
+            max_boost_db: Some(2.0),
+            device_profile: Some(profile.clone()),
+            ..test_mocks::mocks::create_captured_curve_params()
+        };
+        let result = run_optimization_internal(
+            params,
+            Arc::new(NoopProgressCallback),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let filters = result.filters.unwrap();
+        let bands = profile.frequencies.unwrap();
+        for filter in &filters {
+            assert!(bands.contains(&filter.frequency), "{} Hz", filter.frequency);
+            assert_eq!(filter.gain, filter.gain.round(), "1 dB steps");
+        }
+        assert!(peak_boost_db(&filters, 48000.0) <= 2.0 + 1e-6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_boost_penalty_reduces_boosts() {
+        use crate::CancellationToken;
+        use crate::filters::filters_response;
+        use crate::optim::run_optimization_internal;
+        use ndarray::Array1;
+        use std::sync::Arc;
+
+        let freq = Array1::logspace(10.0, 20f64.log10(), 20000f64.log10(), 500);
+        let boost_energy = |boost_penalty: f64| {
+            let freq = freq.clone();
+            async move {
+                let params = OptimizationParams {
+                    maxeval: 2000,
+                    seed: Some(3),
+                    boost_penalty,
+                    ..test_mocks::mocks::create_captured_curve_params()
+                };
+                let result = run_optimization_internal(
+                    params,
+                    Arc::new(NoopProgressCallback),
+                    CancellationToken::new(),
+                )
+                .await
+                .unwrap();
+                filters_response(&result.filters.unwrap(), &freq, 48000.0)
+                    .iter()
+                    .map(|e| e.max(0.0).powi(2))
+                    .sum::<f64>()
+            }
+        };
+
+        // The dip at 3 kHz asks for a boost; the penalty makes the EQ boost less
+        let plain = boost_energy(0.0).await;
+        let penalized = boost_energy(5.0).await;
+        assert!(plain > 0.0);
+        assert!(
+            penalized < plain,
+            "boost energy {} with the penalty, {} without",
+            penalized,
+            plain
+        );
     }
 }
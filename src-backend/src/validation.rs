@@ -0,0 +1,523 @@
+//! Validation of optimization parameters
+//!
+//! Every failed check names the `OptimizationParams` field it concerns and,
+//! for numeric fields, the allowed range, so that UIs can highlight the
+//! offending inputs. All failures are reported at once rather than the first
+//! one only. The limits live here and nowhere else: UIs call
+//! [`validate_params`] instead of repeating the checks.
+
+use crate::choices::Algorithm;
+use crate::filters::model_filter_types;
+use crate::loss::MAX_BOOST_PENALTY;
+use crate::optim::OptimizationParams;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const MAX_FILTERS: usize = 50;
+pub const MIN_FREQ: f64 = 20.0;
+pub const MAX_FREQ: f64 = 20000.0;
+pub const MIN_Q: f64 = 0.1;
+pub const MAX_Q: f64 = 20.0;
+pub const MIN_DB: f64 = 0.25;
+pub const MAX_DB: f64 = 20.0;
+pub const MIN_SAMPLE_RATE: f64 = 8000.0;
+pub const MAX_SAMPLE_RATE: f64 = 192000.0;
+pub const MAX_POPULATION: usize = 10000;
+pub const MAX_SMOOTH_N: usize = 24;
+pub const MAX_TOTAL_BOOST_DB: f64 = 20.0;
+
+/// One failed check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub field: String,    // Params field, e.g. "max_q" or "pinned_filters[1]"
+    pub min: Option<f64>, // Allowed range of the field, when it has one
+    pub max: Option<f64>,
+    pub message: String,
+}
+
+/// Every failed check of a parameter set, in field order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    /// Whether any check on `field` failed
+    pub fn has_field(&self, field: &str) -> bool {
+        self.0.iter().any(|e| e.field == field)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.0.iter().map(|e| e.message.as_str()).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Failed checks collected so far
+#[derive(Default)]
+struct Checks(Vec<ValidationError>);
+
+impl Checks {
+    fn fail(&mut self, field: &str, min: Option<f64>, max: Option<f64>, message: String) {
+        self.0.push(ValidationError {
+            field: field.to_string(),
+            min,
+            max,
+            message,
+        });
+    }
+
+    /// Fail unless `min <= value <= max`
+    fn within(&mut self, field: &str, name: &str, value: f64, min: f64, max: f64, unit: &str) {
+        if !(min..=max).contains(&value) {
+            self.fail(
+                field,
+                Some(min),
+                Some(max),
+                format!(
+                    "{} must be between {} and {}{} (got: {}{})",
+                    name, min, max, unit, value, unit
+                ),
+            );
+        }
+    }
+}
+
+/// Check `params`, reporting every failed check
+pub fn validate_params(params: &OptimizationParams) -> Result<(), ValidationErrors> {
+    let mut checks = Checks::default();
+    let filters_range = (Some(1.0), Some(MAX_FILTERS as f64));
+
+    // Number of filters
+    if params.num_filters == 0 {
+        checks.fail(
+            "num_filters",
+            filters_range.0,
+            filters_range.1,
+            "Number of filters must be at least 1".to_string(),
+        );
+    } else if params.num_filters > MAX_FILTERS {
+        checks.fail(
+            "num_filters",
+            filters_range.0,
+            filters_range.1,
+            format!(
+                "Number of filters must be between 1 and {} (got: {})",
+                MAX_FILTERS, params.num_filters
+            ),
+        );
+    }
+
+    // Tolerances, no upper bound
+    if let Some(tol) = params.tolerance
+        && tol < 1e-12
+    {
+        checks.fail(
+            "tolerance",
+            Some(1e-12),
+            None,
+            format!("Tolerance must be >= 1e-12 (got: {})", tol),
+        );
+    }
+    if let Some(atol) = params.atolerance
+        && atol < 1e-15
+    {
+        checks.fail(
+            "atolerance",
+            Some(1e-15),
+            None,
+            format!("Absolute tolerance must be >= 1e-15 (got: {})", atol),
+        );
+    }
+
+    // Frequency range
+    if params.min_freq >= params.max_freq {
+        checks.fail(
+            "min_freq",
+            Some(MIN_FREQ),
+            Some(params.max_freq),
+            format!(
+                "Minimum frequency ({} Hz) must be less than maximum frequency ({} Hz)",
+                params.min_freq, params.max_freq
+            ),
+        );
+    }
+    if params.min_freq < MIN_FREQ {
+        checks.fail(
+            "min_freq",
+            Some(MIN_FREQ),
+            Some(MAX_FREQ),
+            format!(
+                "Minimum frequency must be >= {} Hz (got: {} Hz)",
+                MIN_FREQ, params.min_freq
+            ),
+        );
+    }
+    if params.max_freq > MAX_FREQ {
+        checks.fail(
+            "max_freq",
+            Some(MIN_FREQ),
+            Some(MAX_FREQ),
+            format!(
+                "Maximum frequency must be <= {} Hz (got: {} Hz)",
+                MAX_FREQ, params.max_freq
+            ),
+        );
+    }
+
+    // Analysis grid
+    let grid = &params.frequency_grid;
+    if let Err(message) = grid.validate() {
+        checks.fail("frequency_grid", None, None, message);
+    } else if grid.min_freq > params.min_freq || grid.max_freq < params.max_freq {
+        checks.fail(
+            "frequency_grid",
+            None,
+            None,
+            format!(
+                "Frequency grid ({}-{} Hz) must cover the optimization range ({}-{} Hz)",
+                grid.min_freq, grid.max_freq, params.min_freq, params.max_freq
+            ),
+        );
+    }
+
+    // Q range
+    if params.min_q >= params.max_q {
+        checks.fail(
+            "min_q",
+            Some(MIN_Q),
+            Some(params.max_q),
+            format!(
+                "Minimum Q ({}) must be less than maximum Q ({})",
+                params.min_q, params.max_q
+            ),
+        );
+    }
+    if params.min_q < MIN_Q {
+        checks.fail(
+            "min_q",
+            Some(MIN_Q),
+            Some(MAX_Q),
+            format!("Minimum Q must be >= {} (got: {})", MIN_Q, params.min_q),
+        );
+    }
+    if params.max_q > MAX_Q {
+        checks.fail(
+            "max_q",
+            Some(MIN_Q),
+            Some(MAX_Q),
+            format!("Maximum Q must be <= {} (got: {})", MAX_Q, params.max_q),
+        );
+    }
+
+    // Gain range
+    if params.min_db > params.max_db {
+        checks.fail(
+            "min_db",
+            Some(MIN_DB),
+            Some(params.max_db),
+            format!(
+                "Minimum dB ({}) must be <= maximum dB ({})",
+                params.min_db, params.max_db
+            ),
+        );
+    }
+    if params.min_db < MIN_DB {
+        checks.fail(
+            "min_db",
+            Some(MIN_DB),
+            Some(MAX_DB),
+            format!("Minimum dB must be >= {} (got: {})", MIN_DB, params.min_db),
+        );
+    }
+    if params.max_db > MAX_DB {
+        checks.fail(
+            "max_db",
+            Some(MIN_DB),
+            Some(MAX_DB),
+            format!("Maximum dB must be <= {} (got: {})", MAX_DB, params.max_db),
+        );
+    }
+
+    checks.within(
+        "sample_rate",
+        "Sample rate",
+        params.sample_rate,
+        MIN_SAMPLE_RATE,
+        MAX_SAMPLE_RATE,
+        " Hz",
+    );
+
+    // Optimizer budget
+    if params.population == 0 {
+        checks.fail(
+            "population",
+            Some(1.0),
+            Some(MAX_POPULATION as f64),
+            "Population size must be at least 1".to_string(),
+        );
+    } else {
+        checks.within(
+            "population",
+            "Population size",
+            params.population as f64,
+            1.0,
+            MAX_POPULATION as f64,
+            "",
+        );
+    }
+    if params.maxeval == 0 {
+        checks.fail(
+            "maxeval",
+            Some(1.0),
+            None,
+            "Maximum evaluations must be at least 1".to_string(),
+        );
+    }
+
+    checks.within(
+        "smooth_n",
+        "Smoothing N",
+        params.smooth_n as f64,
+        1.0,
+        MAX_SMOOTH_N as f64,
+        "",
+    );
+
+    // DE parameters, when set
+    if let Some(de_f) = params.de_f {
+        checks.within("de_f", "Mutation factor (F)", de_f, 0.0, 2.0, "");
+    }
+    if let Some(de_cr) = params.de_cr {
+        checks.within(
+            "de_cr",
+            "Recombination probability (CR)",
+            de_cr,
+            0.0,
+            1.0,
+            "",
+        );
+    }
+    if let Some(w) = params.adaptive_weight_f {
+        checks.within("adaptive_weight_f", "Adaptive weight F", w, 0.0, 1.0, "");
+    }
+    if let Some(w) = params.adaptive_weight_cr {
+        checks.within("adaptive_weight_cr", "Adaptive weight CR", w, 0.0, 1.0, "");
+    }
+
+    if let Some(max_boost) = params.max_boost_db {
+        checks.within(
+            "max_boost_db",
+            "Maximum total boost",
+            max_boost,
+            0.0,
+            MAX_TOTAL_BOOST_DB,
+            " dB",
+        );
+    }
+
+    if let Some(profile) = &params.device_profile
+        && let Err(message) = profile.check_params(params)
+    {
+        checks.fail("device_profile", None, None, message);
+    }
+
+    // Measurement sets
+    for (i, measurement) in params.captured_measurements.iter().enumerate() {
+        if let Err(message) = measurement.validate() {
+            checks.fail(
+                &format!("captured_measurements[{}]", i),
+                None,
+                None,
+                message,
+            );
+        }
+    }
+    if !params.captured_measurements.is_empty() && params.captured_frequencies.is_some() {
+        checks.fail(
+            "captured_measurements",
+            None,
+            None,
+            "Use either captured measurements or a single captured curve, not both".to_string(),
+        );
+    }
+
+    // Loss shaping
+    if let Some(weighting) = &params.loss_weighting
+        && let Err(message) = weighting.validate()
+    {
+        checks.fail("loss_weighting", None, None, message);
+    }
+    checks.within(
+        "boost_penalty",
+        "Boost penalty",
+        params.boost_penalty,
+        0.0,
+        MAX_BOOST_PENALTY,
+        "",
+    );
+    if params.shapes_loss() && !params.loss.is_flat() {
+        checks.fail(
+            "loss",
+            None,
+            None,
+            format!(
+                "Frequency weighting and boost penalty need a flat loss (got: {})",
+                params.loss
+            ),
+        );
+    }
+
+    // Greedy peak picking
+    if params.algo == Algorithm::GreedyPeaks || params.greedy_init {
+        if model_filter_types(params.peq_model, params.num_filters).is_none() {
+            checks.fail(
+                "peq_model",
+                None,
+                None,
+                format!(
+                    "Greedy peak picking needs a PEQ model with fixed filter types (got: {})",
+                    params.peq_model
+                ),
+            );
+        }
+        if params.greedy_init && params.initial_filters.is_some() {
+            checks.fail(
+                "greedy_init",
+                None,
+                None,
+                "Greedy initial guess and initial filters are exclusive".to_string(),
+            );
+        }
+    }
+
+    // Pinned filters
+    for (i, pinned) in params.pinned_filters.iter().enumerate() {
+        let field = format!("pinned_filters[{}]", i);
+        let filter = &pinned.filter;
+        if filter.frequency <= 0.0 || filter.q <= 0.0 {
+            checks.fail(
+                &field,
+                None,
+                None,
+                format!(
+                    "Pinned filter needs a positive frequency and Q (got: {} Hz, Q {})",
+                    filter.frequency, filter.q
+                ),
+            );
+        }
+        let Some(range) = &pinned.range else {
+            continue;
+        };
+        if range.min_freq <= 0.0
+            || range.min_q <= 0.0
+            || range.min_freq > range.max_freq
+            || range.min_q > range.max_q
+            || range.min_gain > range.max_gain
+        {
+            checks.fail(
+                &field,
+                None,
+                None,
+                format!(
+                    "Invalid range for the pinned filter at {} Hz: {:?}",
+                    filter.frequency, range
+                ),
+            );
+            continue;
+        }
+        // The optimizer starts from the pinned values, they must be reachable
+        if filter.frequency < range.min_freq
+            || filter.frequency > range.max_freq
+            || filter.q < range.min_q
+            || filter.q > range.max_q
+            || (filter.filter_type.has_gain()
+                && (filter.gain < range.min_gain || filter.gain > range.max_gain))
+        {
+            checks.fail(
+                &field,
+                None,
+                None,
+                format!(
+                    "Pinned filter at {} Hz (Q {}, {} dB) is outside its own range: {:?}",
+                    filter.frequency, filter.q, filter.gain, range
+                ),
+            );
+        }
+        if range.min_freq < params.min_freq || range.max_freq > params.max_freq {
+            checks.fail(
+                &field,
+                Some(params.min_freq),
+                Some(params.max_freq),
+                format!(
+                    "Pinned filter at {} Hz: range {}-{} Hz must be within {}-{} Hz",
+                    filter.frequency,
+                    range.min_freq,
+                    range.max_freq,
+                    params.min_freq,
+                    params.max_freq
+                ),
+            );
+        }
+    }
+
+    if checks.0.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(checks.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_every_failure() {
+        let params = OptimizationParams {
+            num_filters: 0,
+            max_q: 25.0,
+            sample_rate: 1000.0,
+            ..OptimizationParams::default()
+        };
+        let errors = validate_params(&params).unwrap_err();
+        let fields: Vec<&str> = errors.0.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["num_filters", "max_q", "sample_rate"]);
+        assert!(errors.has_field("max_q"));
+        assert!(!errors.has_field("min_q"));
+    }
+
+    #[test]
+    fn test_range_matches_check() {
+        let params = OptimizationParams {
+            max_q: MAX_Q + 1.0,
+            ..OptimizationParams::default()
+        };
+        let errors = validate_params(&params).unwrap_err();
+        let error = &errors.0[0];
+        assert_eq!(error.max, Some(MAX_Q));
+        assert!(error.message.contains(&format!("<= {}", MAX_Q)));
+
+        let at_limit = OptimizationParams {
+            max_q: MAX_Q,
+            ..OptimizationParams::default()
+        };
+        assert!(validate_params(&at_limit).is_ok());
+    }
+
+    #[test]
+    fn test_serializes_as_list() {
+        let errors = ValidationErrors(vec![ValidationError {
+            field: "max_db".to_string(),
+            min: Some(MIN_DB),
+            max: Some(MAX_DB),
+            message: "Maximum dB must be <= 20 (got: 30)".to_string(),
+        }]);
+        let json = serde_json::to_value(&errors).unwrap();
+        assert_eq!(json[0]["field"], "max_db");
+        assert_eq!(json[0]["max"], 20.0);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::design::{colors, components, fonts, spacing, StyledDiv, RADIUS};
 use autoeq_backend::{
-    optim::{run_optimization_internal, CancellationState, ProgressCallback, ProgressUpdate},
-    CurveData, OptimizationParams, OptimizationResult,
+    optim::{JobId, JobManager, ProgressCallback, ProgressUpdate},
+    optimization_choices, validate_params, Algorithm, ChoiceInfo, CurveAggregate, CurveData,
+    FrequencyGrid, Loss, OptimizationChoices, OptimizationParams, OptimizationResult, PeqModel,
 };
 use gpui::{prelude::FluentBuilder, *};
 use std::sync::Arc;
@@ -15,11 +16,19 @@ pub enum OptimizationStatus {
     Error(String),
 }
 
+/// Parameters picked from the backend's optimization choices
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChoiceField {
+    Loss,
+    PeqModel,
+    Algorithm,
+}
+
 /// Main EQ Design component with complete parameter management
 pub struct EQDesignComponent {
     // === EQ Design Section Parameters ===
     // Loss & Curve
-    loss: String,
+    loss: Loss,
     curve_name: String,
 
     // Filter Configuration
@@ -39,7 +48,7 @@ pub struct EQDesignComponent {
     max_freq: f64,
 
     // PEQ Model
-    peq_model: String,
+    peq_model: PeqModel,
 
     // Spacing
     min_spacing_oct: f64,
@@ -47,7 +56,7 @@ pub struct EQDesignComponent {
 
     // === Optimization Fine Tuning Section Parameters ===
     // Algorithm
-    algo: String,
+    algo: Algorithm,
     population: usize,
     maxeval: usize,
 
@@ -70,13 +79,19 @@ pub struct EQDesignComponent {
     smooth: bool,
     smooth_n: usize,
 
+    // === Dropdowns ===
+    choices: OptimizationChoices,
+    open_dropdown: Option<ChoiceField>,
+
     // === Status and Results ===
     optimization_status: OptimizationStatus,
+    invalid_fields: Vec<String>, // Parameters rejected by the last validation
 
     // === Progress Tracking ===
     current_iteration: usize,
     current_fitness: f64,
-    cancellation_state: Option<Arc<CancellationState>>,
+    job_manager: JobManager,
+    current_job: Option<JobId>,
 
     // === Input Data ===
     // These will be set externally (from captured audio or loaded files)
@@ -88,9 +103,14 @@ impl EQDesignComponent {
     /// Create a new EQDesignComponent with default values
     /// These defaults match the TypeScript OPTIMIZATION_DEFAULTS from optimization-constants.ts
     pub fn new() -> Self {
+        Self::with_job_manager(JobManager::default())
+    }
+
+    /// Create a component submitting its optimizations to a shared job manager
+    pub fn with_job_manager(job_manager: JobManager) -> Self {
         Self {
             // EQ Design Parameters - defaults
-            loss: "speaker-flat".to_string(),
+            loss: Loss::SpeakerFlat,
             curve_name: "Listening Window".to_string(),
             num_filters: 5,
             sample_rate: 48000.0,
@@ -100,12 +120,12 @@ impl EQDesignComponent {
             max_q: 3.0,
             min_freq: 60.0,
             max_freq: 16000.0,
-            peq_model: "pk".to_string(),
+            peq_model: PeqModel::Pk,
             min_spacing_oct: 0.5,
             spacing_weight: 20.0,
 
             // Optimization Fine Tuning Parameters - defaults
-            algo: "autoeq:de".to_string(),
+            algo: Algorithm::AutoeqDe,
             population: 30,
             maxeval: 20000,
             strategy: "currenttobest1bin".to_string(),
@@ -120,11 +140,16 @@ impl EQDesignComponent {
             smooth: true,
             smooth_n: 1,
 
+            choices: optimization_choices(),
+            open_dropdown: None,
+
             // Status and data
             optimization_status: OptimizationStatus::Idle,
+            invalid_fields: Vec::new(),
             current_iteration: 0,
             current_fitness: 0.0,
-            cancellation_state: None,
+            job_manager,
+            current_job: None,
             input_curve: None,
             target_curve: None,
         }
@@ -132,18 +157,17 @@ impl EQDesignComponent {
 
     /// Reset all parameters to default values
     pub fn reset_to_defaults(&mut self, cx: &mut Context<Self>) {
-        *self = Self::new();
+        *self = Self::with_job_manager(self.job_manager.clone());
         cx.notify();
     }
 
     /// Cancel ongoing optimization
     pub fn cancel_optimization(&mut self, cx: &mut Context<Self>) {
-        if let Some(cancellation) = &self.cancellation_state {
-            cancellation.cancel();
-            log::info!("[EQDesign] Optimization cancellation requested");
+        if let Some(job_id) = self.current_job.take() {
+            self.job_manager.cancel(job_id);
+            log::info!("[EQDesign] Optimization cancellation requested for job {}", job_id);
         }
         self.optimization_status = OptimizationStatus::Error("Cancelled by user".to_string());
-        self.cancellation_state = None;
         cx.notify();
     }
 
@@ -152,6 +176,30 @@ impl EQDesignComponent {
         cx.notify();
     }
 
+    /// Open a dropdown, or close it when it is already open
+    fn toggle_dropdown(&mut self, field: ChoiceField, cx: &mut Context<Self>) {
+        self.open_dropdown = if self.open_dropdown == Some(field) {
+            None
+        } else {
+            Some(field)
+        };
+        cx.notify();
+    }
+
+    /// Apply a value picked from a dropdown
+    fn select_choice(&mut self, field: ChoiceField, value: &str, cx: &mut Context<Self>) {
+        let parsed = match field {
+            ChoiceField::Loss => value.parse().map(|loss| self.loss = loss),
+            ChoiceField::PeqModel => value.parse().map(|model| self.peq_model = model),
+            ChoiceField::Algorithm => value.parse().map(|algo| self.algo = algo),
+        };
+        if let Err(e) = parsed {
+            log::error!("[EQDesign] {}", e);
+        }
+        self.open_dropdown = None;
+        cx.notify();
+    }
+
     pub fn toggle_smooth(&mut self, cx: &mut Context<Self>) {
         self.smooth = !self.smooth;
         cx.notify();
@@ -203,7 +251,7 @@ impl EQDesignComponent {
             version: None,
             measurement: None,
             curve_name: self.curve_name.clone(),
-            algo: self.algo.clone(),
+            algo: self.algo,
             population: self.population,
             maxeval: self.maxeval,
             refine: self.refine,
@@ -212,8 +260,8 @@ impl EQDesignComponent {
             spacing_weight: self.spacing_weight,
             smooth: self.smooth,
             smooth_n: self.smooth_n,
-            loss: self.loss.clone(),
-            peq_model: Some(self.peq_model.clone()),
+            loss: self.loss,
+            peq_model: self.peq_model,
             strategy: Some(self.strategy.clone()),
             de_f: Some(self.de_f),
             de_cr: Some(self.de_cr),
@@ -225,53 +273,66 @@ impl EQDesignComponent {
             captured_magnitudes: Some(input_curve.spl),
             target_frequencies: self.target_curve.as_ref().map(|t| t.freq.clone()),
             target_magnitudes: self.target_curve.as_ref().map(|t| t.spl.clone()),
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            frequency_grid: FrequencyGrid::default(),
         })
     }
 
-    /// Validate parameters
-    fn validate_parameters(&self) -> Result<(), String> {
-        // Validate numeric ranges
-        if self.num_filters < 1 || self.num_filters > 20 {
-            return Err("Number of filters must be between 1 and 20".to_string());
-        }
-        if self.min_db >= self.max_db {
-            return Err("Min dB must be less than Max dB".to_string());
-        }
-        if self.min_q >= self.max_q {
-            return Err("Min Q must be less than Max Q".to_string());
-        }
-        if self.min_freq >= self.max_freq {
-            return Err("Min frequency must be less than Max frequency".to_string());
-        }
-        if self.input_curve.is_none() {
-            return Err("No input curve data available".to_string());
+    /// Submit optimization
+    fn submit_optimization(&mut self, cx: &mut Context<Self>) {
+        if let Some(params) = self.checked_params(cx) {
+            self.run_job(params, cx);
         }
-        Ok(())
     }
 
-    /// Submit optimization
-    fn submit_optimization(&mut self, cx: &mut Context<Self>) {
-        // Validate parameters
-        if let Err(error) = self.validate_parameters() {
-            self.optimization_status = OptimizationStatus::Error(error);
-            cx.notify();
-            return;
+    /// Instant preview: fit the EQ with greedy peak picking, which takes milliseconds
+    fn preview_optimization(&mut self, cx: &mut Context<Self>) {
+        if let Some(params) = self.checked_params(cx) {
+            self.run_job(
+                OptimizationParams {
+                    algo: Algorithm::GreedyPeaks,
+                    ..params
+                },
+                cx,
+            );
         }
+    }
 
-        // Build params
-        let params = match self.build_params() {
-            Ok(p) => p,
-            Err(e) => {
-                self.optimization_status = OptimizationStatus::Error(e);
+    /// Parameters validated by the backend, or `None` after showing the errors
+    fn checked_params(&mut self, cx: &mut Context<Self>) -> Option<OptimizationParams> {
+        self.invalid_fields.clear();
+        let checked = self
+            .build_params()
+            .and_then(|params| match validate_params(&params) {
+                Ok(()) => Ok(params),
+                Err(errors) => {
+                    self.invalid_fields = errors.0.iter().map(|e| e.field.clone()).collect();
+                    Err(errors.to_string())
+                }
+            });
+        match checked {
+            Ok(params) => Some(params),
+            Err(error) => {
+                self.optimization_status = OptimizationStatus::Error(error);
                 cx.notify();
-                return;
+                None
             }
-        };
-
-        // Create cancellation state
-        let cancellation_state = Arc::new(CancellationState::new());
-        self.cancellation_state = Some(Arc::clone(&cancellation_state));
+        }
+    }
 
+    /// Run an optimization job and show its result
+    fn run_job(&mut self, params: OptimizationParams, cx: &mut Context<Self>) {
         // Update status to Running
         self.optimization_status = OptimizationStatus::Running;
         self.current_iteration = 0;
@@ -301,30 +362,119 @@ impl EQDesignComponent {
         // A future version can improve this with proper async/await patterns
         log::info!("[EQDesign] Starting optimization (blocking UI temporarily)");
         
-        // Run in blocking context
-        let result = cx.background_executor().block(run_optimization_internal(
-            params,
-            progress_callback,
-            cancellation_state,
-        ));
-        
+        // Run as a job and block until it finishes
+        let job_id = self.job_manager.submit(params, progress_callback);
+        self.current_job = Some(job_id);
+        let result = cx.background_executor().block(self.job_manager.wait(job_id));
+        self.job_manager.remove(job_id);
+
         // Update component with result
-        self.cancellation_state = None;
+        self.current_job = None;
         match result {
-            Ok(opt_result) => {
+            Some(opt_result) if opt_result.success => {
                 log::info!("[EQDesign] Optimization completed successfully");
                 self.optimization_status = OptimizationStatus::Success(opt_result);
             }
-            Err(e) => {
-                log::error!("[EQDesign] Optimization failed: {}", e);
-                self.optimization_status = OptimizationStatus::Error(e.to_string());
+            Some(opt_result) => {
+                let message = opt_result
+                    .error_message
+                    .unwrap_or_else(|| "Optimization failed".to_string());
+                log::error!("[EQDesign] Optimization failed: {}", message);
+                self.optimization_status = OptimizationStatus::Error(message);
+            }
+            None => {
+                log::error!("[EQDesign] Optimization job {} was lost", job_id);
+                self.optimization_status =
+                    OptimizationStatus::Error(format!("Optimization job {} was lost", job_id));
             }
         }
         cx.notify();
     }
 
+    /// Input showing a numeric parameter, outlined when validation rejected it
+    fn param_field(&self, field: &str, value: impl Into<SharedString>) -> Div {
+        let invalid = self.invalid_fields.iter().any(|f| f == field);
+        components::input_field()
+            .child(value.into())
+            .when(invalid, |d| d.border_color(colors::danger()))
+    }
+
+    /// Dropdown listing the backend choices for a field
+    fn choice_dropdown(
+        &self,
+        field: ChoiceField,
+        current: &'static str,
+        cx: &mut Context<Self>,
+    ) -> Div {
+        let options: &[ChoiceInfo] = match field {
+            ChoiceField::Loss => &self.choices.losses,
+            ChoiceField::PeqModel => &self.choices.peq_models,
+            ChoiceField::Algorithm => &self.choices.algorithms,
+        };
+        let current_label = options
+            .iter()
+            .find(|c| c.value == current)
+            .map_or(current, |c| c.label);
+
+        div()
+            .flex()
+            .flex_col()
+            .gap(spacing::XS)
+            .child(
+                components::input_field()
+                    .cursor_pointer()
+                    .hover(|s| s.bg(colors::hover_bg()))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            this.toggle_dropdown(field, cx);
+                        }),
+                    )
+                    .child(format!("{} ▾", current_label)),
+            )
+            .when(self.open_dropdown == Some(field), |d| {
+                d.child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .rounded(RADIUS)
+                        .border_1()
+                        .border_color(colors::select_border())
+                        .bg(colors::select_bg())
+                        .children(options.iter().map(|choice| {
+                            let value = choice.value;
+                            div()
+                                .px(spacing::MD)
+                                .py(spacing::XS)
+                                .cursor_pointer()
+                                .hover(|s| s.bg(colors::hover_bg()))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |this, _, _, cx| {
+                                        this.select_choice(field, value, cx);
+                                    }),
+                                )
+                                .child(
+                                    div()
+                                        .text_color(colors::text_primary())
+                                        .when(value == current, |d| {
+                                            d.font_weight(FontWeight::SEMIBOLD)
+                                        })
+                                        .child(choice.label),
+                                )
+                                .child(
+                                    div()
+                                        .text_size(fonts::SIZE_SM)
+                                        .text_color(colors::text_secondary())
+                                        .child(choice.description),
+                                )
+                        })),
+                )
+            })
+    }
+
     /// Render EQ Design section
-    fn render_eq_design_section(&self, _cx: &mut Context<Self>) -> Div {
+    fn render_eq_design_section(&self, cx: &mut Context<Self>) -> Div {
         div()
             .section_group()
             .child(components::section_header("EQ Design"))
@@ -346,16 +496,11 @@ impl EQDesignComponent {
                                     .gap(spacing::XS)
                                     .flex_1()
                                     .child(components::label("Loss Function"))
-                                    .child(
-                                        div()
-                                            .px(spacing::MD)
-                                            .py(spacing::SM)
-                                            .rounded(RADIUS)
-                                            .border_1()
-                                            .border_color(colors::border())
-                                            .bg(colors::bg_secondary())
-                                            .child(self.loss.clone()),
-                                    ),
+                                    .child(self.choice_dropdown(
+                                        ChoiceField::Loss,
+                                        self.loss.as_str(),
+                                        cx,
+                                    )),
                             )
                             .child(
                                 div()
@@ -390,14 +535,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Filters"))
                                     .child(
-                                        div()
-                                            .px(spacing::MD)
-                                            .py(spacing::SM)
-                                            .rounded(RADIUS)
-                                            .border_1()
-                                            .border_color(colors::border())
-                                            .bg(colors::bg_secondary())
-                                            .child(self.num_filters.to_string()),
+                                        self.param_field(
+                                            "num_filters",
+                                            self.num_filters.to_string(),
+                                        ),
                                     ),
                             )
                             .child(
@@ -408,14 +549,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Sample Rate"))
                                     .child(
-                                        div()
-                                            .px(spacing::MD)
-                                            .py(spacing::SM)
-                                            .rounded(RADIUS)
-                                            .border_1()
-                                            .border_color(colors::border())
-                                            .bg(colors::bg_secondary())
-                                            .child(self.sample_rate.to_string()),
+                                        self.param_field(
+                                            "sample_rate",
+                                            self.sample_rate.to_string(),
+                                        ),
                                     ),
                             ),
                     )
@@ -433,7 +570,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Min dB"))
                                     .child(
-                                        components::input_field().child(self.min_db.to_string()),
+                                        self.param_field("min_db", self.min_db.to_string()),
                                     ),
                             )
                             .child(
@@ -444,7 +581,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Max dB"))
                                     .child(
-                                        components::input_field().child(self.max_db.to_string()),
+                                        self.param_field("max_db", self.max_db.to_string()),
                                     ),
                             ),
                     )
@@ -461,7 +598,7 @@ impl EQDesignComponent {
                                     .gap(spacing::XS)
                                     .flex_1()
                                     .child(components::label("Min Q"))
-                                    .child(components::input_field().child(self.min_q.to_string())),
+                                    .child(self.param_field("min_q", self.min_q.to_string())),
                             )
                             .child(
                                 div()
@@ -470,7 +607,7 @@ impl EQDesignComponent {
                                     .gap(spacing::XS)
                                     .flex_1()
                                     .child(components::label("Max Q"))
-                                    .child(components::input_field().child(self.max_q.to_string())),
+                                    .child(self.param_field("max_q", self.max_q.to_string())),
                             ),
                     )
                     // Frequency Range row
@@ -487,7 +624,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Min Freq"))
                                     .child(
-                                        components::input_field().child(self.min_freq.to_string()),
+                                        self.param_field("min_freq", self.min_freq.to_string()),
                                     ),
                             )
                             .child(
@@ -498,7 +635,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Max Freq"))
                                     .child(
-                                        components::input_field().child(self.max_freq.to_string()),
+                                        self.param_field("max_freq", self.max_freq.to_string()),
                                     ),
                             ),
                     )
@@ -509,7 +646,11 @@ impl EQDesignComponent {
                             .flex_col()
                             .gap(spacing::XS)
                             .child(components::label("PEQ Model"))
-                            .child(components::input_field().child(self.peq_model.clone())),
+                            .child(self.choice_dropdown(
+                                ChoiceField::PeqModel,
+                                self.peq_model.as_str(),
+                                cx,
+                            )),
                     )
                     // Spacing parameters
                     .child(
@@ -525,8 +666,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Min Spacing (oct)"))
                                     .child(
-                                        components::input_field()
-                                            .child(self.min_spacing_oct.to_string()),
+                                        self.param_field(
+                                            "min_spacing_oct",
+                                            self.min_spacing_oct.to_string(),
+                                        ),
                                     ),
                             )
                             .child(
@@ -537,8 +680,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Spacing Weight"))
                                     .child(
-                                        components::input_field()
-                                            .child(self.spacing_weight.to_string()),
+                                        self.param_field(
+                                            "spacing_weight",
+                                            self.spacing_weight.to_string(),
+                                        ),
                                     ),
                             ),
                     ),
@@ -546,8 +691,8 @@ impl EQDesignComponent {
     }
 
     /// Render Optimization Fine Tuning section
-    fn render_optimization_section(&self, _cx: &mut Context<Self>) -> Div {
-        let is_de_algo = self.algo == "autoeq:de";
+    fn render_optimization_section(&self, cx: &mut Context<Self>) -> Div {
+        let is_de_algo = self.algo == Algorithm::AutoeqDe;
 
         div()
             .section_group()
@@ -564,7 +709,11 @@ impl EQDesignComponent {
                             .flex_col()
                             .gap(spacing::XS)
                             .child(components::label("Algorithm"))
-                            .child(components::input_field().child(self.algo.clone())),
+                            .child(self.choice_dropdown(
+                                ChoiceField::Algorithm,
+                                self.algo.as_str(),
+                                cx,
+                            )),
                     )
                     // Population and MaxEval
                     .child(
@@ -580,8 +729,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Population"))
                                     .child(
-                                        components::input_field()
-                                            .child(self.population.to_string()),
+                                        self.param_field("population", self.population.to_string()),
                                     ),
                             )
                             .child(
@@ -592,7 +740,7 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Max Eval"))
                                     .child(
-                                        components::input_field().child(self.maxeval.to_string()),
+                                        self.param_field("maxeval", self.maxeval.to_string()),
                                     ),
                             ),
                     )
@@ -619,7 +767,7 @@ impl EQDesignComponent {
                                         .flex_1()
                                         .child(components::label("F (Mutation)"))
                                         .child(
-                                            components::input_field().child(self.de_f.to_string()),
+                                            self.param_field("de_f", self.de_f.to_string()),
                                         ),
                                 )
                                 .child(
@@ -630,7 +778,7 @@ impl EQDesignComponent {
                                         .flex_1()
                                         .child(components::label("CR (Recombination)"))
                                         .child(
-                                            components::input_field().child(self.de_cr.to_string()),
+                                            self.param_field("de_cr", self.de_cr.to_string()),
                                         ),
                                 ),
                         )
@@ -649,8 +797,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Tolerance"))
                                     .child(
-                                        components::input_field()
-                                            .child(format!("{:.0e}", self.tolerance)),
+                                        self.param_field(
+                                            "tolerance",
+                                            format!("{:.0e}", self.tolerance),
+                                        ),
                                     ),
                             )
                             .child(
@@ -661,8 +811,10 @@ impl EQDesignComponent {
                                     .flex_1()
                                     .child(components::label("Abs Tolerance"))
                                     .child(
-                                        components::input_field()
-                                            .child(format!("{:.0e}", self.atolerance)),
+                                        self.param_field(
+                                            "atolerance",
+                                            format!("{:.0e}", self.atolerance),
+                                        ),
                                     ),
                             ),
                     )
@@ -718,8 +870,7 @@ impl EQDesignComponent {
                                         .w(px(150.0))
                                         .child(components::label("Smooth 1/N octave"))
                                         .child(
-                                            components::input_field()
-                                                .child(self.smooth_n.to_string()),
+                                            self.param_field("smooth_n", self.smooth_n.to_string()),
                                         ),
                                 )
                             }),
@@ -835,6 +986,16 @@ impl Render for EQDesignComponent {
                             )
                         }),
                     )
+                    .when(!matches!(self.optimization_status, OptimizationStatus::Running), |parent| {
+                        parent.child(
+                            components::secondary_button("Quick Preview").on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|this, _, _, cx| {
+                                    this.preview_optimization(cx);
+                                }),
+                            ),
+                        )
+                    })
                     .when(matches!(self.optimization_status, OptimizationStatus::Running), |parent| {
                         parent.child(
                             components::secondary_button("Cancel").on_mouse_down(
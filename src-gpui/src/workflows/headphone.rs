@@ -3,7 +3,7 @@ use crate::components::eq_design::OptimizationStatus;
 use crate::components::eq_design::EQDesignComponent;
 use crate::components::filter_display::FilterDisplayComponent;
 use crate::components::frequency_plot::FrequencyPlotComponent;
-use autoeq_backend::export::{export_filters, ExportFormat, FilterParam as ExportFilterParam};
+use autoeq_backend::export::{export_filters, filters_for_export, ExportFormat};
 use autoeq_backend::{CurveData, OptimizationResult};
 use gpui::prelude::FluentBuilder;
 use gpui::*;
@@ -137,13 +137,9 @@ impl HeadphoneWorkflow {
             // Store the full result
             self.optimization_result = Some(result.clone());
 
-            // Convert filter parameters for display
-            if let Some(raw_params) = &result.filter_params {
-                let filters = self.convert_raw_params_to_filters(
-                    raw_params,
-                    raw_params.len() / 3,  // num_filters
-                    "pk",  // Default to pk model
-                );
+            // Show the filters decoded by the backend
+            if let Some(decoded) = &result.filters {
+                let filters = filters_for_export(decoded);
 
                 // Update filter display
                 self.filter_display.update(cx, |display, cx| {
@@ -365,55 +361,11 @@ impl HeadphoneWorkflow {
         })
     }
 
-    fn convert_raw_params_to_filters(
-        &self,
-        raw_params: &[f64],
-        num_filters: usize,
-        peq_model: &str,
-    ) -> Vec<ExportFilterParam> {
-        let mut filters = Vec::new();
-
-        for i in 0..num_filters {
-            let base_idx = i * 3;
-            if base_idx + 2 >= raw_params.len() {
-                break;
-            }
-
-            // Extract parameters (3 values per filter in log-space)
-            let log_freq = raw_params[base_idx];
-            let q = raw_params[base_idx + 1];
-            let gain = raw_params[base_idx + 2];
-
-            // Convert log frequency to linear: 10^log_freq
-            let frequency = 10_f64.powf(log_freq);
-
-            // Determine filter type based on PEQ model and position
-            let filter_type = match peq_model {
-                "hp-pk" if i == 0 => "HP",
-                "hp-pk-lp" if i == 0 => "HP",
-                "hp-pk-lp" if i == num_filters - 1 => "LP",
-                _ => "PK", // Default to peak filter
-            };
-
-            filters.push(ExportFilterParam {
-                filter_type: filter_type.to_string(),
-                frequency,
-                gain,
-                q,
-            });
-        }
-
-        // Sort filters by frequency for better readability
-        filters.sort_by(|a, b| a.frequency.partial_cmp(&b.frequency).unwrap());
-
-        filters
-    }
-
     fn export_filters(&self, format: ExportFormat) {
-        // Get filter parameters from optimization result
-        let raw_params = match &self.optimization_result {
-            Some(result) => match &result.filter_params {
-                Some(f) => f.clone(),
+        // Get the decoded filters from the optimization result
+        let decoded = match &self.optimization_result {
+            Some(result) => match &result.filters {
+                Some(f) => f,
                 None => {
                     log::warn!("No filter parameters to export");
                     return;
@@ -425,9 +377,8 @@ impl HeadphoneWorkflow {
             }
         };
 
-        // Convert raw parameters to FilterParam structs
-        let num_filters = raw_params.len() / 3;
-        let export_filter_params = self.convert_raw_params_to_filters(&raw_params, num_filters, "pk");
+        // Export exactly the filter types that were optimized
+        let export_filter_params = filters_for_export(decoded);
 
         // Generate export content
         let content = match export_filters(&export_filter_params, format, 48000) {
@@ -1,4 +1,7 @@
-use autoeq_backend::{CurveData, OptimizationParams, OptimizationResult};
+use autoeq_backend::{
+    Algorithm, CurveAggregate, CurveData, FrequencyGrid, Loss, OptimizationParams,
+    OptimizationResult, PeqModel,
+};
 use gpui::*;
 use gpui::prelude::*;
 
@@ -7,7 +10,7 @@ pub struct RoomWorkflow {
     room_measurement: Option<CurveData>,
     corrected_response: Option<CurveData>,
     num_filters: usize,
-    algorithm: String,
+    algorithm: Algorithm,
     population: usize,
     maxeval: usize,
     optimization_result: Option<OptimizationResult>,
@@ -24,7 +27,7 @@ impl RoomWorkflow {
             room_measurement: None,
             corrected_response: None,
             num_filters: 10, // Rooms typically need more filters for modes
-            algorithm: "nlopt:cobyla".to_string(),
+            algorithm: Algorithm::NloptCobyla,
             population: 300,
             maxeval: 2000,
             optimization_result: None,
@@ -130,7 +133,7 @@ impl RoomWorkflow {
             version: None,
             measurement: None,
             curve_name: "Listening Window".to_string(),
-            algo: self.algorithm.clone(),
+            algo: self.algorithm,
             population: self.population,
             maxeval: self.maxeval,
             refine: false,
@@ -139,8 +142,8 @@ impl RoomWorkflow {
             spacing_weight: 10.0,
             smooth: true,
             smooth_n: 3,
-            loss: "flat".to_string(),
-            peq_model: Some("pk".to_string()),
+            loss: Loss::SpeakerFlat,
+            peq_model: PeqModel::Pk,
             strategy: Some("currenttobest1bin".to_string()),
             de_f: Some(0.8),
             de_cr: Some(0.9),
@@ -152,23 +155,29 @@ impl RoomWorkflow {
             captured_magnitudes: Some(curve.spl),
             target_frequencies: None,
             target_magnitudes: None,
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            // Room modes need a dense grid for their high-Q filters
+            frequency_grid: FrequencyGrid {
+                points_per_octave: 48,
+                ..FrequencyGrid::default()
+            },
         };
 
         // Note: Backend run_optimization requires additional parameters like progress callback and cancellation state
         // For now, we'll create a placeholder result until we implement the full integration
-        let result = OptimizationResult {
-            success: false,
-            error_message: Some("Backend integration in progress - placeholder result".to_string()),
-            filter_params: None,
-            objective_value: None,
-            preference_score_before: None,
-            preference_score_after: None,
-            filter_response: None,
-            spin_details: None,
-            filter_plots: None,
-            input_curve: None,
-            deviation_curve: None,
-        };
+        let result =
+            OptimizationResult::failure("Backend integration in progress - placeholder result");
 
         self.optimization_result = Some(result);
     }
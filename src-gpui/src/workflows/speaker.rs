@@ -1,5 +1,8 @@
 use autoeq_backend::spinorama_api::SpinAudioClient;
-use autoeq_backend::{CurveData, OptimizationParams, OptimizationResult};
+use autoeq_backend::{
+    Algorithm, CurveAggregate, CurveData, FrequencyGrid, Loss, OptimizationParams,
+    OptimizationResult, PeqModel,
+};
 use gpui::*;
 use gpui::prelude::*;
 
@@ -9,7 +12,7 @@ pub struct SpeakerWorkflow {
     selected_measurement: Option<String>,
     measurement_curve: Option<CurveData>,
     num_filters: usize,
-    algorithm: String,
+    algorithm: Algorithm,
     population: usize,
     maxeval: usize,
     optimization_result: Option<OptimizationResult>,
@@ -27,7 +30,7 @@ impl SpeakerWorkflow {
             selected_measurement: None,
             measurement_curve: None,
             num_filters: 7,
-            algorithm: "nlopt:cobyla".to_string(),
+            algorithm: Algorithm::NloptCobyla,
             population: 300,
             maxeval: 2000,
             optimization_result: None,
@@ -110,7 +113,7 @@ impl SpeakerWorkflow {
             version: None,
             measurement: None,
             curve_name: "Listening Window".to_string(),
-            algo: self.algorithm.clone(),
+            algo: self.algorithm,
             population: self.population,
             maxeval: self.maxeval,
             refine: false,
@@ -119,8 +122,8 @@ impl SpeakerWorkflow {
             spacing_weight: 20.0,
             smooth: true,
             smooth_n: 1,
-            loss: "flat".to_string(),
-            peq_model: Some("pk".to_string()),
+            loss: Loss::SpeakerFlat,
+            peq_model: PeqModel::Pk,
             strategy: Some("currenttobest1bin".to_string()),
             de_f: Some(0.8),
             de_cr: Some(0.9),
@@ -132,23 +135,25 @@ impl SpeakerWorkflow {
             captured_magnitudes: Some(curve.spl),
             target_frequencies: None,
             target_magnitudes: None,
+            captured_measurements: Vec::new(),
+            curve_aggregate: CurveAggregate::Mean,
+            seed: None,
+            parallel_threads: 0,
+            no_parallel: false,
+            initial_filters: None,
+            greedy_init: false,
+            pinned_filters: Vec::new(),
+            max_boost_db: None,
+            device_profile: None,
+            loss_weighting: None,
+            boost_penalty: 0.0,
+            frequency_grid: FrequencyGrid::default(),
         };
 
         // Note: Backend run_optimization requires additional parameters like progress callback and cancellation state
         // For now, we'll create a placeholder result until we implement the full integration
-        let result = OptimizationResult {
-            success: false,
-            error_message: Some("Backend integration in progress - placeholder result".to_string()),
-            filter_params: None,
-            objective_value: None,
-            preference_score_before: None,
-            preference_score_after: None,
-            filter_response: None,
-            spin_details: None,
-            filter_plots: None,
-            input_curve: None,
-            deviation_curve: None,
-        };
+        let result =
+            OptimizationResult::failure("Backend integration in progress - placeholder result");
 
         self.optimization_result = Some(result);
     }
@@ -447,7 +452,7 @@ impl SpeakerWorkflow {
                                     .rounded(px(4.0))
                                     .border_1()
                                     .border_color(rgb(0xcccccc))
-                                    .child(self.algorithm.clone()),
+                                    .child(self.algorithm.label()),
                             ),
                     ),
             )
@@ -9,13 +9,17 @@ use tauri::{AppHandle, Emitter, State};
 
 // Import from autoeq_backend
 use autoeq_backend::camilla::ChannelMapMode;
-use autoeq_backend::optim::{ProgressCallback, ProgressUpdate, run_optimization_internal};
+use autoeq_backend::optim::{
+    JobId, JobInfo, JobManager, OptimizationPhase, ProgressCallback, ProgressUpdate,
+};
 use autoeq_backend::plot::{PlotFiltersParams, PlotSpinParams, plot_to_json};
 use autoeq_backend::{
-    AudioManager, CancellationState, OptimizationParams, OptimizationResult, SharedAudioState,
-    audio, curve_data_to_curve,
+    AudioManager, CancellationToken, DeviceProfile, MeterReading, OptimizationChoices,
+    OptimizationParams, OptimizationResult, SharedAudioState, SweepOptions, SweepResult,
+    ValidationError, audio, curve_data_to_curve, device_profiles, optimization_choices,
+    validate_params,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, broadcast};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -114,13 +118,28 @@ impl ProgressCallback for TauriProgressCallback {
             }
         }
     }
+
+    fn on_phase(&self, phase: OptimizationPhase) {
+        if let Err(e) = self.app_handle.emit("optimization_phase", phase) {
+            eprintln!("Failed to emit optimization phase: {}", e);
+        }
+    }
+}
+
+// Jobs submitted with submit_optimization report progress through get_job_status
+struct JobStatusProgressCallback;
+
+impl ProgressCallback for JobStatusProgressCallback {
+    fn on_progress(&self, _update: ProgressUpdate) -> bool {
+        true
+    }
 }
 
 #[tauri::command]
 async fn run_optimization(
     params: OptimizationParams,
     app_handle: AppHandle,
-    cancellation_state: State<'_, CancellationState>,
+    job_manager: State<'_, JobManager>,
 ) -> Result<OptimizationResult, String> {
     println!(
         "[RUST DEBUG] run_optimization called with algo: {}",
@@ -131,41 +150,100 @@ async fn run_optimization(
         params.num_filters, params.population, params.maxeval
     );
 
-    // Reset cancellation state at the start of optimization
-    cancellation_state.reset();
-
     // Create progress callback
     let progress_callback = Arc::new(TauriProgressCallback { app_handle });
 
-    let result = run_optimization_internal(
-        params,
-        progress_callback,
-        Arc::new((*cancellation_state).clone()),
-    )
-    .await;
+    // Run as a job and wait for it; cancel_optimization cancels it
+    let job_id = job_manager.submit(params, progress_callback);
+    let result = job_manager.wait(job_id).await;
+    job_manager.remove(job_id);
+
+    let result = result.ok_or_else(|| format!("Optimization job {} was lost", job_id))?;
+    if result.success {
+        println!("[RUST DEBUG] Optimization completed successfully");
+    } else {
+        println!(
+            "[RUST DEBUG] Optimization failed with error: {}",
+            result.error_message.as_deref().unwrap_or("unknown error")
+        );
+    }
+    Ok(result)
+}
 
-    match result {
-        Ok(res) => {
-            println!("[RUST DEBUG] Optimization completed successfully");
-            Ok(res)
-        }
-        Err(e) => {
-            println!("[RUST DEBUG] Optimization failed with error: {}", e);
-            Ok(OptimizationResult {
-                success: false,
-                error_message: Some(e.to_string()),
-                filter_params: None,
-                objective_value: None,
-                preference_score_before: None,
-                preference_score_after: None,
-                filter_response: None,
-                spin_details: None,
-                filter_plots: None,
-                input_curve: None,
-                deviation_curve: None,
-            })
-        }
+#[tauri::command]
+async fn run_filter_sweep(
+    params: OptimizationParams,
+    options: SweepOptions,
+    job_manager: State<'_, JobManager>,
+) -> Result<SweepResult, String> {
+    println!(
+        "[RUST DEBUG] run_filter_sweep called for {}-{} filters",
+        options.min_filters, options.max_filters
+    );
+    // Every configuration runs as a job, so cancel_optimization stops the sweep
+    autoeq_backend::sweep::run_sweep(&job_manager, &params, &options, CancellationToken::new())
+        .await
+}
+
+#[tauri::command]
+fn submit_optimization(params: OptimizationParams, job_manager: State<JobManager>) -> JobId {
+    println!(
+        "[RUST DEBUG] submit_optimization called with algo: {}",
+        params.algo
+    );
+    job_manager.submit(params, Arc::new(JobStatusProgressCallback))
+}
+
+#[tauri::command]
+fn get_job_status(job_id: JobId, job_manager: State<JobManager>) -> Result<JobInfo, String> {
+    job_manager
+        .status(job_id)
+        .ok_or_else(|| format!("Unknown optimization job {}", job_id))
+}
+
+#[tauri::command]
+fn list_optimization_jobs(job_manager: State<JobManager>) -> Vec<JobInfo> {
+    job_manager.list()
+}
+
+/// Result of a finished job; the job is forgotten once its result is fetched
+#[tauri::command]
+fn get_job_result(
+    job_id: JobId,
+    job_manager: State<JobManager>,
+) -> Result<Option<OptimizationResult>, String> {
+    let status = job_manager
+        .status(job_id)
+        .ok_or_else(|| format!("Unknown optimization job {}", job_id))?;
+    if !status.state.is_finished() {
+        return Ok(None);
     }
+    Ok(job_manager.remove(job_id))
+}
+
+#[tauri::command]
+fn cancel_job(job_id: JobId, job_manager: State<JobManager>) -> Result<bool, String> {
+    println!("[RUST DEBUG] Cancellation requested for job {}", job_id);
+    Ok(job_manager.cancel(job_id))
+}
+
+#[tauri::command]
+fn get_optimization_choices() -> OptimizationChoices {
+    optimization_choices()
+}
+
+#[tauri::command]
+fn list_device_profiles() -> Vec<DeviceProfile> {
+    device_profiles()
+}
+
+/// Every problem with `params`, empty when they are valid
+#[tauri::command]
+fn validate_optimization_params(params: OptimizationParams) -> Vec<ValidationError> {
+    validate_params(&params)
+        .err()
+        .map(|e| e.0)
+        .unwrap_or_default()
 }
 
 #[tauri::command]
@@ -202,13 +280,7 @@ async fn generate_plot_filters(params: PlotFiltersParams) -> Result<serde_json::
         smooth: true,
         smooth_n: 2,
         loss: LossType::SpeakerFlat,
-        peq_model: match params.peq_model.as_deref() {
-            Some("hp-pk") => autoeq::cli::PeqModel::HpPk,
-            Some("hp-pk-lp") => autoeq::cli::PeqModel::HpPkLp,
-            Some("free-pk-free") => autoeq::cli::PeqModel::FreePkFree,
-            Some("free") => autoeq::cli::PeqModel::Free,
-            Some("pk") | _ => autoeq::cli::PeqModel::Pk,
-        },
+        peq_model: params.peq_model.into(),
         peq_model_list: false,
         algo_list: false,
         tolerance: 1e-3,
@@ -311,10 +383,11 @@ fn exit_app(window: tauri::Window) {
     window.close().unwrap();
 }
 
+/// Cancel every queued and running optimization job
 #[tauri::command]
-fn cancel_optimization(cancellation_state: State<CancellationState>) -> Result<(), String> {
+fn cancel_optimization(job_manager: State<JobManager>) -> Result<(), String> {
     println!("[RUST DEBUG] Cancellation requested");
-    cancellation_state.cancel();
+    job_manager.cancel_all();
     Ok(())
 }
 
@@ -323,7 +396,7 @@ fn cancel_optimization(cancellation_state: State<CancellationState>) -> Result<(
 // ============================================================================
 
 use autoeq_backend::audio::{AudioConfig, AudioDevice};
-use autoeq_backend::{AudioState, AudioStreamState, FilterParams};
+use autoeq_backend::{AudioState, AudioStreamState, FilterParams, SharedAudioStreamState};
 use std::path::PathBuf;
 
 // ============================================================================
@@ -584,6 +657,79 @@ async fn audio_get_signal_peak(
         .map_err(|e| format!("{}", e))
 }
 
+#[tauri::command]
+async fn audio_set_meter_interval(
+    interval_ms: u64,
+    audio_manager: State<'_, Mutex<AudioManager>>,
+) -> Result<(), String> {
+    let manager = audio_manager.lock().await;
+    manager
+        .set_meter_interval(std::time::Duration::from_millis(interval_ms))
+        .map_err(|e| format!("{}", e))
+}
+
+#[tauri::command]
+async fn audio_set_looping(
+    looping: bool,
+    audio_manager: State<'_, Mutex<AudioManager>>,
+) -> Result<(), String> {
+    let manager = audio_manager.lock().await;
+    manager.set_looping(looping).map_err(|e| format!("{}", e))
+}
+
+/// Forward the playback position, and the return to idle at the end of the file
+async fn forward_playback(state: SharedAudioStreamState, app_handle: AppHandle) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(250));
+    let mut was_playing = false;
+    loop {
+        ticker.tick().await;
+        let Ok(current) = state.lock().map(|state| state.clone()) else {
+            break;
+        };
+        let playing = current.state == AudioState::Playing;
+        if playing {
+            let _ = app_handle.emit(
+                "audio:position-update",
+                AudioPositionUpdate {
+                    position_seconds: current.position_seconds,
+                    duration_seconds: current.duration_seconds,
+                },
+            );
+        } else if was_playing {
+            let _ = app_handle.emit(
+                "audio:state-changed",
+                AudioStateChanged {
+                    state: audio_state_to_string(current.state),
+                    file: None,
+                    output_device: None,
+                    input_device: None,
+                },
+            );
+        }
+        was_playing = playing;
+    }
+}
+
+/// Forward the level readings to the frontend VU meters
+async fn forward_meter(mut readings: broadcast::Receiver<MeterReading>, app_handle: AppHandle) {
+    loop {
+        match readings.recv().await {
+            Ok(reading) => {
+                let _ = app_handle.emit(
+                    "audio:signal-peak",
+                    AudioSignalPeak {
+                        peak: reading.playback_peak_max(),
+                    },
+                );
+                let _ = app_handle.emit("audio:meter", reading);
+            }
+            // Readings missed while the frontend was busy are stale anyway
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
 // ============================================================================
 // Audio Device Management Commands (Tauri wrappers for backend functions)
 // ============================================================================
@@ -629,18 +775,35 @@ pub fn run() {
     });
 
     // Create AudioManager (wrapped in Mutex for Tauri state)
-    let audio_manager = Mutex::new(AudioManager::new(camilla_binary));
+    let audio_manager = AudioManager::new(camilla_binary);
+    let meter = audio_manager.subscribe_meter();
+    let playback = audio_manager.shared_state();
+    let audio_manager = Mutex::new(audio_manager);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(CancellationState::new())
+        .manage(JobManager::default())
         .manage(SharedAudioState::default())
         .manage(audio_manager)
+        .setup(move |app| {
+            tauri::async_runtime::spawn(forward_meter(meter, app.handle().clone()));
+            tauri::async_runtime::spawn(forward_playback(playback, app.handle().clone()));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             run_optimization,
             cancel_optimization,
+            run_filter_sweep,
+            submit_optimization,
+            get_job_status,
+            list_optimization_jobs,
+            get_job_result,
+            cancel_job,
+            get_optimization_choices,
+            list_device_profiles,
+            validate_optimization_params,
             get_speakers,
             get_speaker_versions,
             get_speaker_measurements,
@@ -659,7 +822,9 @@ pub fn run() {
             audio_get_state,
             audio_start_recording,
             audio_stop_recording,
-            audio_get_signal_peak
+            audio_get_signal_peak,
+            audio_set_meter_interval,
+            audio_set_looping
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -10,9 +10,25 @@ import {
   LayoutManager,
   generateAppHTML,
 } from "./modules";
-import { OptimizationParams, OptimizationResult } from "./types";
+import {
+  OptimizationParams,
+  OptimizationResult,
+  ValidationError,
+} from "./types";
 import { AutoEQPlotAPI, PlotFiltersParams, PlotSpinParams } from "./types";
 
+// Web Audio type of a backend filter type, see FilterType in filters.rs
+const WEB_AUDIO_FILTER_TYPES: Record<string, BiquadFilterType> = {
+  peak: "peaking",
+  lowpass: "lowpass",
+  highpass: "highpass",
+  highpass_variable_q: "highpass",
+  lowshelf: "lowshelf",
+  highshelf: "highshelf",
+  bandpass: "bandpass",
+  notch: "notch",
+};
+
 class AutoEQApplication {
   private uiManager: UIManager;
   private plotManager: PlotManager;
@@ -275,6 +291,11 @@ class AutoEQApplication {
 
   private async initialize(): Promise<void> {
     try {
+      // Dropdowns list what the backend accepts
+      this.uiManager.setOptimizationChoices(
+        await this.apiManager.loadOptimizationChoices(),
+      );
+
       // Initialize UI state
       this.uiManager.resetToDefaults();
       this.uiManager.setEQEnabled(false);
@@ -323,6 +344,16 @@ class AutoEQApplication {
       const params =
         await this.optimizationManager.extractOptimizationParams(formData);
 
+      // Check the limits in the backend and flag the offending inputs
+      const errors = await this.apiManager.validateWithBackend(params);
+      this.markInvalidFields(errors);
+      if (errors.length > 0) {
+        this.uiManager.showError(
+          "Validation errors:\n" + errors.map((e) => e.message).join("\n"),
+        );
+        return;
+      }
+
       console.log("Starting optimization with parameters:", params);
 
       // Update UI state
@@ -344,6 +375,17 @@ class AutoEQApplication {
     }
   }
 
+  // Flag each form input named after a failed field, clear the others
+  private markInvalidFields(errors: ValidationError[]): void {
+    const form = this.uiManager.getForm();
+    for (const element of Array.from(form.elements)) {
+      if (element instanceof HTMLInputElement) {
+        const error = errors.find((e) => e.field === element.name);
+        element.setCustomValidity(error ? error.message : "");
+      }
+    }
+  }
+
   private async handleOptimizationSuccess(
     result: OptimizationResult,
   ): Promise<void> {
@@ -404,20 +446,15 @@ class AutoEQApplication {
         );
       }
 
-      // Update audio player with new filter parameters
-      if (result.filter_params) {
-        const filterParams: FilterParam[] = [];
-        for (let i = 0; i < result.filter_params.length; i += 3) {
-          // Convert frequency from log space to linear space
-          const logFreq = result.filter_params[i];
-          const linearFreq = Math.pow(10, logFreq);
-          filterParams.push({
-            frequency: linearFreq,
-            q: result.filter_params[i + 1],
-            gain: result.filter_params[i + 2],
-            enabled: true,
-          });
-        }
+      // Preview the filters that were optimized and exported, fixed ones included
+      if (result.filters) {
+        const filterParams: FilterParam[] = result.filters.map((filter) => ({
+          type: WEB_AUDIO_FILTER_TYPES[filter.filter_type] ?? "peaking",
+          frequency: filter.frequency,
+          q: filter.q,
+          gain: filter.gain,
+          enabled: true,
+        }));
         this.audioPlayer?.updateFilterParams(filterParams);
         this.audioPlayer?.setEQEnabled(true);
       }
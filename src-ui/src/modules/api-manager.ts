@@ -2,6 +2,11 @@
 
 import { invoke } from "@tauri-apps/api/core";
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
+import type {
+  OptimizationChoices,
+  OptimizationParams,
+  ValidationError,
+} from "../types/optimization";
 
 export interface SpeakerData {
   name: string;
@@ -840,7 +845,22 @@ export class APIManager {
     }
   }
 
+  // Dropdown values of loss, algorithm and PEQ model, listed by the backend
+  async loadOptimizationChoices(): Promise<OptimizationChoices> {
+    return (await invoke("get_optimization_choices")) as OptimizationChoices;
+  }
+
   // Validation helpers
+
+  // Numeric limits are checked by the backend, the single source of truth
+  async validateWithBackend(
+    params: OptimizationParams,
+  ): Promise<ValidationError[]> {
+    return (await invoke("validate_optimization_params", {
+      params,
+    })) as ValidationError[];
+  }
+
   validateOptimizationParams(formData: FormData): {
     isValid: boolean;
     errors: string[];
@@ -853,63 +873,7 @@ export class APIManager {
       console.log(`  ${key}: ${value}`);
     }
 
-    // Helper function to get and validate numeric values with defaults
-    const getNumericValue = (
-      key: string,
-      defaultValue: number,
-      min?: number,
-      max?: number,
-    ): number => {
-      const str = formData.get(key) as string;
-      if (!str || str.trim() === "") {
-        console.log(
-          `${key}: using default value ${defaultValue} (form value was empty)`,
-        );
-        return defaultValue;
-      }
-      const value = parseFloat(str);
-      if (isNaN(value)) {
-        console.log(
-          `${key}: using default value ${defaultValue} (form value "${str}" was not a number)`,
-        );
-        return defaultValue;
-      }
-      return value;
-    };
-
-    // Use default values if form elements don't exist (using HTML form field names)
-    const numFilters = getNumericValue("num_filters", 5);
-    if (numFilters < 1 || numFilters > 20) {
-      errors.push("Number of filters must be between 1 and 20");
-    }
-
-    const sampleRate = getNumericValue("sample_rate", 48000);
-    if (sampleRate < 8000 || sampleRate > 192000) {
-      errors.push("Sample rate must be between 8000 and 192000 Hz");
-    }
-
-    const maxDb = getNumericValue("max_db", 6.0);
-    const minDb = getNumericValue("min_db", -1.0);
-    if (maxDb <= minDb) {
-      errors.push("Max dB must be greater than Min dB");
-    }
-
-    const maxQ = getNumericValue("max_q", 10);
-    const minQ = getNumericValue("min_q", 0.1);
-    if (maxQ <= minQ || minQ <= 0) {
-      errors.push(
-        "Max Q must be greater than Min Q, and Min Q must be positive",
-      );
-    }
-
-    const maxFreq = getNumericValue("max_freq", 20000);
-    const minFreq = getNumericValue("min_freq", 20);
-    if (maxFreq <= minFreq || minFreq <= 0) {
-      errors.push(
-        "Max frequency must be greater than Min frequency, and Min frequency must be positive",
-      );
-    }
-
+    // Only the input selection is checked here, see validateWithBackend
     const inputType = formData.get("input_source") as string;
     if (inputType === "speaker") {
       const speaker = formData.get("speaker") as string;
@@ -34,6 +34,7 @@ export interface AudioStreamState {
   channels: number;
   filters: FilterParams[];
   error_message: string | null;
+  looping: boolean;
 }
 
 // Event payloads
@@ -57,6 +58,17 @@ export interface AudioSignalPeakEvent {
   peak: number;
 }
 
+// Levels in dBFS, one entry per channel
+export interface AudioMeterEvent {
+  capture_peak: number[];
+  capture_rms: number[];
+  playback_peak: number[];
+  playback_rms: number[];
+  clipped_samples: number;
+  clipping: boolean;
+  buffer_level: number;
+}
+
 // ============================================================================
 // Audio Manager Class
 // ============================================================================
@@ -69,9 +81,13 @@ export class AudioManagerRust {
   ) => void)[] = [];
   private errorListeners: ((event: AudioErrorEvent) => void)[] = [];
   private signalPeakListeners: ((event: AudioSignalPeakEvent) => void)[] = [];
+  private meterListeners: ((event: AudioMeterEvent) => void)[] = [];
 
   private stateChangeUnlisten: UnlistenFn | null = null;
+  private positionUpdateUnlisten: UnlistenFn | null = null;
   private errorUnlisten: UnlistenFn | null = null;
+  private signalPeakUnlisten: UnlistenFn | null = null;
+  private meterUnlisten: UnlistenFn | null = null;
 
   constructor() {
     this.setupEventListeners();
@@ -94,6 +110,16 @@ export class AudioManagerRust {
         },
       );
 
+      // Position published by the backend while a file plays
+      this.positionUpdateUnlisten = await listen<AudioPositionUpdateEvent>(
+        "audio:position-update",
+        (event) => {
+          this.positionUpdateListeners.forEach((listener) =>
+            listener(event.payload),
+          );
+        },
+      );
+
       // Listen for error events
       this.errorUnlisten = await listen<AudioErrorEvent>(
         "audio:error",
@@ -103,6 +129,23 @@ export class AudioManagerRust {
         },
       );
 
+      // Level readings pushed by the backend while audio runs
+      this.signalPeakUnlisten = await listen<AudioSignalPeakEvent>(
+        "audio:signal-peak",
+        (event) => {
+          this.signalPeakListeners.forEach((listener) =>
+            listener(event.payload),
+          );
+        },
+      );
+
+      this.meterUnlisten = await listen<AudioMeterEvent>(
+        "audio:meter",
+        (event) => {
+          this.meterListeners.forEach((listener) => listener(event.payload));
+        },
+      );
+
       console.log("[AudioManager] Event listeners registered");
     } catch (error) {
       console.error("[AudioManager] Failed to setup event listeners:", error);
@@ -167,6 +210,19 @@ export class AudioManagerRust {
     };
   }
 
+  /**
+   * Subscribe to per-channel levels, clipping and buffer level (for VU meters)
+   */
+  onMeter(listener: (event: AudioMeterEvent) => void): () => void {
+    this.meterListeners.push(listener);
+    return () => {
+      const index = this.meterListeners.indexOf(listener);
+      if (index > -1) {
+        this.meterListeners.splice(index, 1);
+      }
+    };
+  }
+
   // ============================================================================
   // Public API - Playback Control
   // ============================================================================
@@ -306,6 +362,20 @@ export class AudioManagerRust {
     }
   }
 
+  /**
+   * Set how often the backend pushes level readings
+   */
+  async setMeterInterval(intervalMs: number): Promise<void> {
+    await invoke("audio_set_meter_interval", { intervalMs });
+  }
+
+  /**
+   * Restart the file at its end instead of stopping
+   */
+  async setLooping(looping: boolean): Promise<void> {
+    await invoke("audio_set_looping", { looping });
+  }
+
   /**
    * Start polling signal peak at regular intervals
    * Returns a function to stop polling
@@ -347,15 +417,31 @@ export class AudioManagerRust {
       this.stateChangeUnlisten = null;
     }
 
+    if (this.positionUpdateUnlisten) {
+      this.positionUpdateUnlisten();
+      this.positionUpdateUnlisten = null;
+    }
+
     if (this.errorUnlisten) {
       this.errorUnlisten();
       this.errorUnlisten = null;
     }
 
+    if (this.signalPeakUnlisten) {
+      this.signalPeakUnlisten();
+      this.signalPeakUnlisten = null;
+    }
+
+    if (this.meterUnlisten) {
+      this.meterUnlisten();
+      this.meterUnlisten = null;
+    }
+
     this.stateChangeListeners = [];
     this.positionUpdateListeners = [];
     this.errorListeners = [];
     this.signalPeakListeners = [];
+    this.meterListeners = [];
   }
 }
 
@@ -24,5 +24,6 @@ export {
   type AudioPositionUpdateEvent,
   type AudioErrorEvent,
   type AudioSignalPeakEvent,
+  type AudioMeterEvent,
   AudioState,
 } from "./audio-manager-rust";
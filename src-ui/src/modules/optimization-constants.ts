@@ -55,28 +55,6 @@ export interface OptimizationDefaults {
   input_source: string;
 }
 
-export interface OptimizationLimits {
-  num_filters: { min: number; max: number };
-  sample_rate: { min: number; max: number };
-  min_db: { min: number; max: number };
-  max_db: { min: number; max: number };
-  min_q: { min: number; max: number };
-  max_q: { min: number; max: number };
-  min_freq: { min: number; max: number };
-  max_freq: { min: number; max: number };
-  population: { min: number; max: number };
-  maxeval: { min: number; max: number };
-  de_f: { min: number; max: number };
-  de_cr: { min: number; max: number };
-  adaptive_weight_f: { min: number; max: number };
-  adaptive_weight_cr: { min: number; max: number };
-  min_spacing_oct: { min: number; max: number };
-  spacing_weight: { min: number; max: number };
-  tolerance: { min: number; max: number };
-  abs_tolerance: { min: number; max: number };
-  smooth_n: { min: number; max: number };
-}
-
 export interface OptimizationSteps {
   num_filters: number;
   sample_rate: number;
@@ -155,29 +133,6 @@ export const OPTIMIZATION_DEFAULTS: OptimizationDefaults = {
   input_source: "file",
 };
 
-// Minimum and maximum limits for parameters
-export const OPTIMIZATION_LIMITS: OptimizationLimits = {
-  num_filters: { min: 1, max: 20 },
-  sample_rate: { min: 8000, max: 192000 },
-  min_db: { min: 0.1, max: 25 },
-  max_db: { min: 0.1, max: 25 },
-  min_q: { min: 0.1, max: 10 },
-  max_q: { min: 0.1, max: 10 },
-  min_freq: { min: 20, max: 20000 },
-  max_freq: { min: 20, max: 20000 },
-  population: { min: 10, max: 10000 },
-  maxeval: { min: 200, max: 1000000 },
-  de_f: { min: 0.0, max: 2.0 },
-  de_cr: { min: 0.0, max: 1.0 },
-  adaptive_weight_f: { min: 0.1, max: 1.0 },
-  adaptive_weight_cr: { min: 0.1, max: 1.0 },
-  min_spacing_oct: { min: 0.01, max: 10.0 },
-  spacing_weight: { min: 0.0, max: 1000.0 },
-  tolerance: { min: 1e-15, max: 1.0 },
-  abs_tolerance: { min: 1e-15, max: 1.0 },
-  smooth_n: { min: 1, max: 24 },
-};
-
 // Step sizes for input controls
 export const OPTIMIZATION_STEPS: OptimizationSteps = {
   num_filters: 1,
@@ -201,31 +156,6 @@ export const OPTIMIZATION_STEPS: OptimizationSteps = {
   smooth_n: 1,
 };
 
-// Algorithm options
-export const ALGORITHM_OPTIONS = {
-  "autoeq:de": "Auto DE (Recommended)",
-  "nlopt:isres": "NLOPT ISRES",
-  "nlopt:ags": "NLOPT AGS",
-  "nlopt:origdirect": "NLOPT Original DIRECT",
-  "nlopt:crs2lm": "NLOPT CRS2 LM",
-  "nlopt:direct": "NLOPT DIRECT",
-  "nlopt:directl": "NLOPT DIRECT-L",
-  "nlopt:gmlsl": "NLOPT GMLSL",
-  "nlopt:gmlsllds": "NLOPT GMLSL LDS",
-  "nlopt:stogo": "NLOPT StoGO",
-  "nlopt:stogorand": "NLOPT StoGO Rand",
-  "nlopt:cobyla": "NLOPT COBYLA",
-  "nlopt:bobyqa": "NLOPT BOBYQA",
-  "nlopt:neldermead": "NLOPT Nelder-Mead",
-  "nlopt:sbplx": "NLOPT Subplex",
-  "nlopt:slsqp": "NLOPT SLSQP",
-  "mh:de": "MH Differential Evolution",
-  "mh:pso": "MH Particle Swarm",
-  "mh:rga": "MH Genetic Algorithm",
-  "mh:tlbo": "MH TLBO",
-  "mh:firefly": "MH Firefly",
-};
-
 // DE Strategy options
 export const DE_STRATEGY_OPTIONS = {
   currenttobest1bin: "Current-to-Best/1/Bin (Recommended)",
@@ -244,26 +174,6 @@ export const DE_STRATEGY_OPTIONS = {
   adaptiveexp: "Adaptive/Exp (Experimental)",
 };
 
-// Loss function options
-export const LOSS_OPTIONS = {
-  "speaker-flat": "Speaker Flat",
-  "speaker-score": "Speaker Score",
-  "headphone-flat": "Headphone Flat",
-  "headphone-score": "Headphone Score",
-};
-
-// Speaker-specific loss options
-export const SPEAKER_LOSS_OPTIONS = {
-  "speaker-flat": "Speaker Flat",
-  "speaker-score": "Speaker Score",
-};
-
-// Headphone-specific loss options
-export const HEADPHONE_LOSS_OPTIONS = {
-  "headphone-flat": "Headphone Flat",
-  "headphone-score": "Headphone Score",
-};
-
 // Curve name options
 export const CURVE_NAME_OPTIONS = {
   "Listening Window": "Listening Window",
@@ -293,13 +203,6 @@ export function getDefaultValue(paramName: keyof OptimizationDefaults): any {
   return OPTIMIZATION_DEFAULTS[paramName];
 }
 
-// Helper function to get limits for a parameter
-export function getLimits(
-  paramName: keyof OptimizationLimits,
-): { min: number; max: number } | undefined {
-  return OPTIMIZATION_LIMITS[paramName];
-}
-
 // Helper function to get step size for a parameter
 export function getStepSize(
   paramName: keyof OptimizationSteps,
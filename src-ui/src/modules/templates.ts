@@ -2,13 +2,12 @@
 // Replaces the static HTML templates with TypeScript-generated content
 
 import {
-  ALGORITHM_OPTIONS,
   DE_STRATEGY_OPTIONS,
-  LOSS_OPTIONS,
   CURVE_NAME_OPTIONS,
   LOCAL_ALGO_OPTIONS,
   WARNING_THRESHOLDS,
 } from "./optimization-constants";
+import type { ChoiceInfo } from "../types";
 
 // Helper function to generate option elements from a record of options
 function generateOptions(
@@ -23,51 +22,51 @@ function generateOptions(
     .join("\n");
 }
 
-// Helper function to group algorithms by category
-function generateAlgorithmOptions(): string {
-  const autoEQ: string[] = [];
-  const nloptGlobal: string[] = [];
-  const nloptLocal: string[] = [];
-  const metaheuristics: string[] = [];
-
-  Object.entries(ALGORITHM_OPTIONS).forEach(([value, label]) => {
-    if (value.startsWith("autoeq:")) {
-      autoEQ.push(
-        `                    <option value="${value}">${label}</option>`,
-      );
-    } else if (value.startsWith("nlopt:")) {
-      // Determine if it's global or local based on the algorithm name
-      const localAlgos = ["cobyla", "bobyqa", "neldermead", "sbplx", "slsqp"];
-      const algoName = value.split(":")[1];
-      if (localAlgos.includes(algoName)) {
-        nloptLocal.push(
-          `                    <option value="${value}">${label}</option>`,
-        );
-      } else {
-        nloptGlobal.push(
-          `                    <option value="${value}">${label}</option>`,
-        );
+// Option elements of backend choices, see get_optimization_choices
+export function generateChoiceOptions(
+  choices: ChoiceInfo[],
+  defaultValue?: string,
+): string {
+  return choices
+    .map(({ value, label, description }) => {
+      const selected = defaultValue === value ? " selected" : "";
+      return `                <option value="${value}" title="${description}"${selected}>${label}</option>`;
+    })
+    .join("\n");
+}
+
+// Algorithm choices grouped by category
+export function generateAlgorithmOptions(algorithms: ChoiceInfo[]): string {
+  const localAlgos = ["cobyla", "bobyqa", "neldermead", "sbplx", "slsqp"];
+  const groups: [string, (value: string) => boolean][] = [
+    ["AutoEQ Algorithms", (value) => value.startsWith("autoeq:")],
+    [
+      "NLOPT Global Optimizers",
+      (value) =>
+        value.startsWith("nlopt:") &&
+        !localAlgos.includes(value.split(":")[1]),
+    ],
+    [
+      "NLOPT Local Optimizers",
+      (value) =>
+        value.startsWith("nlopt:") && localAlgos.includes(value.split(":")[1]),
+    ],
+    ["Metaheuristics", (value) => value.startsWith("mh:")],
+    ["Instant", (value) => value.startsWith("greedy:")],
+  ];
+
+  return groups
+    .map(([label, inGroup]) => {
+      const options = algorithms.filter((choice) => inGroup(choice.value));
+      if (options.length === 0) {
+        return "";
       }
-    } else if (value.startsWith("mh:")) {
-      metaheuristics.push(
-        `                    <option value="${value}">${label}</option>`,
-      );
-    }
-  });
-
-  return `
-                <optgroup label="AutoEQ Algorithms">
-${autoEQ.join("\n")}
-                </optgroup>
-                <optgroup label="NLOPT Global Optimizers">
-${nloptGlobal.join("\n")}
-                </optgroup>
-                <optgroup label="NLOPT Local Optimizers">
-${nloptLocal.join("\n")}
-                </optgroup>
-                <optgroup label="Metaheuristics">
-${metaheuristics.join("\n")}
+      return `
+                <optgroup label="${label}">
+${generateChoiceOptions(options)}
                 </optgroup>`;
+    })
+    .join("");
 }
 
 // Generate DE Strategy options
@@ -204,9 +203,8 @@ export function generateEQDesign(): string {
       <div class="inline-params">
 	<div class="inline-item">
           <label>Loss</label>
-          <select id="loss" name="loss">
-	    ${generateOptions(LOSS_OPTIONS, "speaker-flat")}
-          </select>
+          <!-- Filled from the backend choices -->
+          <select id="loss" name="loss"></select>
 	</div>
 	<div class="param-item">
           <label>Curve</label>
@@ -269,13 +267,8 @@ export function generateEQDesign(): string {
     <div class="param-group-section">
       <div class="param-item">
         <label>PEQ Model</label>
-        <select id="peq_model" name="peq_model">
-          <option value="pk">PK - All Peak Filters</option>
-          <option value="hp-pk">HP+PK - Highpass + Peaks</option>
-          <option value="hp-pk-lp">HP+PK+LP - Highpass + Peaks + Lowpass</option>
-          <option value="free-pk-free">Free+PK+Free - Flexible ends, peaks middle</option>
-          <option value="free">Free - All filters flexible</option>
-        </select>
+        <!-- Filled from the backend choices -->
+        <select id="peq_model" name="peq_model"></select>
       </div>
     </div>
 
@@ -311,7 +304,8 @@ export function generateOptimizationFineTuning(): string {
   <div class="param-grid">
     <div class="param-item">
       <label>Algorithm</label>
-      <select id="algo" name="algo">${generateAlgorithmOptions()}</select>
+      <!-- Filled from the backend choices -->
+      <select id="algo" name="algo"></select>
     </div>
   </div>
 
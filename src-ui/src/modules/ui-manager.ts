@@ -2,9 +2,13 @@
 
 import {
   OPTIMIZATION_DEFAULTS,
-  OPTIMIZATION_LIMITS,
   OPTIMIZATION_STEPS,
 } from "./optimization-constants";
+import {
+  generateAlgorithmOptions,
+  generateChoiceOptions,
+} from "./templates";
+import type { ChoiceInfo, OptimizationChoices } from "../types";
 import { CaptureModalManager } from "../../../src-audio-capture/src/capture-modal-manager";
 import { RoutingMatrix } from "../../../src-audio-player/src/audio-routing";
 import {
@@ -62,6 +66,7 @@ export class UIManager {
 
   // State
   private eqEnabled: boolean = true;
+  private lossChoices: ChoiceInfo[] = [];
   private isResizing: boolean = false;
   private startX: number = 0;
   private startWidth: number = 0;
@@ -842,52 +847,53 @@ export class UIManager {
     }
   }
 
+  // Fill the dropdowns of the typed parameters from the backend choices
+  setOptimizationChoices(choices: OptimizationChoices): void {
+    this.lossChoices = choices.losses;
+
+    const algoSelect = document.getElementById("algo") as HTMLSelectElement;
+    if (algoSelect) {
+      algoSelect.innerHTML = generateAlgorithmOptions(choices.algorithms);
+      algoSelect.value = OPTIMIZATION_DEFAULTS.algo;
+    }
+    const peqModelSelect = document.getElementById(
+      "peq_model",
+    ) as HTMLSelectElement;
+    if (peqModelSelect) {
+      peqModelSelect.innerHTML = generateChoiceOptions(
+        choices.peq_models,
+        OPTIMIZATION_DEFAULTS.peq_model,
+      );
+    }
+    this.updateConditionalParameters();
+  }
+
   private updateLossOptions(
     inputType: string,
     lossSelect: HTMLSelectElement,
   ): void {
-    // Import loss options
-    import("./optimization-constants").then(
-      ({ LOSS_OPTIONS, SPEAKER_LOSS_OPTIONS, HEADPHONE_LOSS_OPTIONS }) => {
-        const currentValue = lossSelect.value;
-
-        // Clear existing options
-        lossSelect.innerHTML = "";
-
-        // Determine which options to use
-        let options;
-        let defaultValue;
-
-        if (inputType === "headphone") {
-          // Headphone: only headphone options
-          options = HEADPHONE_LOSS_OPTIONS;
-          defaultValue = "headphone-flat";
-        } else if (inputType === "speaker") {
-          // Speaker: only speaker options
-          options = SPEAKER_LOSS_OPTIONS;
-          defaultValue = "speaker-flat";
-        } else {
-          // File, Capture, or any other: show all 4 options
-          options = LOSS_OPTIONS;
-          defaultValue = "speaker-flat";
-        }
-
-        // Populate with appropriate options
-        Object.entries(options).forEach(([value, label]) => {
-          const option = document.createElement("option");
-          option.value = value;
-          option.textContent = label;
-          lossSelect.appendChild(option);
-        });
-
-        // Try to keep the current value if it's still valid, otherwise set default
-        if (lossSelect.querySelector(`option[value="${currentValue}"]`)) {
-          lossSelect.value = currentValue;
-        } else {
-          lossSelect.value = defaultValue;
-        }
-      },
+    const currentValue = lossSelect.value;
+
+    // Speakers and headphones only get their own losses, files and captures all
+    let prefix = "";
+    let defaultValue = "speaker-flat";
+    if (inputType === "headphone") {
+      prefix = "headphone-";
+      defaultValue = "headphone-flat";
+    } else if (inputType === "speaker") {
+      prefix = "speaker-";
+    }
+    const choices = this.lossChoices.filter((choice) =>
+      choice.value.startsWith(prefix),
     );
+    lossSelect.innerHTML = generateChoiceOptions(choices);
+
+    // Try to keep the current value if it's still valid, otherwise set default
+    if (lossSelect.querySelector(`option[value="${currentValue}"]`)) {
+      lossSelect.value = currentValue;
+    } else {
+      lossSelect.value = defaultValue;
+    }
   }
 
   // Event handlers (to be connected to main application logic)
@@ -0,0 +1,41 @@
+import { describe, it, expect } from "vitest";
+import {
+  generateAlgorithmOptions,
+  generateChoiceOptions,
+} from "../modules/templates";
+import type { ChoiceInfo } from "../types";
+
+const choice = (value: string): ChoiceInfo => ({
+  value,
+  label: value.toUpperCase(),
+  description: `About ${value}`,
+});
+
+describe("Backend choice dropdowns", () => {
+  it("should list every choice with its label and description", () => {
+    const html = generateChoiceOptions(
+      [choice("pk"), choice("hp-pk")],
+      "hp-pk",
+    );
+    expect(html).toContain('<option value="pk" title="About pk">PK</option>');
+    expect(html).toContain(
+      '<option value="hp-pk" title="About hp-pk" selected>HP-PK</option>',
+    );
+  });
+
+  it("should group algorithms and skip empty groups", () => {
+    const html = generateAlgorithmOptions([
+      choice("autoeq:de"),
+      choice("nlopt:isres"),
+      choice("nlopt:cobyla"),
+      choice("greedy:peaks"),
+    ]);
+    expect(html).toContain('<optgroup label="NLOPT Global Optimizers">');
+    expect(html).toContain('<optgroup label="NLOPT Local Optimizers">');
+    expect(html).toContain('<option value="greedy:peaks"');
+    expect(html).not.toContain("Metaheuristics");
+    expect(html.indexOf("nlopt:isres")).toBeLessThan(
+      html.indexOf("NLOPT Local Optimizers"),
+    );
+  });
+});
@@ -6,6 +6,9 @@ export type {
   OptimizationResult,
   ProgressData,
   OptimizationStage,
+  ValidationError,
+  ChoiceInfo,
+  OptimizationChoices,
 } from "./optimization";
 
 export type {
@@ -2,15 +2,20 @@
 
 export type PeqModel = "pk" | "hp-pk" | "hp-pk-lp" | "free-pk-free" | "free";
 
-export const PEQ_MODEL_DESCRIPTIONS: Record<PeqModel, string> = {
-  pk: "All filters are peak/bell filters",
-  "hp-pk": "First filter is highpass, rest are peak filters",
-  "hp-pk-lp":
-    "First filter is highpass, last is lowpass, rest are peak filters",
-  "free-pk-free":
-    "First and last filters can be any type, middle filters are peak",
-  free: "All filters can be any type",
-};
+// One dropdown entry, from get_optimization_choices
+export interface ChoiceInfo {
+  value: string;
+  label: string;
+  description: string;
+}
+
+// Valid values of the typed parameters, listed by the backend
+export interface OptimizationChoices {
+  losses: ChoiceInfo[];
+  algorithms: ChoiceInfo[];
+  peq_models: ChoiceInfo[];
+  aggregates: ChoiceInfo[];
+}
 
 export interface OptimizationParams {
   num_filters: number;
@@ -51,22 +56,157 @@ export interface OptimizationParams {
   // Captured curve data
   captured_frequencies?: number[];
   captured_magnitudes?: number[];
+  // Several measurements of the same device (reseats, mic positions), fitted through their aggregate
+  captured_measurements?: CapturedMeasurement[];
+  curve_aggregate?: CurveAggregate; // Default "mean"
   // Target curve data (for headphones)
   target_frequencies?: number[];
   target_magnitudes?: number[];
+  // Warm start from a previous result or an imported preset
+  initial_filters?: DecodedFilter[];
+  // Filters kept through the optimization, fixed or within a range
+  pinned_filters?: PinnedFilter[];
+  max_boost_db?: number; // Largest boost of the combined EQ, unlimited when omitted
+  device_profile?: DeviceProfile; // Hardware EQ the result must play on
+  // Flat losses only
+  loss_weighting?: WeightingCurve; // Weight of the error by frequency, uniform when omitted
+  boost_penalty?: number; // Extra cost of boosts over cuts, 0 = symmetric
+  frequency_grid?: FrequencyGrid; // Analysis and plot grid, 20 points per octave over 20-20000 Hz when omitted
+}
+
+export interface FrequencyGrid {
+  points_per_octave: number; // 1-96
+  min_freq: number; // Hz
+  max_freq: number; // Hz, up to 24000
+}
+
+export type CurveAggregate = "mean" | "median" | "worst-case";
+
+export interface CapturedMeasurement {
+  frequencies: number[];
+  magnitudes: number[];
+  weight?: number; // > 0, default 1
+}
+
+export interface WeightingCurve {
+  frequencies: number[]; // Hz, increasing
+  weights: number[]; // > 0, interpolated over log frequency
 }
 
 export interface PlotData {
   frequencies: number[];
-  curves: { [name: string]: number[] };
+  curves: { [name: string]: number[] }; // A missing value is null
   metadata: { [key: string]: any };
 }
 
+// Filter-count and PEQ-model sweep, see run_filter_sweep
+export interface SweepOptions {
+  min_filters: number;
+  max_filters: number;
+  peq_models: PeqModel[];
+  tolerance: number; // Relative loss tolerance, 0.05 = within 5% of the best
+}
+
+export interface SweepPoint {
+  num_filters: number;
+  peq_model: PeqModel;
+  objective_value?: number; // Final loss, lower is better
+  preference_score?: number;
+  residual_rms_db?: number;
+  error?: string;
+}
+
+export interface SweepResult {
+  points: SweepPoint[];
+  best?: number; // Index into points
+  recommended?: number; // Smallest configuration within tolerance
+  tolerance: number;
+  plot: PlotData; // x = filter count, one curve per PEQ model, null for failures
+}
+
+export interface BandMetrics {
+  min_freq: number;
+  max_freq: number;
+  points: number;
+  rms_db: number;
+  max_abs_db: number;
+}
+
+export interface ResidualMetrics {
+  overall: BandMetrics;
+  bass?: BandMetrics;
+  mid?: BandMetrics;
+  treble?: BandMetrics;
+  worst_fit: { freq: number; deviation_db: number }[];
+}
+
+export interface MeasurementSpread {
+  residual_rms_db: number[]; // One per measurement, in input order
+  worst_rms_db: number;
+  mean_std_db: number; // Std dev across measurements, mean over frequency
+  max_std_db: number; // Std dev across measurements, largest over frequency
+}
+
+// One failed check of validate_optimization_params
+export interface ValidationError {
+  field: string; // OptimizationParams field, e.g. "max_q" or "pinned_filters[1]"
+  min?: number; // Allowed range of the field, when it has one
+  max?: number;
+  message: string;
+}
+
+export interface DecodedFilter {
+  filter_type: string; // "peak", "highpass", "highpass_variable_q", "lowshelf", ...
+  frequency: number;
+  q: number;
+  gain: number;
+}
+
+export type ExportFormat = "camilladsp" | "parametric-eq" | "rew";
+
+// Limits of a hardware EQ, from list_device_profiles
+export interface DeviceProfile {
+  id: string;
+  name: string;
+  max_filters: number;
+  filter_types: string[];
+  min_gain: number;
+  max_gain: number;
+  gain_step?: number; // dB, continuous when omitted
+  min_q: number;
+  max_q: number;
+  q_values?: number[]; // Allowed Q values, continuous when omitted
+  frequencies?: number[]; // Band frequencies in Hz, continuous when omitted
+  export_format: ExportFormat;
+}
+
+export interface FilterRange {
+  min_freq: number;
+  max_freq: number;
+  min_q: number;
+  max_q: number;
+  min_gain: number;
+  max_gain: number;
+}
+
+export interface PinnedFilter extends DecodedFilter {
+  range?: FilterRange; // Fixed as given when omitted
+}
+
+export type FilterLock = "free" | "bounded" | "fixed";
+
 export interface OptimizationResult {
   success: boolean;
+  truncated?: boolean; // Time budget ran out, filters are the best found by then
   error_message?: string;
   filter_params?: number[];
+  filters?: DecodedFilter[]; // Filters decoded from filter_params
+  peq_model?: string;
+  filter_locks?: FilterLock[]; // How each of filters was optimized
+  preamp_db?: number; // Gain before the EQ that avoids clipping, <= 0
   objective_value?: number;
+  residual_metrics?: ResidualMetrics; // Deviation from the target after EQ
+  measurement_spread?: MeasurementSpread; // When captured_measurements were given
   preference_score_before?: number;
   preference_score_after?: number;
   filter_response?: PlotData;